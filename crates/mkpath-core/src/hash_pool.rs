@@ -7,6 +7,23 @@ use ahash::AHashMap;
 use crate::node::{Node, NodeAllocator, NodeMemberPointer, NodeRef};
 use crate::traits::NodePool;
 
+/// Node pool which deduplicates states using a hash map.
+///
+/// Memory use is proportional to the number of states generated, so this pool is suitable for
+/// sparse or unbounded state spaces.
+///
+/// ```
+/// # use mkpath_core::{HashPool, NodeBuilder};
+/// # use mkpath_core::traits::NodePool;
+/// let mut builder = NodeBuilder::new();
+/// let state = builder.add_field((0i32, 0i32));
+/// let pool = HashPool::new(builder.build(), state);
+///
+/// let a = pool.generate((3, 4));
+/// let b = pool.generate((3, 4));
+/// assert!(a.ptr_eq(b));
+/// assert!(pool.get(&(5, 6)).is_none());
+/// ```
 pub struct HashPool<S> {
     state_field: NodeMemberPointer<S>,
     allocator: NodeAllocator,
@@ -16,6 +33,10 @@ pub struct HashPool<S> {
 }
 
 impl<S: Copy + Hash + Eq + 'static> HashPool<S> {
+    /// Creates a pool which allocates nodes from `allocator`, storing states in `state_field`.
+    ///
+    /// # Panics
+    /// Panics if `state_field` is not a member of the allocator's node layout.
     #[track_caller]
     pub fn new(allocator: NodeAllocator, state_field: NodeMemberPointer<S>) -> Self {
         assert!(
@@ -29,6 +50,26 @@ impl<S: Copy + Hash + Eq + 'static> HashPool<S> {
         }
     }
 
+    /// Resets the pool, ensuring that at least `capacity` nodes can be generated before the pool
+    /// needs to request more memory.
+    pub fn reset_with_capacity(&mut self, capacity: usize) {
+        let map = self.map.get_mut();
+        map.clear();
+        map.reserve(capacity);
+        self.allocator.reset_with_capacity(capacity);
+    }
+
+    /// Resets the pool, releasing memory beyond what is needed to generate `capacity` nodes.
+    ///
+    /// See [`NodeAllocator::shrink_to`].
+    pub fn shrink_to(&mut self, capacity: usize) {
+        let map = self.map.get_mut();
+        map.clear();
+        map.shrink_to(capacity);
+        self.allocator.shrink_to(capacity);
+    }
+
+    /// Returns the node for `state` if it has been generated since the last reset.
     pub fn get(&self, state: &S) -> Option<NodeRef> {
         self.map
             .borrow()
@@ -3,6 +3,22 @@
 //! Core types and utilities for `mkpath`.
 //!
 //! This crate primarily provides the interface for working with nodes.
+//!
+//! ## Choosing a node pool
+//!
+//! A [`NodePool`](traits::NodePool) maps search states to nodes, so that generating the same
+//! state twice in one search yields the same node.
+//!
+//! | Pool | Lookup | Memory | Use for |
+//! |------|--------|--------|---------|
+//! | [`NullPool`] | none | generated nodes only | searches that never generate a state twice |
+//! | [`HashPool`] | hash map | generated nodes only | sparse or unbounded state spaces |
+//! | `GridPool` (`mkpath-grid`) | array | proportional to map size | dense grid maps |
+//!
+//! `NullPool` always allocates a fresh node, so it is only correct when the search space is a tree
+//! or duplicates are harmless. `HashPool` works for any `Hash + Eq` state and is a good default
+//! when searches only touch a small part of the state space. `GridPool` is the fastest option on
+//! grid maps when searches touch a substantial part of the map, at the cost of a per-cell table.
 
 mod node;
 mod pqueue;
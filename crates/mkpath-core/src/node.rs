@@ -42,6 +42,17 @@ pub struct NodeMemberPointer<T> {
     _marker: PhantomData<T>,
 }
 
+/// A group of consecutive fields of a node layout, which can be copied between nodes at once with
+/// [`NodeRef::copy_fields_from`].
+///
+/// Created by [`NodeBuilder::add_field_group`].
+#[derive(Clone, Copy, Debug)]
+pub struct NodeFieldGroup {
+    layout_id: LayoutId,
+    start: usize,
+    end: usize,
+}
+
 impl<T> Clone for NodeMemberPointer<T> {
     fn clone(&self) -> Self {
         *self
@@ -108,11 +119,18 @@ impl NodeBuilder {
         }
     }
 
+    /// Builds a node allocator for the layout.
     #[must_use]
     pub fn build(self) -> NodeAllocator {
         self.build_with_capacity(0)
     }
 
+    /// Builds a node allocator for the layout with space for `capacity` nodes preallocated.
+    ///
+    /// The allocator grows as needed, but growing in the middle of a search causes a latency spike.
+    /// If the number of nodes a search generates is known or can be bounded ahead of time (e.g.
+    /// the number of cells in a grid map), passing it here avoids this. The allocator keeps its
+    /// largest block of memory across resets, so only the first search(es) are affected.
     #[must_use]
     pub fn build_with_capacity(self, capacity: usize) -> NodeAllocator {
         let layout = self.layout.pad_to_align();
@@ -151,13 +169,84 @@ impl NodeBuilder {
             _marker: PhantomData,
         }
     }
+
+    /// Adds the fields added by `add` as a group, which can be copied from one node to another
+    /// with a single [`NodeRef::copy_fields_from`] instead of one get and set per field.
+    ///
+    /// Returns the result of `add`, which is typically the member pointers of the fields, along
+    /// with the group. The group may also be used to copy fields added by nested groups.
+    ///
+    /// ```
+    /// # use mkpath_core::NodeBuilder;
+    /// let mut builder = NodeBuilder::new();
+    /// let ((g, h), group) =
+    ///     builder.add_field_group(|builder| (builder.add_field(0.0), builder.add_field(0.0)));
+    /// let allocator = builder.build();
+    ///
+    /// let from = allocator.new_node();
+    /// from.set(g, 1.5);
+    /// from.set(h, 2.5);
+    /// let to = allocator.new_node();
+    /// to.copy_fields_from(from, group);
+    /// assert_eq!((to.get(g), to.get(h)), (1.5, 2.5));
+    /// ```
+    pub fn add_field_group<R>(
+        &mut self,
+        add: impl FnOnce(&mut NodeBuilder) -> R,
+    ) -> (R, NodeFieldGroup) {
+        // Fields are only ever appended, so everything added by `add` lies in this range, along
+        // with any padding between them, which is initialized from the default node as well.
+        let start = self.layout.size();
+        let result = add(self);
+        let group = NodeFieldGroup {
+            layout_id: self.layout_id,
+            start,
+            end: self.layout.size(),
+        };
+        (result, group)
+    }
 }
 
 impl NodeAllocator {
+    /// Frees all nodes allocated by this allocator.
     pub fn reset(&mut self) {
         self.arena.reset();
     }
 
+    /// Frees all nodes allocated by this allocator, and ensures that at least `capacity` nodes can
+    /// be allocated before the allocator needs to request more memory.
+    pub fn reset_with_capacity(&mut self, capacity: usize) {
+        self.arena.reset();
+        let bytes = capacity
+            .checked_mul(self.layout.size())
+            .expect("capacity overflow");
+        if self.arena.allocated_bytes() < bytes {
+            self.arena = Bump::with_capacity(bytes);
+        }
+    }
+
+    /// Frees all nodes allocated by this allocator, and releases memory beyond what is needed to
+    /// allocate `capacity` nodes.
+    ///
+    /// The allocator keeps its largest block of memory across resets, so a single large search
+    /// otherwise holds on to its memory for the lifetime of the allocator. This is useful for
+    /// long-running processes which mostly perform small searches, at the cost of having to
+    /// regrow the next time a large search is performed.
+    pub fn shrink_to(&mut self, capacity: usize) {
+        self.arena.reset();
+        let bytes = capacity
+            .checked_mul(self.layout.size())
+            .expect("capacity overflow");
+        if self.arena.allocated_bytes() > bytes {
+            self.arena = Bump::with_capacity(bytes);
+        }
+    }
+
+    /// Returns the number of bytes of memory currently held by the allocator.
+    pub fn allocated_bytes(&self) -> usize {
+        self.arena.allocated_bytes()
+    }
+
     /// Allocates a new node with the default value and returns a `NodeRef` to it.
     pub fn new_node(&self) -> NodeRef {
         let ptr = self.arena.alloc_layout(self.layout);
@@ -273,6 +362,30 @@ impl<'a> NodeRef<'a> {
         }
     }
 
+    /// Copies the fields in `group` from `other` to `self`.
+    ///
+    /// # Panics
+    /// Panics if the group is incompatible with `self` or `other`.
+    #[track_caller]
+    #[inline(always)]
+    pub fn copy_fields_from(self, other: NodeRef, group: NodeFieldGroup) {
+        self.check_layout(group.layout_id);
+        other.check_layout(group.layout_id);
+        if self.ptr_eq(other) {
+            return;
+        }
+        // SAFETY: Both nodes have the layout the group was created for, so the range of bytes
+        //         lies within each node, and holds only fields of `Copy` types and the padding
+        //         between them. The nodes are distinct, so the ranges don't overlap.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                other.ptr.as_ptr().cast::<u8>().add(group.start),
+                self.ptr.as_ptr().cast::<u8>().add(group.start),
+                group.end - group.start,
+            );
+        }
+    }
+
     /// Returns `true` if the two `NodeRef`s point at the same node.
     #[inline(always)]
     pub fn ptr_eq(self, other: NodeRef) -> bool {
@@ -314,3 +427,56 @@ impl<T: Copy> NodeMemberPointer<T> {
         self.layout_id
     }
 }
+
+#[test]
+fn shrink_releases_memory() {
+    let mut builder = NodeBuilder::new();
+    let field = builder.add_field(0u64);
+    let mut allocator = builder.build();
+
+    for i in 0..100_000 {
+        allocator.new_node().set(field, i);
+    }
+
+    // Resetting keeps the largest block, which holds most of the nodes.
+    allocator.reset();
+    let large = allocator.allocated_bytes();
+    assert!(large >= 50_000 * std::mem::size_of::<u64>());
+
+    allocator.shrink_to(100);
+    assert!(allocator.allocated_bytes() < large / 100);
+    let node = allocator.new_node();
+    assert_eq!(node.get(field), 0);
+}
+
+#[test]
+fn field_groups_copy_only_their_fields() {
+    let mut builder = NodeBuilder::new();
+    let before = builder.add_field(1u8);
+    let ((a, (b, c)), group) = builder.add_field_group(|builder| {
+        let a = builder.add_field(0u16);
+        let (b, _) = builder.add_field_group(|builder| builder.add_field(0.0f64));
+        (a, (b, builder.add_field([0u8; 3])))
+    });
+    let after = builder.add_field(2u32);
+    let allocator = builder.build();
+
+    let from = allocator.new_node();
+    from.set(before, 10);
+    from.set(a, 20);
+    from.set(b, 30.5);
+    from.set(c, [4, 5, 6]);
+    from.set(after, 70);
+
+    let to = allocator.new_node();
+    to.copy_fields_from(from, group);
+    assert_eq!((to.get(a), to.get(b), to.get(c)), (20, 30.5, [4, 5, 6]));
+    assert_eq!((to.get(before), to.get(after)), (1, 2));
+
+    to.copy_fields_from(to, group);
+    assert_eq!(to.get(b), 30.5);
+
+    let other = NodeBuilder::new().build();
+    let copy = || other.new_node().copy_fields_from(from, group);
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(copy)).is_err());
+}
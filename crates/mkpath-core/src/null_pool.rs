@@ -1,12 +1,33 @@
 use crate::node::{NodeAllocator, NodeMemberPointer, NodeRef};
 use crate::traits::NodePool;
 
+/// Node pool which does not deduplicate states.
+///
+/// Every call to `generate` allocates a new node, even if the state has been generated before.
+/// This is the cheapest possible pool, but is only suitable for searches which never generate the
+/// same state twice, or for which generating duplicate nodes is harmless.
+///
+/// ```
+/// # use mkpath_core::{NodeBuilder, NullPool};
+/// # use mkpath_core::traits::NodePool;
+/// let mut builder = NodeBuilder::new();
+/// let state = builder.add_field(0u32);
+/// let pool = NullPool::new(builder.build(), state);
+///
+/// let a = pool.generate(1);
+/// let b = pool.generate(1);
+/// assert!(!a.ptr_eq(b));
+/// ```
 pub struct NullPool<S: Copy> {
     state_field: NodeMemberPointer<S>,
     allocator: NodeAllocator,
 }
 
 impl<S: Copy + 'static> NullPool<S> {
+    /// Creates a pool which allocates nodes from `allocator`, storing states in `state_field`.
+    ///
+    /// # Panics
+    /// Panics if `state_field` is not a member of the allocator's node layout.
     #[track_caller]
     pub fn new(allocator: NodeAllocator, state_field: NodeMemberPointer<S>) -> Self {
         assert!(allocator.layout_id() == state_field.layout_id(), "mismatched layouts");
@@ -16,6 +37,20 @@ impl<S: Copy + 'static> NullPool<S> {
         }
     }
 
+    /// Resets the pool, ensuring that at least `capacity` nodes can be generated before the pool
+    /// needs to request more memory.
+    pub fn reset_with_capacity(&mut self, capacity: usize) {
+        self.allocator.reset_with_capacity(capacity);
+    }
+
+    /// Resets the pool, releasing memory beyond what is needed to generate `capacity` nodes.
+    ///
+    /// See [`NodeAllocator::shrink_to`].
+    pub fn shrink_to(&mut self, capacity: usize) {
+        self.allocator.shrink_to(capacity);
+    }
+
+    /// Always returns `None`, since this pool does not keep track of generated nodes.
     pub fn get(&self, _state: &S) -> Option<NodeRef> {
         None
     }
@@ -1,9 +1,74 @@
+use std::marker::PhantomData;
+
 use crate::NodeRef;
 
 pub trait Expander<'a> {
     type Edge: 'a;
 
     fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<Self::Edge>);
+
+    /// Returns the number of edges a single expansion usually produces at most, which searches
+    /// use as the initial capacity of their edge buffers.
+    ///
+    /// This is only a hint: expansions may produce more edges, which merely costs a reallocation.
+    /// The default is a generous constant.
+    fn max_degree(&self) -> usize {
+        16
+    }
+}
+
+/// An [`Expander`] defined by a closure, created by [`fn_expander`].
+///
+/// This is intended for prototyping successor generators (e.g. portals or one-way doors) without
+/// defining a type for them. The edge type is whatever the closure pushes, so edge traits such as
+/// [`Cost`] and [`EdgeId`] work as usual.
+pub struct FnExpander<F, E> {
+    f: F,
+    _marker: PhantomData<fn(&mut Vec<E>)>,
+}
+
+/// Creates an [`Expander`] which calls `f` to expand nodes.
+///
+/// ```
+/// # use mkpath_core::{NodeBuilder, NullPool};
+/// # use mkpath_core::traits::{fn_expander, Expander, NodePool, WeightedEdge};
+/// let mut builder = NodeBuilder::new();
+/// let state = builder.add_field(0u32);
+/// let pool = NullPool::new(builder.build(), state);
+///
+/// // Each number n has edges to 2n and 2n + 1.
+/// let mut expander = fn_expander(|node, edges: &mut Vec<WeightedEdge>| {
+///     let n = node.get(state);
+///     for successor in [2 * n, 2 * n + 1] {
+///         edges.push(WeightedEdge { successor: pool.generate(successor), cost: 1.0 });
+///     }
+/// });
+///
+/// let mut edges = vec![];
+/// expander.expand(pool.generate(3), &mut edges);
+/// assert_eq!(edges.iter().map(|e| e.successor.get(state)).collect::<Vec<_>>(), [6, 7]);
+/// ```
+pub fn fn_expander<'a, F, E>(f: F) -> FnExpander<F, E>
+where
+    F: FnMut(NodeRef<'a>, &mut Vec<E>),
+    E: 'a,
+{
+    FnExpander {
+        f,
+        _marker: PhantomData,
+    }
+}
+
+impl<'a, F, E> Expander<'a> for FnExpander<F, E>
+where
+    F: FnMut(NodeRef<'a>, &mut Vec<E>),
+    E: 'a,
+{
+    type Edge = E;
+
+    fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<E>) {
+        (self.f)(node, edges)
+    }
 }
 
 pub trait OpenList<'a> {
@@ -28,6 +93,84 @@ pub trait Cost {
     fn cost(&self) -> f64;
 }
 
+/// A numeric type in which searches store costs in node fields.
+///
+/// Edge costs and heuristic values are always computed as `f64`, and converted when stored.
+/// Storing them as `f32` halves the size of the cost fields, which makes nodes more cache
+/// friendly on large searches, at the cost of precision: `f32` represents integers exactly only
+/// up to 2^24, and path costs accumulate rounding error much faster than with `f64`. Costs which
+/// differ slightly may round to the same value, so tie detection may report ties which are not
+/// ties in exact arithmetic, and vice versa.
+///
+/// `i64` stores costs rounded to the nearest integer. With integer edge costs, such as the scaled
+/// octile costs of `mkpath_grid::Metric::SCALED_OCTILE`, every path cost is exact, so ties are
+/// detected exactly. `i64::MAX` and `i64::MIN` stand in for infinity and NaN.
+pub trait CostValue: Copy + PartialOrd + 'static {
+    /// The cost of an empty path.
+    const ZERO: Self;
+    /// The cost of nodes which have not been reached yet.
+    const INFINITY: Self;
+    /// A NaN value, used to mark heuristic values which have not been computed yet.
+    const NAN: Self;
+
+    /// Converts a cost computed in `f64`, rounding to the nearest representable value.
+    fn from_f64(value: f64) -> Self;
+
+    /// Converts the cost to `f64` for further arithmetic. This is exact, except for `i64`
+    /// costs beyond 2^53.
+    fn to_f64(self) -> f64;
+}
+
+impl CostValue for f64 {
+    const ZERO: Self = 0.0;
+    const INFINITY: Self = f64::INFINITY;
+    const NAN: Self = f64::NAN;
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl CostValue for f32 {
+    const ZERO: Self = 0.0;
+    const INFINITY: Self = f32::INFINITY;
+    const NAN: Self = f32::NAN;
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl CostValue for i64 {
+    const ZERO: Self = 0;
+    const INFINITY: Self = i64::MAX;
+    const NAN: Self = i64::MIN;
+
+    fn from_f64(value: f64) -> Self {
+        match value.is_nan() {
+            true => i64::MIN,
+            // Infinite values saturate to `i64::MAX`.
+            false => value.round() as i64,
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        match self {
+            i64::MAX => f64::INFINITY,
+            i64::MIN => f64::NAN,
+            _ => self as f64,
+        }
+    }
+}
+
 pub trait EdgeId {
     fn edge_id(&self) -> usize;
 }
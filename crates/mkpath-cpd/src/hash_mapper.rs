@@ -0,0 +1,188 @@
+use std::hash::Hash;
+
+use ahash::HashMap;
+use mkpath_core::traits::{Expander, Successor};
+use mkpath_core::{NodeMemberPointer, NodeRef};
+
+use crate::{dfs_traversal, StateIdMapper};
+
+/// Maps states to dense ids using a hash map.
+///
+/// Memory use is proportional to the number of mapped states rather than to the range of their
+/// coordinates, so this is suitable for sparse state spaces, where a dense mapper such as a grid
+/// of ids would mostly be empty. Ids are assigned in the order states are added, starting at 0.
+#[derive(Clone, Debug)]
+pub struct HashMapper<S> {
+    ids: HashMap<S, usize>,
+    states: Vec<S>,
+}
+
+impl<S: Copy + Hash + Eq> HashMapper<S> {
+    pub fn new() -> Self {
+        HashMapper {
+            ids: HashMap::default(),
+            states: vec![],
+        }
+    }
+
+    /// Returns the id of `state`, assigning it the next id if it doesn't have one yet.
+    pub fn insert(&mut self, state: S) -> usize {
+        *self.ids.entry(state).or_insert_with(|| {
+            self.states.push(state);
+            self.states.len() - 1
+        })
+    }
+
+    /// Returns the id of `state`, or `None` if it has not been added.
+    pub fn get(&self, state: S) -> Option<usize> {
+        self.ids.get(&state).copied()
+    }
+
+    /// Assigns ids to the states reachable from `start` which don't have one yet, in depth-first
+    /// preorder.
+    ///
+    /// Nearby states then tend to have nearby ids, which makes CPD rows compress better than an
+    /// arbitrary order does.
+    pub fn add_reachable<'a, E, Edge>(
+        &mut self,
+        start: NodeRef<'a>,
+        expander: E,
+        state: NodeMemberPointer<S>,
+    ) where
+        S: 'static,
+        E: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a>,
+    {
+        dfs_traversal(start, expander, |node| {
+            let state = node.get(state);
+            let new = !self.ids.contains_key(&state);
+            if new {
+                self.insert(state);
+            }
+            new
+        });
+    }
+}
+
+impl<S: Copy + Hash + Eq> Default for HashMapper<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Copy + Hash + Eq> FromIterator<S> for HashMapper<S> {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        let mut mapper = HashMapper::new();
+        for state in iter {
+            mapper.insert(state);
+        }
+        mapper
+    }
+}
+
+impl<S: Copy + Hash + Eq> StateIdMapper for HashMapper<S> {
+    type State = S;
+
+    fn num_ids(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Panics if `state` has not been added to the mapper.
+    #[track_caller]
+    fn state_to_id(&self, state: S) -> usize {
+        self.get(state)
+            .expect("state has not been added to the mapper")
+    }
+
+    fn id_to_state(&self, id: usize) -> S {
+        self.states[id]
+    }
+}
+
+#[test]
+fn cpd_over_sparse_states() {
+    use mkpath_core::traits::{fn_expander, Cost, EdgeId, NodePool};
+    use mkpath_core::{HashPool, NodeBuilder};
+
+    use crate::{BucketQueueFactory, CpdRow, FirstMoveSearcher};
+
+    struct RingEdge<'a> {
+        successor: NodeRef<'a>,
+        id: usize,
+    }
+
+    impl<'a> Successor<'a> for RingEdge<'a> {
+        fn successor(&self) -> NodeRef<'a> {
+            self.successor
+        }
+    }
+
+    impl Cost for RingEdge<'_> {
+        fn cost(&self) -> f64 {
+            1.0
+        }
+    }
+
+    impl EdgeId for RingEdge<'_> {
+        fn edge_id(&self) -> usize {
+            self.id
+        }
+    }
+
+    // A ring of 7 states spread over a huge coordinate range. Edge 0 steps forwards around the
+    // ring and edge 1 steps backwards.
+    const SPREAD: i64 = 1 << 40;
+    const LEN: i64 = 7;
+    fn expander<'a>(
+        pool: &'a HashPool<i64>,
+        state: NodeMemberPointer<i64>,
+    ) -> impl Expander<'a, Edge = RingEdge<'a>> {
+        fn_expander(move |node: NodeRef<'a>, edges: &mut Vec<RingEdge<'a>>| {
+            let s = node.get(state);
+            for (id, step) in [(0, 1), (1, LEN - 1)] {
+                let successor = pool.generate((s + step * SPREAD) % (LEN * SPREAD));
+                edges.push(RingEdge { successor, id });
+            }
+        })
+    }
+
+    let mut builder = NodeBuilder::new();
+    let state = builder.add_field(0i64);
+    let mut searcher = FirstMoveSearcher::new(&mut builder);
+    let pqueue = BucketQueueFactory::new(&mut builder);
+    let mut pool = HashPool::new(builder.build(), state);
+
+    let mut mapper = HashMapper::new();
+    mapper.add_reachable(pool.generate(3 * SPREAD), expander(&pool, state), state);
+    assert_eq!(mapper.num_ids(), LEN as usize);
+    assert_eq!(mapper.state_to_id(3 * SPREAD), 0);
+    assert_eq!(mapper.get(SPREAD / 2), None);
+    for id in 0..mapper.num_ids() {
+        assert_eq!(mapper.state_to_id(mapper.id_to_state(id)), id);
+    }
+
+    for source in 0..LEN {
+        pool.reset();
+        let g = searcher.g();
+        let row = CpdRow::compute(
+            &mapper,
+            &mut searcher,
+            expander(&pool, state),
+            pqueue.new_queue(g, 0.5),
+            pool.generate(source * SPREAD),
+            state,
+        );
+        for target in 0..LEN {
+            if target == source {
+                continue;
+            }
+            // The ring has odd length, so there are no ties.
+            let expected = match (target - source).rem_euclid(LEN) <= LEN / 2 {
+                true => 0,
+                false => 1,
+            };
+            let id = mapper.state_to_id(target * SPREAD);
+            assert_eq!(row.lookup(id), expected, "from {source} to {target}");
+        }
+    }
+}
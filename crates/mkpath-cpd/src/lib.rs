@@ -1,9 +1,14 @@
+use std::borrow::Cow;
 use std::collections::VecDeque;
 use std::io::{Read, Write};
 
-use mkpath_core::traits::{Cost, EdgeId, Expander, OpenList, Successor};
+use mkpath_core::traits::{Cost, CostValue, EdgeId, Expander, OpenList, Successor};
 use mkpath_core::{NodeBuilder, NodeMemberPointer, NodeRef};
 
+mod hash_mapper;
+
+pub use self::hash_mapper::*;
+
 pub trait StateIdMapper {
     type State;
 
@@ -38,25 +43,112 @@ pub fn dfs_traversal<'a, E: Expander<'a, Edge = Edge>, Edge: Successor<'a>>(
     }
 }
 
+/// Header at the start of a saved file, identifying the kind of data it holds and the version of
+/// the format it was written in.
+///
+/// Loaders check the header and reject files they don't understand with an
+/// [`InvalidData`](std::io::ErrorKind::InvalidData) error, instead of misreading them, so formats
+/// can gain fields without old files being silently corrupted. The header is two little-endian
+/// `u32`s, which keeps the data after it 4-byte aligned.
+///
+/// Rows saved with [`CpdRow::save`] have no header of their own, since they are stored inside
+/// files which do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatVersion {
+    /// Identifies the kind of file.
+    pub magic: u32,
+    /// Incremented whenever the format changes.
+    pub version: u32,
+}
+
+impl FormatVersion {
+    pub fn save(self, to: &mut impl Write) -> std::io::Result<()> {
+        to.write_all(&self.magic.to_le_bytes())?;
+        to.write_all(&self.version.to_le_bytes())
+    }
+
+    /// Reads a header, returning an error unless it is the same as this one.
+    pub fn check(self, from: &mut impl Read) -> std::io::Result<()> {
+        let mut bytes = [0; 4];
+        from.read_exact(&mut bytes)?;
+        let magic = u32::from_le_bytes(bytes);
+        from.read_exact(&mut bytes)?;
+        let version = u32::from_le_bytes(bytes);
+
+        let error = |message| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        if magic != self.magic {
+            Err(error(format!(
+                "unrecognized file: magic number is {magic:#010x}, expected {:#010x}",
+                self.magic
+            )))
+        } else if version != self.version {
+            Err(error(format!(
+                "unsupported format version {version}, expected {}",
+                self.version
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
 #[repr(transparent)]
 pub struct CpdRow {
     runs: [CpdEntry],
 }
 
+/// Entries of rows built by [`CpdRow::compress`] store the first id of the run in the low
+/// `START_BITS` bits and the edge id in the remaining high bits. Entries of rows built
+/// by [`CpdRow::compress_sets`] instead store the first id in the low `SET_START_BITS`
+/// bits and the set of edge ids in the high 8 bits.
 #[derive(Copy, Clone, Debug)]
+#[repr(transparent)]
 struct CpdEntry(u32);
 
 impl CpdEntry {
+    const START_BITS: u32 = 26;
+    const SET_START_BITS: u32 = 24;
+
+    fn new(start: usize, edge: usize) -> Self {
+        debug_assert!(start < CpdRow::MAX_IDS);
+        assert!(
+            edge <= CpdRow::MAX_EDGE_ID,
+            "edge id {edge} exceeds maximum supported value {}",
+            CpdRow::MAX_EDGE_ID
+        );
+        CpdEntry(start as u32 | (edge as u32) << Self::START_BITS)
+    }
+
+    fn new_set(start: usize, edge_set: u8) -> Self {
+        debug_assert!(start < CpdRow::MAX_SET_IDS);
+        CpdEntry(start as u32 | (edge_set as u32) << Self::SET_START_BITS)
+    }
+
     fn start(self) -> usize {
-        (self.0 & (1 << 26) - 1) as usize
+        (self.0 & ((1 << Self::START_BITS) - 1)) as usize
     }
 
     fn edge(self) -> usize {
-        (self.0 >> 26) as usize
+        (self.0 >> Self::START_BITS) as usize
+    }
+
+    fn set_start(self) -> usize {
+        (self.0 & ((1 << Self::SET_START_BITS) - 1)) as usize
+    }
+
+    fn edge_set(self) -> u8 {
+        (self.0 >> Self::SET_START_BITS) as u8
     }
 }
 
 impl CpdRow {
+    /// Number of ids supported by rows built by [`CpdRow::compress`]; ids must be below this.
+    pub const MAX_IDS: usize = 1 << CpdEntry::START_BITS;
+    /// Largest edge id which can be stored in rows built by [`CpdRow::compress`].
+    pub const MAX_EDGE_ID: usize = (1 << (32 - CpdEntry::START_BITS)) - 1;
+    /// Number of ids supported by rows built by [`CpdRow::compress_sets`]; ids must be below this.
+    pub const MAX_SET_IDS: usize = 1 << CpdEntry::SET_START_BITS;
+
     fn from_raw_box(slice: Box<[CpdEntry]>) -> Box<CpdRow> {
         unsafe {
             // SAFETY: `CpdRow` wraps a `[CpdEntry]` transparently, so this is safe
@@ -64,6 +156,13 @@ impl CpdRow {
         }
     }
 
+    fn from_raw_slice(slice: &[CpdEntry]) -> &CpdRow {
+        unsafe {
+            // SAFETY: `CpdRow` wraps a `[CpdEntry]` transparently, so this is safe
+            &*(slice as *const [CpdEntry] as *const CpdRow)
+        }
+    }
+
     pub fn compute<'a, M, S, Exp, Edge, Open>(
         mapper: &M,
         searcher: &mut FirstMoveSearcher,
@@ -79,7 +178,12 @@ impl CpdRow {
         Edge: Successor<'a> + Cost + EdgeId,
         Open: OpenList<'a>,
     {
-        assert!(mapper.num_ids() < 1 << 26);
+        assert!(
+            mapper.num_ids() <= Self::MAX_IDS,
+            "number of ids {} exceeds maximum supported value {}",
+            mapper.num_ids(),
+            Self::MAX_IDS
+        );
         let mut first_moves = vec![!0; mapper.num_ids()];
 
         searcher.search(start, expander, open, |node, fm| {
@@ -89,18 +193,37 @@ impl CpdRow {
         Self::compress(first_moves)
     }
 
+    /// Compresses a row from the set of first moves of each id, as a bitmask of edge ids, keeping
+    /// one first move per id.
+    ///
+    /// A set with all bits set is treated as a wildcard, as is done for unreachable ids.
+    ///
+    /// Panics if a set is empty, or if there are more than [`CpdRow::MAX_IDS`] ids. Since sets
+    /// are 64-bit masks, every edge id they can contain is at most [`CpdRow::MAX_EDGE_ID`].
     pub fn compress(first_move_bits: impl IntoIterator<Item = u64>) -> Box<CpdRow> {
         Self::compress_runs(first_move_bits.into_iter().enumerate())
     }
 
+    /// Compresses a row like [`CpdRow::compress`], but from `(id, first move set)` pairs in
+    /// increasing order of id. Ids which are skipped take on the first move of the id before them.
     pub fn compress_runs(first_move_bits: impl IntoIterator<Item = (usize, u64)>) -> Box<CpdRow> {
         let mut runs = vec![];
         let mut current_id = 0;
         let mut current_moves = !0;
         for (id, moves) in first_move_bits.into_iter().chain(Some((0, 0))) {
+            assert!(
+                id < Self::MAX_IDS,
+                "id {id} exceeds maximum supported value {}",
+                Self::MAX_IDS - 1
+            );
             if current_moves & moves == 0 {
-                runs.push(CpdEntry(current_id | current_moves.trailing_zeros() << 26));
-                current_id = id as u32;
+                assert!(
+                    current_moves != 0,
+                    "first move set of id {current_id} is empty"
+                );
+                let edge = current_moves.trailing_zeros() as usize;
+                runs.push(CpdEntry::new(current_id, edge));
+                current_id = id;
                 current_moves = moves;
             } else {
                 current_moves &= moves;
@@ -113,18 +236,118 @@ impl CpdRow {
         Self::from_raw_box(runs.into_boxed_slice())
     }
 
+    /// Compresses a row which keeps every first move of each id, rather than a single one.
+    ///
+    /// Each item is a set of edge ids as a bitmask, so only edge ids below 8 can be represented. A
+    /// set with all bits set is treated as a wildcard which may take on any set, as is done for
+    /// unreachable ids. Only consecutive ids with the same set are merged, so these rows compress
+    /// worse than those built by [`CpdRow::compress`]. Rows built this way support fewer ids
+    /// (below [`CpdRow::MAX_SET_IDS`]), and must be queried using [`CpdRow::lookup_set`].
+    pub fn compress_sets(first_move_sets: impl IntoIterator<Item = u8>) -> Box<CpdRow> {
+        let mut runs = vec![];
+        let mut current: Option<(usize, u8)> = None;
+        for (id, set) in first_move_sets.into_iter().enumerate() {
+            assert!(
+                id < Self::MAX_SET_IDS,
+                "id {id} exceeds maximum supported value {}",
+                Self::MAX_SET_IDS - 1
+            );
+            current = match current {
+                None => Some((id, set)),
+                Some((start, run_set)) if set == run_set || set == !0 => Some((start, run_set)),
+                Some((start, run_set)) if run_set == !0 => Some((start, set)),
+                Some((start, run_set)) => {
+                    runs.push(CpdEntry::new_set(start, run_set));
+                    Some((id, set))
+                }
+            };
+        }
+        if let Some((start, run_set)) = current {
+            runs.push(CpdEntry::new_set(start, run_set));
+        }
+
+        let sorted = runs.clone();
+        reorder_eytzinger(&mut sorted.into_iter(), &mut runs, 0);
+
+        Self::from_raw_box(runs.into_boxed_slice())
+    }
+
     pub fn len(&self) -> usize {
         self.runs.len()
     }
 
+    /// Reads every entry of the row, so that its memory is loaded.
+    ///
+    /// This is useful for rows borrowed from a memory-mapped file, whose pages are otherwise only
+    /// read from disk when first looked up, to keep that cost out of timed queries.
+    pub fn prefetch(&self) {
+        let checksum = self.runs.iter().fold(0, |acc, run| acc ^ run.0);
+        std::hint::black_box(checksum);
+    }
+
     pub fn lookup(&self, id: usize) -> usize {
+        self.find(id, CpdEntry::start).map_or(0, CpdEntry::edge)
+    }
+
+    /// Looks up the first move of every id in `0..num_ids`, in order.
+    ///
+    /// This walks the runs once rather than searching for each id, so it takes time linear in the
+    /// length of the row and `num_ids`. The result is the same as calling [`CpdRow::lookup`] on
+    /// each id.
+    pub fn decompress(&self, num_ids: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut runs = self.runs_in_order().peekable();
+        let mut edge = 0;
+        (0..num_ids).map(move |id| {
+            while let Some(run) = runs.next_if(|run| run.start() <= id) {
+                edge = run.edge();
+            }
+            edge
+        })
+    }
+
+    /// Iterates over the runs in order of their first id, i.e. an in-order traversal of the
+    /// Eytzinger layout.
+    fn runs_in_order(&self) -> impl Iterator<Item = CpdEntry> + '_ {
+        let n = self.runs.len();
+        let leftmost = move |mut i: usize| {
+            while 2 * i + 1 < n {
+                i = 2 * i + 1;
+            }
+            i
+        };
+        let mut next = (n > 0).then(|| leftmost(0));
+        std::iter::from_fn(move || {
+            let i = next?;
+            next = if 2 * i + 2 < n {
+                Some(leftmost(2 * i + 2))
+            } else {
+                // Climb until we leave a left subtree; its parent is next.
+                let mut j = i;
+                while j > 0 && j % 2 == 0 {
+                    j = (j - 1) / 2;
+                }
+                (j > 0).then(|| (j - 1) / 2)
+            };
+            Some(self.runs[i])
+        })
+    }
+
+    /// Looks up the set of first moves of an id in a row built by [`CpdRow::compress_sets`], as a
+    /// bitmask of edge ids.
+    pub fn lookup_set(&self, id: usize) -> u8 {
+        self.find(id, CpdEntry::set_start)
+            .map_or(0, CpdEntry::edge_set)
+    }
+
+    /// Finds the run containing `id`, using `start` to decode the first id of each run.
+    fn find(&self, id: usize, start: impl Fn(CpdEntry) -> usize) -> Option<CpdEntry> {
         let mut i = 0;
-        let mut result = 0;
+        let mut result = None;
         while i < self.runs.len() {
-            if id < self.runs[i].start() {
+            if id < start(self.runs[i]) {
                 i = 2 * i + 1;
             } else {
-                result = self.runs[i].edge();
+                result = Some(self.runs[i]);
                 i = 2 * i + 2;
             }
         }
@@ -151,22 +374,99 @@ impl CpdRow {
             .collect::<std::io::Result<_>>()?;
         Ok(Self::from_raw_box(rows))
     }
+
+    /// Reads a row in the format written by [`CpdRow::save`] from the start of `bytes`, advancing
+    /// `bytes` past it.
+    ///
+    /// The row is borrowed from `bytes` when possible, which requires a little-endian target and
+    /// the row data to be 4-byte aligned. Otherwise, the row is copied.
+    pub fn load_borrowed<'a>(bytes: &mut &'a [u8]) -> std::io::Result<Cow<'a, CpdRow>> {
+        let mut len = [0; 4];
+        bytes.read_exact(&mut len)?;
+        let size = (u32::from_le_bytes(len) as usize)
+            .checked_mul(4)
+            .filter(|&size| size <= bytes.len())
+            .ok_or(std::io::ErrorKind::UnexpectedEof)?;
+        let (data, rest) = bytes.split_at(size);
+        *bytes = rest;
+        Ok(Self::from_bytes_or_copy(data).unwrap())
+    }
+
+    /// Reinterprets the entries of a row as written by [`CpdRow::save`], excluding the length
+    /// prefix, as a row without copying.
+    ///
+    /// Returns `None` if the length of `bytes` is not a multiple of 4, if `bytes` is not 4-byte
+    /// aligned, or if the target is not little-endian.
+    pub fn from_bytes(bytes: &[u8]) -> Option<&CpdRow> {
+        if cfg!(target_endian = "big") || !bytes.len().is_multiple_of(4) {
+            return None;
+        }
+        // SAFETY: Every bit pattern is a valid `u32`.
+        let (prefix, entries, _) = unsafe { bytes.align_to::<u32>() };
+        if !prefix.is_empty() {
+            return None;
+        }
+        // SAFETY: `CpdEntry` wraps a `u32` transparently, and the entries are stored
+        //         little-endian, which is the native representation.
+        let entries = unsafe { &*(entries as *const [u32] as *const [CpdEntry]) };
+        Some(Self::from_raw_slice(entries))
+    }
+
+    /// Like [`CpdRow::from_bytes`], but copies the row if it can't be borrowed due to the
+    /// alignment of `bytes` or the endianness of the target.
+    ///
+    /// Returns `None` if the length of `bytes` is not a multiple of 4.
+    pub fn from_bytes_or_copy(bytes: &[u8]) -> Option<Cow<'_, CpdRow>> {
+        if !bytes.len().is_multiple_of(4) {
+            return None;
+        }
+        if let Some(row) = Self::from_bytes(bytes) {
+            return Some(Cow::Borrowed(row));
+        }
+        let entries = bytes
+            .chunks_exact(4)
+            .map(|entry| CpdEntry(u32::from_le_bytes(entry.try_into().unwrap())))
+            .collect();
+        Some(Cow::Owned(Self::from_raw_box(entries)))
+    }
+}
+
+impl ToOwned for CpdRow {
+    type Owned = Box<CpdRow>;
+
+    fn to_owned(&self) -> Box<CpdRow> {
+        Self::from_raw_box(self.runs.into())
+    }
 }
 
-pub struct FirstMoveSearcher {
+/// Dijkstra search which computes the set of optimal first moves from the start to every
+/// reachable node, storing `g` in node fields of type `C`.
+pub struct FirstMoveSearcher<C = f64> {
     first_move: NodeMemberPointer<u64>,
-    g: NodeMemberPointer<f64>,
+    g: NodeMemberPointer<C>,
 }
 
 impl FirstMoveSearcher {
     pub fn new(builder: &mut NodeBuilder) -> Self {
+        FirstMoveSearcher::with_cost_type(builder)
+    }
+}
+
+impl<C: CostValue> FirstMoveSearcher<C> {
+    /// Creates a searcher which stores costs as `C`, e.g. `FirstMoveSearcher::<f32>::with_cost_type`
+    /// for smaller nodes.
+    ///
+    /// Ties are detected by comparing stored costs exactly, so with `f32`, paths whose costs differ
+    /// by less than the rounding error may be treated as tied and contribute extra first moves, or
+    /// tied paths which accumulate different rounding errors may not be.
+    pub fn with_cost_type(builder: &mut NodeBuilder) -> Self {
         FirstMoveSearcher {
             first_move: builder.add_field(0),
-            g: builder.add_field(f64::INFINITY),
+            g: builder.add_field(C::INFINITY),
         }
     }
 
-    pub fn g(&self) -> NodeMemberPointer<f64> {
+    pub fn g(&self) -> NodeMemberPointer<C> {
         self.g
     }
 
@@ -183,9 +483,9 @@ impl FirstMoveSearcher {
     {
         let FirstMoveSearcher { first_move, g } = *self;
 
-        start.set(g, 0.0);
+        start.set(g, C::ZERO);
 
-        let mut edges = vec![];
+        let mut edges = Vec::with_capacity(expander.max_degree());
 
         // We need to handle expansion of the start node specially so that we can set the first
         // move set correctly.
@@ -197,7 +497,7 @@ impl FirstMoveSearcher {
                 edge_id < 63,
                 "edge id {edge_id} exceeds maximum supported value 62"
             );
-            node.set(g, edge.cost());
+            node.set(g, C::from_f64(edge.cost()));
             node.set(first_move, 1 << edge.edge_id());
             node.set_parent(Some(start));
             open.relaxed(node);
@@ -208,12 +508,12 @@ impl FirstMoveSearcher {
             edges.clear();
             expander.expand(node, &mut edges);
 
-            let node_g = node.get(g);
+            let node_g = node.get(g).to_f64();
             let node_first_move = node.get(first_move);
 
             for edge in &edges {
                 let successor = edge.successor();
-                let new_g = edge.cost() + node_g;
+                let new_g = C::from_f64(edge.cost() + node_g);
                 // TODO: think about floating point round-off error
                 if new_g < successor.get(g) {
                     // Shorter path to node; update g and first move field.
@@ -313,3 +613,88 @@ fn reorder_eytzinger(items: &mut impl Iterator<Item = CpdEntry>, into: &mut [Cpd
         reorder_eytzinger(items, into, 2 * k + 2);
     }
 }
+
+#[test]
+fn format_version_rejects_other_files() {
+    const FORMAT: FormatVersion = FormatVersion {
+        magic: 0x1234_5678,
+        version: 2,
+    };
+
+    let mut bytes = vec![];
+    FORMAT.save(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 8);
+    assert!(FORMAT.check(&mut &bytes[..]).is_ok());
+
+    let newer = FormatVersion {
+        version: 3,
+        ..FORMAT
+    };
+    let error = newer.check(&mut &bytes[..]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("version 2"));
+
+    let other = FormatVersion { magic: 1, ..FORMAT };
+    let error = other.check(&mut &bytes[..]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(FORMAT.check(&mut &bytes[..4]).is_err());
+}
+
+#[test]
+fn row_from_bytes() {
+    let moves = [1, 1, 3, 2, 2, 4, 4, 4, 8, 1];
+    let row = CpdRow::compress(moves);
+    let mut bytes = vec![];
+    row.save(&mut bytes).unwrap();
+
+    // Store the entries in `u32`s so that the buffer is aligned.
+    let words: Vec<u32> = bytes[4..]
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+        .collect();
+    // SAFETY: `words` holds exactly the entry bytes of `bytes`.
+    let aligned =
+        unsafe { std::slice::from_raw_parts(words.as_ptr().cast::<u8>(), bytes.len() - 4) };
+    let unaligned = [&[0][..], &bytes[4..]].concat();
+
+    let borrowed = CpdRow::from_bytes(aligned).unwrap();
+    assert!(CpdRow::from_bytes(&unaligned[1..]).is_none());
+    assert!(CpdRow::from_bytes(&aligned[1..]).is_none());
+    let copied = CpdRow::from_bytes_or_copy(&unaligned[1..]).unwrap();
+    assert!(matches!(copied, Cow::Owned(_)));
+
+    for (id, &moves) in moves.iter().enumerate() {
+        assert_ne!(moves & 1 << row.lookup(id), 0);
+        assert_eq!(borrowed.lookup(id), row.lookup(id));
+        assert_eq!(copied.lookup(id), row.lookup(id));
+    }
+}
+
+#[test]
+fn decompress_matches_lookup() {
+    for len in [0, 1, 2, 5, 17, 64] {
+        // Runs of varying length, so rows of many sizes are exercised.
+        let moves: Vec<u64> = (0..len).map(|id| 1 << (id * id / 7 % 5)).collect();
+        let row = CpdRow::compress(moves.iter().copied());
+        let expected: Vec<_> = (0..len).map(|id| row.lookup(id)).collect();
+        assert_eq!(row.decompress(len).collect::<Vec<_>>(), expected);
+        for (id, &fm) in expected.iter().enumerate() {
+            assert_eq!(1 << fm, moves[id]);
+        }
+    }
+}
+
+#[test]
+fn rejects_unrepresentable_rows() {
+    use std::panic::catch_unwind;
+
+    let row = CpdRow::compress([1 << CpdRow::MAX_EDGE_ID, 1 << 3, !0]);
+    assert_eq!(
+        row.decompress(3).collect::<Vec<_>>(),
+        [CpdRow::MAX_EDGE_ID, 3, 3]
+    );
+
+    assert!(catch_unwind(|| CpdRow::compress([1, 0, 1])).is_err());
+    assert!(catch_unwind(|| CpdRow::compress_runs([(0, 1), (CpdRow::MAX_IDS, 2)])).is_err());
+    assert!(catch_unwind(|| CpdRow::compress_sets((0..=CpdRow::MAX_SET_IDS).map(|_| 1))).is_err());
+}
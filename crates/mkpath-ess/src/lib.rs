@@ -1,13 +1,17 @@
 use std::ops::{IndexMut, Range};
 
-use mkpath_core::traits::{Expander, NodePool, Successor};
+use mkpath_core::traits::{Cost, Expander, NodePool, Successor};
 use mkpath_core::{NodeAllocator, NodeBuilder, NodeMemberPointer};
 
 pub trait ExplicitStateSpace {
     type State: Copy + 'static;
     type Auxiliary<T>: IndexMut<Self::State, Output = T>;
     type NodePool: NodePool<State = Self::State>;
-    type Expander<'a>: Expander<'a>
+    /// Expander for the state space. Edges must have a cost so that distances can be computed.
+    ///
+    /// The bounds on edges are stated here rather than where the expander is used, so that state
+    /// spaces borrowing their data don't have to be `'static`.
+    type Expander<'a>: Expander<'a, Edge: Successor<'a> + Cost>
     where
         Self: 'a;
 
@@ -37,10 +41,7 @@ pub struct Mapper<S: ExplicitStateSpace> {
 }
 
 impl<S: ExplicitStateSpace> Mapper<S> {
-    pub fn dfs_preorder(domain: &S) -> Self
-    where
-        for<'a> <S::Expander<'a> as Expander<'a>>::Edge: Successor<'a>,
-    {
+    pub fn dfs_preorder(domain: &S) -> Self {
         let states = domain.list_valid_states();
         let mut from_id = Vec::with_capacity(states.len());
         let mut to_id = domain.new_auxiliary(|_| usize::MAX);
@@ -128,6 +129,17 @@ impl<S: ExplicitStateSpace> Mapper<S> {
         start..end
     }
 
+    /// Returns the number of states in the component.
+    pub fn component_size(&self, component_id: usize) -> usize {
+        self.component_id_range(component_id).len()
+    }
+
+    /// Iterates over the states of the component, in id order.
+    pub fn component_states(&self, component_id: usize) -> impl Iterator<Item = S::State> + '_ {
+        self.component_id_range(component_id)
+            .map(|id| self.to_state(id))
+    }
+
     pub fn same_component(&self, s1: S::State, s2: S::State) -> bool {
         self.component_id_range(self.component_id(s1))
             .contains(&self.to_id(s2))
@@ -3,7 +3,8 @@ use std::sync::Mutex;
 use std::time::Duration;
 
 use enumset::EnumSet;
-use mkpath_grid::{BitGrid, Direction, Grid};
+use mkpath_cpd::FormatVersion;
+use mkpath_grid::{octile_distance, BitGrid, Direction, Grid, SAFE_SQRT_2};
 use mkpath_jps::JumpDatabase;
 
 use crate::first_move::FirstMoveComputer;
@@ -14,6 +15,25 @@ pub struct PartialCellBb {
     partial_bb: Grid<Option<[Rectangle; 8]>>,
 }
 
+/// How cells are assigned to the bounding boxes of a jump point.
+///
+/// A cell is added to the bounding box of one of its (tiebroken) first moves. When there are
+/// several, the choice affects how tight the boxes are, and so how much the boxes prune.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BbBuildStrategy {
+    /// Assign each cell, in the order first moves are computed, to the box whose area it increases
+    /// the least.
+    #[default]
+    Greedy,
+    /// Also try first assigning the cells with only a single first move, then the remaining cells
+    /// greedily, and keep whichever assignment has the smallest total area.
+    ///
+    /// Cells with several first moves are then often absorbed by boxes which must already cover
+    /// them. This takes a bit more time and memory to build.
+    MinAreaRetry,
+}
+
+#[derive(Clone, Copy)]
 struct Rectangle {
     low_x: i16,
     low_y: i16,
@@ -22,39 +42,66 @@ struct Rectangle {
 }
 
 impl PartialCellBb {
+    /// Header of files written by [`PartialCellBb::save`]. Files with any other header are
+    /// rejected when loading.
+    pub const CURRENT_VERSION: FormatVersion = FormatVersion {
+        magic: u32::from_le_bytes(*b"mkBB"),
+        version: 1,
+    };
+
     pub fn compute(
         map: &BitGrid,
         jump_db: &JumpDatabase,
         progress_callback: impl FnMut(usize, usize, Duration) + Send,
     ) -> Self {
-        let jump_points = independent_jump_points(map, jump_db);
+        Self::compute_with_strategy(map, jump_db, BbBuildStrategy::Greedy, progress_callback)
+    }
+
+    pub fn compute_with_strategy(
+        map: &BitGrid,
+        jump_db: &JumpDatabase,
+        strategy: BbBuildStrategy,
+        progress_callback: impl FnMut(usize, usize, Duration) + Send,
+    ) -> Self {
+        let jump_points = independent_jump_points(jump_db);
 
         let start = std::time::Instant::now();
         let num_jps = jump_points.len();
         let progress = Mutex::new((0, progress_callback));
 
-        let partial_bb = Mutex::new(Grid::new(map.width(), map.height(), |_, _| None));
+        let partial_bb = Mutex::new(Grid::new_default(map.width(), map.height()));
 
         parallel_for(
             jump_points.into_iter(),
-            || FirstMoveComputer::new(map),
-            |fm_computer, (source, jps)| {
+            || (FirstMoveComputer::new(map), vec![]),
+            |(fm_computer, cells), (source, jps)| {
                 let tiebreak_table =
                     compute_tiebreak_table(map.get_neighborhood(source.0, source.1), jps);
 
-                let mut result = [(); 8].map(|_| Rectangle::empty());
+                let mut result = [Rectangle::empty(); 8];
 
                 fm_computer.compute(source, |(x, y), fm| {
                     let fm = tiebreak_table[fm.as_usize()];
-                    let best = fm
-                        .iter()
-                        .min_by_key(|&d| {
-                            result[d as usize].area_increase_from_grow(x as i16, y as i16)
-                        })
-                        .unwrap();
-                    result[best as usize].grow(x as i16, y as i16);
+                    Rectangle::grow_best(&mut result, x as i16, y as i16, fm);
+                    if strategy == BbBuildStrategy::MinAreaRetry {
+                        cells.push((x as i16, y as i16, fm));
+                    }
                 });
 
+                if strategy == BbBuildStrategy::MinAreaRetry {
+                    let mut retry = [Rectangle::empty(); 8];
+                    for &(x, y, fm) in cells.iter().filter(|&&(_, _, fm)| fm.len() == 1) {
+                        Rectangle::grow_best(&mut retry, x, y, fm);
+                    }
+                    for &(x, y, fm) in cells.iter().filter(|&&(_, _, fm)| fm.len() > 1) {
+                        Rectangle::grow_best(&mut retry, x, y, fm);
+                    }
+                    if Rectangle::total_area(&retry) < Rectangle::total_area(&result) {
+                        result = retry;
+                    }
+                    cells.clear();
+                }
+
                 let mut progress = progress.lock().unwrap();
                 let (progress, callback) = &mut *progress;
                 *progress += 1;
@@ -72,6 +119,7 @@ impl PartialCellBb {
     }
 
     pub fn load(map: &BitGrid, from: &mut impl Read) -> std::io::Result<Self> {
+        Self::CURRENT_VERSION.check(from)?;
         let mut bytes = [0; 4];
         from.read_exact(&mut bytes)?;
         let num_jps = u32::from_le_bytes(bytes) as usize;
@@ -79,7 +127,7 @@ impl PartialCellBb {
         let mut bytes = [0; 2];
         let mut read_i16 = || from.read(&mut bytes).map(|_| i16::from_le_bytes(bytes));
 
-        let mut partial_bb = Grid::new(map.width(), map.height(), |_, _| None);
+        let mut partial_bb = Grid::new_default(map.width(), map.height());
         for _ in 0..num_jps {
             let x = read_i16()? as i32;
             let y = read_i16()? as i32;
@@ -89,7 +137,7 @@ impl PartialCellBb {
             assert!(x < map.width());
             assert!(y < map.height());
 
-            let mut result = [(); 8].map(|_| Rectangle::empty());
+            let mut result = [Rectangle::empty(); 8];
             for dir in 0..8 {
                 result[dir] = Rectangle {
                     low_x: read_i16()?,
@@ -105,6 +153,7 @@ impl PartialCellBb {
     }
 
     pub fn save(&self, to: &mut impl Write) -> std::io::Result<()> {
+        Self::CURRENT_VERSION.save(to)?;
         let num = self
             .partial_bb
             .storage()
@@ -146,6 +195,34 @@ impl PartialCellBb {
         }
         canonical
     }
+
+    /// Returns a lower bound on the cost of a path from `pos` to `target`, for use as an A*
+    /// heuristic.
+    ///
+    /// Every cell reachable from a jump point is in the bounding box of one of its optimal first
+    /// moves, so a path from a jump point must start with a move whose box contains `target`. The
+    /// bound is therefore the cheapest such move plus the octile distance from the cell it leads
+    /// to, which is never less than the octile distance, and is infinite if no box contains
+    /// `target` since it is then unreachable. For cells without bounding boxes, and when `pos` is
+    /// `target`, this is just the octile distance.
+    ///
+    /// Since the bound is only tightened at jump points, it is admissible but not consistent.
+    pub fn heuristic(&self, pos: (i32, i32), target: (i32, i32)) -> f64 {
+        let Some(rects) = &self.partial_bb[pos] else {
+            return octile_distance(pos, target);
+        };
+        if pos == target {
+            return 0.0;
+        }
+        Direction::ALL
+            .into_iter()
+            .filter(|&d| rects[d as usize].contains(target.0, target.1))
+            .map(|d| {
+                let step = if d.is_diagonal() { SAFE_SQRT_2 } else { 1.0 };
+                step + octile_distance(d.step(pos), target)
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
 }
 
 impl Rectangle {
@@ -176,6 +253,22 @@ impl Rectangle {
         }
     }
 
+    /// Grows the rectangle of the direction in `dirs` whose area increases the least.
+    fn grow_best(rects: &mut [Rectangle; 8], x: i16, y: i16, dirs: EnumSet<Direction>) {
+        let best = dirs
+            .iter()
+            .min_by_key(|&d| rects[d as usize].area_increase_from_grow(x, y))
+            .unwrap();
+        rects[best as usize].grow(x, y);
+    }
+
+    fn total_area(rects: &[Rectangle; 8]) -> i64 {
+        rects
+            .iter()
+            .map(|r| (r.high_x - r.low_x) as i64 * (r.high_y - r.low_y) as i64)
+            .sum()
+    }
+
     fn area_increase_from_grow(&self, x: i16, y: i16) -> i32 {
         if self.is_empty() {
             return 1;
@@ -194,3 +287,75 @@ impl Rectangle {
             && y < self.high_y as i32
     }
 }
+
+#[test]
+fn min_area_retry_is_tighter_and_optimal() {
+    use crate::verify_oracle;
+
+    let mut map = BitGrid::new(24, 16);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x % 6 == 3 && y % 5 != 2) || (y == 8 && x % 4 == 1) || (x == 17 && y > 10);
+            map.set(x, y, !wall);
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+
+    let greedy = PartialCellBb::compute(&map, &jump_db, |_, _, _| {});
+    let retry = PartialCellBb::compute_with_strategy(
+        &map,
+        &jump_db,
+        BbBuildStrategy::MinAreaRetry,
+        |_, _, _| {},
+    );
+    assert_eq!(verify_oracle(&map, &jump_db, &retry, 500, 3), Ok(()));
+
+    let cells = greedy.partial_bb.storage().iter();
+    for (greedy, retry) in cells.zip(retry.partial_bb.storage()) {
+        match (greedy, retry) {
+            (Some(greedy), Some(retry)) => {
+                assert!(Rectangle::total_area(retry) <= Rectangle::total_area(greedy))
+            }
+            (None, None) => {}
+            _ => panic!("strategies computed boxes for different cells"),
+        }
+    }
+}
+
+#[test]
+fn heuristic_is_admissible_and_detects_unreachable_targets() {
+    use mkpath_grid::exact_distance;
+
+    let mut map = BitGrid::new(20, 14);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x == 6 && y != 4) || (y == 9 && x > 9) || (x == 14 && y > 9);
+            map.set(x, y, !wall);
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let bb = PartialCellBb::compute(&map, &jump_db, |_, _, _| {});
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+    let (mut tighter, mut unreachable) = (0, 0);
+    for pos in independent_jump_points(&jump_db).into_keys() {
+        for &target in &cells {
+            let h = bb.heuristic(pos, target);
+            let exact = exact_distance(&map, pos, target);
+            let octile = octile_distance(pos, target);
+            assert!(h >= octile - 1e-9);
+            assert!(h <= exact + 1e-9, "{pos:?} to {target:?}");
+            if h.is_infinite() {
+                assert!(exact.is_infinite());
+                unreachable += 1;
+            } else if h > octile + 1e-9 {
+                tighter += 1;
+            }
+        }
+    }
+    assert!(tighter > 0);
+    assert!(unreachable > 0);
+}
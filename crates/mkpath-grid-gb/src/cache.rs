@@ -0,0 +1,187 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use mkpath_grid::BitGrid;
+use mkpath_jps::JumpDatabase;
+
+use crate::{PartialCellBb, PartialCellCpd};
+
+/// Stores preprocessed data next to the map file it was computed from.
+///
+/// Each kind of data is identified by a tag, and is stored at the map path with `.` and the tag
+/// appended (e.g. `maze.map.top+`). Stored data is considered stale if the map file has been
+/// modified since, in which case it is rebuilt. Data is written to a temporary file which is only
+/// moved into place once complete, so an interrupted build never leaves a truncated file behind.
+///
+/// Jump databases are cheap to compute, so they are not stored; compute them with
+/// [`JumpDatabase::new`].
+pub struct PreprocessCache {
+    map_path: PathBuf,
+}
+
+impl PreprocessCache {
+    /// Tag of the [`PartialCellCpd`] used by TOPS and Topping+.
+    pub const PARTIAL_CELL_CPD: &'static str = "top+";
+    /// Tag of the [`PartialCellBb`] used by JPS+BB+.
+    pub const PARTIAL_CELL_BB: &'static str = "bb+";
+
+    pub fn new(map_path: impl Into<PathBuf>) -> Self {
+        PreprocessCache {
+            map_path: map_path.into(),
+        }
+    }
+
+    pub fn map_path(&self) -> &Path {
+        &self.map_path
+    }
+
+    /// Returns the path that data with the given tag is stored at.
+    pub fn artifact_path(&self, tag: &str) -> PathBuf {
+        let mut path = self.map_path.clone();
+        path.as_mut_os_string().push(".");
+        path.as_mut_os_string().push(tag);
+        path
+    }
+
+    /// Returns whether data with the given tag exists and is at least as new as the map file.
+    pub fn is_fresh(&self, tag: &str) -> std::io::Result<bool> {
+        let artifact = match std::fs::metadata(self.artifact_path(tag)) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let map = std::fs::metadata(&self.map_path)?;
+        Ok(artifact.modified()? >= map.modified()?)
+    }
+
+    /// Loads the data with the given tag using `load` if it is fresh, and otherwise builds it with
+    /// `build` before loading it.
+    pub fn load_or_build<T>(
+        &self,
+        tag: &str,
+        load: impl FnOnce(&mut BufReader<File>) -> std::io::Result<T>,
+        build: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+    ) -> std::io::Result<T> {
+        if !self.is_fresh(tag)? {
+            self.build(tag, build)?;
+        }
+        load(&mut BufReader::new(File::open(self.artifact_path(tag))?))
+    }
+
+    /// Builds the data with the given tag using `build`, replacing any stored data.
+    pub fn build(
+        &self,
+        tag: &str,
+        build: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let path = self.artifact_path(tag);
+        let mut partial = path.clone();
+        partial.as_mut_os_string().push(".partial");
+
+        let mut file = BufWriter::new(File::create(&partial)?);
+        let result = build(&mut file).and_then(|()| file.flush());
+        drop(file);
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+        std::fs::rename(partial, path)
+    }
+
+    /// Loads the [`PartialCellCpd`] for `map`, computing and storing it first if needed.
+    ///
+    /// `progress_callback` is only called if the CPD is computed.
+    pub fn partial_cell_cpd(
+        &self,
+        map: &BitGrid,
+        jump_db: &JumpDatabase,
+        progress_callback: impl FnMut(usize, usize, Duration) + Send,
+    ) -> std::io::Result<PartialCellCpd<'static>> {
+        self.load_or_build(
+            Self::PARTIAL_CELL_CPD,
+            |from| PartialCellCpd::load(map, from),
+            |to| PartialCellCpd::compute_to_file(map, jump_db, to, progress_callback),
+        )
+    }
+
+    /// Loads the [`PartialCellBb`] for `map`, computing and storing it first if needed.
+    ///
+    /// `progress_callback` is only called if the bounding boxes are computed.
+    pub fn partial_cell_bb(
+        &self,
+        map: &BitGrid,
+        jump_db: &JumpDatabase,
+        progress_callback: impl FnMut(usize, usize, Duration) + Send,
+    ) -> std::io::Result<PartialCellBb> {
+        self.load_or_build(
+            Self::PARTIAL_CELL_BB,
+            |from| PartialCellBb::load(map, from),
+            |to| PartialCellBb::compute(map, jump_db, progress_callback).save(to),
+        )
+    }
+}
+
+#[test]
+fn builds_once_and_rebuilds_when_stale() {
+    use std::time::SystemTime;
+
+    let dir = std::env::temp_dir().join(format!("mkpath-cache-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let map_path = dir.join("test.map");
+    std::fs::write(&map_path, "placeholder").unwrap();
+
+    let mut map = BitGrid::new(12, 12);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x * 5 + y * 3) % 7 != 0);
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+
+    let cache = PreprocessCache::new(&map_path);
+    assert!(!cache.is_fresh(PreprocessCache::PARTIAL_CELL_CPD).unwrap());
+
+    let mut builds = 0;
+    let built = cache
+        .partial_cell_cpd(&map, &jump_db, |_, _, _| builds += 1)
+        .unwrap();
+    assert!(builds > 0);
+    assert!(cache.is_fresh(PreprocessCache::PARTIAL_CELL_CPD).unwrap());
+
+    let mut builds = 0;
+    let loaded = cache
+        .partial_cell_cpd(&map, &jump_db, |_, _, _| builds += 1)
+        .unwrap();
+    assert_eq!(builds, 0);
+    for (s, t) in [((0, 1), (11, 10)), ((4, 4), (9, 2))] {
+        assert_eq!(loaded.query(s, t), built.query(s, t));
+    }
+
+    // Modifying the map invalidates the stored data.
+    let later = SystemTime::now() + Duration::from_secs(60);
+    File::options()
+        .write(true)
+        .open(&map_path)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    assert!(!cache.is_fresh(PreprocessCache::PARTIAL_CELL_CPD).unwrap());
+
+    // Failed builds leave nothing behind.
+    let tag = "broken";
+    let result = cache.load_or_build(
+        tag,
+        |_| Ok(()),
+        |to| {
+            to.write_all(b"partial")?;
+            Err(ErrorKind::Interrupted.into())
+        },
+    );
+    assert!(result.is_err());
+    assert!(!cache.artifact_path(tag).exists());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,10 +1,11 @@
+use std::borrow::Cow;
 use std::io::{Read, Write};
 use std::sync::Mutex;
 use std::time::Duration;
 
 use ahash::HashMap;
 use enumset::EnumSet;
-use mkpath_cpd::{CpdRow, StateIdMapper};
+use mkpath_cpd::{CpdRow, FormatVersion, StateIdMapper};
 use mkpath_grid::{BitGrid, Direction, Grid};
 use mkpath_jps::JumpDatabase;
 
@@ -13,26 +14,122 @@ use crate::mapper::GridMapper;
 use crate::tiebreak::compute_tiebreak_table;
 use crate::{independent_jump_points, parallel_for};
 
-pub struct PartialCellCpd {
+/// Flag set in the jump point count of saved CPDs which store move sets.
+const MOVE_SETS_FLAG: u32 = 1 << 31;
+/// Flag set in the jump point count of saved CPDs which store rows keyed by arrival direction.
+/// Each entry of such a CPD has the arrival direction after its coordinates.
+const DIRECTIONAL_FLAG: u32 = 1 << 30;
+/// Arrival direction stored for entries which are not keyed by arrival direction.
+const NO_DIRECTION: u32 = u32::MAX;
+/// Stored in place of the x coordinate of an entry to mark the end of the entries. It is followed
+/// by the number of entries, as a check that none are missing.
+const END_MARKER: u32 = u32::MAX;
+
+/// Options controlling what a [`PartialCellCpd`] stores.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpdOptions {
+    /// Store every tied first move rather than a single one, so that
+    /// [`PartialCellCpd::query_set`] returns all of them.
+    ///
+    /// Runs of targets can only be merged when their move sets are the same, so the CPD is larger.
+    pub move_sets: bool,
+    /// Additionally store a row for each direction a jump point can be reached in, so that the
+    /// first move can be looked up by the direction of travel with [`PartialCellCpd::query_from`].
+    ///
+    /// Each row then only needs to break ties consistently with the canonical successors of a
+    /// single arrival direction rather than all of them. This is intended for domains where the
+    /// first move depends on the arrival direction, and makes the CPD several times larger.
+    pub directional: bool,
+    /// Only store rows for jump points inside the region `(x0, y0, x1, y1)`, where `x0` and `y0`
+    /// are inclusive and `x1` and `y1` are exclusive.
+    ///
+    /// First moves are still computed over the whole map, so the rows that are stored are the same
+    /// as without a region. Queries from cells outside the region return no moves, so that
+    /// [`TopsExpander`](crate::TopsExpander) and [`ToppingPlus`](crate::ToppingPlus) fall back to
+    /// searching with JPS there. The region is not saved, since it is implied by which rows exist.
+    pub bounds: Option<(i32, i32, i32, i32)>,
+}
+
+/// Result of [`PartialCellCpd::query_checked`], which tells apart the reasons for a query not
+/// giving a move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryResult {
+    /// The cell has no row, because it is not a jump point or is outside the region the CPD was
+    /// computed for.
+    NoRow,
+    /// The cell has a row, but it doesn't store a valid move towards the target.
+    NoMove,
+    /// The stored first move.
+    Move(Direction),
+}
+
+impl QueryResult {
+    /// Returns the move, if there is one, as [`PartialCellCpd::query`] does.
+    pub fn direction(self) -> Option<Direction> {
+        match self {
+            QueryResult::Move(dir) => Some(dir),
+            QueryResult::NoRow | QueryResult::NoMove => None,
+        }
+    }
+}
+
+pub struct PartialCellCpd<'a> {
     mapper: GridMapper,
-    partial_cpd: Grid<Option<Box<CpdRow>>>,
+    partial_cpd: Grid<Option<Cow<'a, CpdRow>>>,
+    directional_rows: HashMap<((i32, i32), Direction), Cow<'a, CpdRow>>,
+    options: CpdOptions,
 }
 
-impl PartialCellCpd {
+impl PartialCellCpd<'static> {
     pub fn compute(
         map: &BitGrid,
         jump_db: &JumpDatabase,
+        progress_callback: impl FnMut(usize, usize, Duration) + Send,
+    ) -> Self {
+        Self::compute_with_options(map, jump_db, CpdOptions::default(), progress_callback)
+    }
+
+    /// Computes a CPD which stores every tied first move rather than a single one, so that
+    /// [`PartialCellCpd::query_set`] returns all of them.
+    ///
+    /// Runs of targets can only be merged when their move sets are the same, so the CPD is larger
+    /// than one computed by [`PartialCellCpd::compute`].
+    pub fn compute_with_move_sets(
+        map: &BitGrid,
+        jump_db: &JumpDatabase,
+        progress_callback: impl FnMut(usize, usize, Duration) + Send,
+    ) -> Self {
+        let options = CpdOptions {
+            move_sets: true,
+            ..CpdOptions::default()
+        };
+        Self::compute_with_options(map, jump_db, options, progress_callback)
+    }
+
+    /// Computes a CPD storing the data selected by `options`.
+    pub fn compute_with_options(
+        map: &BitGrid,
+        jump_db: &JumpDatabase,
+        options: CpdOptions,
         mut progress_callback: impl FnMut(usize, usize, Duration) + Send,
     ) -> Self {
         let mapper = GridMapper::dfs_preorder(map);
-        let jump_points = independent_jump_points(map, jump_db);
-        let mut partial_cpd = Grid::new(map.width(), map.height(), |_, _| None);
+        let jump_points = options.jump_points(jump_db);
+        let mut partial_cpd: Grid<Option<Cow<CpdRow>>> =
+            Grid::new_default(map.width(), map.height());
+        let mut directional_rows = HashMap::default();
         Self::compute_impl(
             map,
             &mapper,
             jump_points,
-            |progress, total, time, source, result| {
-                partial_cpd[source] = Some(result);
+            options,
+            |progress, total, time, source, rows| {
+                for (arrival, row) in rows {
+                    match arrival {
+                        Some(dir) => directional_rows.insert((source, dir), Cow::Owned(row)),
+                        None => partial_cpd[source].replace(Cow::Owned(row)),
+                    };
+                }
                 progress_callback(progress, total, time);
                 Ok(())
             },
@@ -42,41 +139,106 @@ impl PartialCellCpd {
         PartialCellCpd {
             mapper,
             partial_cpd,
+            directional_rows,
+            options,
         }
     }
 
+    /// Computes the CPD like [`PartialCellCpd::compute`], writing it to `to` in the format of
+    /// [`PartialCellCpd::save`] rather than keeping it in memory.
+    ///
+    /// Rows are written and flushed as each jump point is finished, and the end of the data is
+    /// only marked once all of them have been written. If the build is interrupted, loading the
+    /// incomplete file fails with an [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) error
+    /// rather than producing a CPD with missing rows.
     pub fn compute_to_file(
         map: &BitGrid,
         jump_db: &JumpDatabase,
         to: &mut (impl Write + Send),
+        progress_callback: impl FnMut(usize, usize, Duration) + Send,
+    ) -> std::io::Result<()> {
+        Self::compute_to_file_with_options(
+            map,
+            jump_db,
+            CpdOptions::default(),
+            to,
+            progress_callback,
+        )
+    }
+
+    /// Like [`PartialCellCpd::compute_to_file`], but stores move sets as with
+    /// [`PartialCellCpd::compute_with_move_sets`].
+    pub fn compute_to_file_with_move_sets(
+        map: &BitGrid,
+        jump_db: &JumpDatabase,
+        to: &mut (impl Write + Send),
+        progress_callback: impl FnMut(usize, usize, Duration) + Send,
+    ) -> std::io::Result<()> {
+        let options = CpdOptions {
+            move_sets: true,
+            ..CpdOptions::default()
+        };
+        Self::compute_to_file_with_options(map, jump_db, options, to, progress_callback)
+    }
+
+    /// Like [`PartialCellCpd::compute_to_file`], but stores the data selected by `options`.
+    pub fn compute_to_file_with_options(
+        map: &BitGrid,
+        jump_db: &JumpDatabase,
+        options: CpdOptions,
+        to: &mut (impl Write + Send),
         mut progress_callback: impl FnMut(usize, usize, Duration) + Send,
     ) -> std::io::Result<()> {
         let mapper = GridMapper::dfs_preorder(map);
-        let jump_points = independent_jump_points(map, jump_db);
+        let jump_points = options.jump_points(jump_db);
+        Self::CURRENT_VERSION.save(to)?;
         mapper.save(to)?;
-        to.write_all(&u32::to_le_bytes(jump_points.len() as u32))?;
+        to.write_all(&options.flags().to_le_bytes())?;
+        let mut num_entries = 0u32;
         Self::compute_impl(
             map,
             &mapper,
             jump_points,
-            |progress, total, time, (x, y), result| {
-                to.write_all(&x.to_le_bytes())?;
-                to.write_all(&y.to_le_bytes())?;
-                result.save(to)?;
+            options,
+            |progress, total, time, (x, y), rows| {
+                num_entries += rows.len() as u32;
+                for (arrival, row) in rows {
+                    to.write_all(&x.to_le_bytes())?;
+                    to.write_all(&y.to_le_bytes())?;
+                    if options.directional {
+                        let arrival = arrival.map_or(NO_DIRECTION, |dir| dir as u32);
+                        to.write_all(&arrival.to_le_bytes())?;
+                    }
+                    row.save(to)?;
+                }
+                // Push each jump point's rows out as soon as they are done, so that an interrupted
+                // build leaves as much as possible behind.
+                to.flush()?;
                 progress_callback(progress, total, time);
                 Ok(())
             },
-        )
+        )?;
+        to.write_all(&END_MARKER.to_le_bytes())?;
+        to.write_all(&num_entries.to_le_bytes())?;
+        to.flush()
     }
 
     fn compute_impl<F>(
         map: &BitGrid,
         mapper: &GridMapper,
         jump_points: HashMap<(i32, i32), EnumSet<Direction>>,
+        options: CpdOptions,
         iter_done: F,
     ) -> std::io::Result<()>
     where
-        F: FnMut(usize, usize, Duration, (i32, i32), Box<CpdRow>) -> std::io::Result<()> + Send,
+        F: FnMut(
+                usize,
+                usize,
+                Duration,
+                (i32, i32),
+                Vec<(Option<Direction>, Box<CpdRow>)>,
+            ) -> std::io::Result<()>
+            + Send,
     {
         let start = std::time::Instant::now();
         let num_jps = jump_points.len();
@@ -89,32 +251,106 @@ impl PartialCellCpd {
                 let mut first_moves = vec![EnumSet::all(); mapper.num_ids()];
                 fm_computer.compute(source, |pos, fm| first_moves[mapper.state_to_id(pos)] = fm);
 
-                let tiebreak_table =
-                    compute_tiebreak_table(map.get_neighborhood(source.0, source.1), jps);
-                let result = CpdRow::compress(
-                    first_moves
-                        .into_iter()
-                        .map(|fm| tiebreak_table[fm.as_usize()].as_u64()),
-                );
+                let nb = map.get_neighborhood(source.0, source.1);
+                let compress = |tiebreak_table: [EnumSet<Direction>; 256]| {
+                    let first_moves = first_moves.iter().map(|fm| tiebreak_table[fm.as_usize()]);
+                    match options.move_sets {
+                        true => CpdRow::compress_sets(first_moves.map(|fm| fm.as_u8())),
+                        false => CpdRow::compress(first_moves.map(|fm| fm.as_u64())),
+                    }
+                };
+
+                let mut rows = vec![(None, compress(compute_tiebreak_table(nb, jps)))];
+                if options.directional {
+                    for dir in jps {
+                        let table = compute_tiebreak_table(nb, EnumSet::only(dir));
+                        rows.push((Some(dir), compress(table)));
+                    }
+                }
 
                 let mut progress = progress.lock().unwrap();
                 let (progress, callback) = &mut *progress;
                 *progress += 1;
-                callback(*progress, num_jps, start.elapsed(), source, result)
+                callback(*progress, num_jps, start.elapsed(), source, rows)
             },
         )
     }
 
+    /// Loads a CPD saved by [`PartialCellCpd::save`] or [`PartialCellCpd::compute_to_file`].
+    ///
+    /// Files which were cut short, e.g. by an interrupted build, are rejected with an
+    /// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) error.
     pub fn load(map: &BitGrid, from: &mut impl Read) -> std::io::Result<Self> {
+        Self::load_impl(map, from, |from| CpdRow::load(from).map(Cow::Owned))
+    }
+}
+
+impl CpdOptions {
+    fn jump_points(self, jump_db: &JumpDatabase) -> HashMap<(i32, i32), EnumSet<Direction>> {
+        let mut jump_points = independent_jump_points(jump_db);
+        if let Some((x0, y0, x1, y1)) = self.bounds {
+            jump_points.retain(|&(x, y), _| x >= x0 && y >= y0 && x < x1 && y < y1);
+        }
+        jump_points
+    }
+
+    fn flags(self) -> u32 {
+        let mut flags = 0;
+        if self.move_sets {
+            flags |= MOVE_SETS_FLAG;
+        }
+        if self.directional {
+            flags |= DIRECTIONAL_FLAG;
+        }
+        flags
+    }
+}
+
+impl<'a> PartialCellCpd<'a> {
+    /// Header of files written by [`PartialCellCpd::save`]. Files with any other header are
+    /// rejected when loading.
+    pub const CURRENT_VERSION: FormatVersion = FormatVersion {
+        magic: u32::from_le_bytes(*b"mkPC"),
+        version: 2,
+    };
+
+    /// Loads a CPD saved by [`PartialCellCpd::save`] from a byte buffer, such as a memory-mapped
+    /// file, borrowing the rows from the buffer instead of allocating each of them.
+    ///
+    /// The format is little-endian. Rows are borrowed only on little-endian targets, and only if
+    /// `bytes` is 4-byte aligned (as memory maps are, being page-aligned). Otherwise, they are
+    /// copied as with [`PartialCellCpd::load`].
+    pub fn load_mmap(map: &BitGrid, mut bytes: &'a [u8]) -> std::io::Result<Self> {
+        Self::load_impl(map, &mut bytes, CpdRow::load_borrowed)
+    }
+
+    fn load_impl<R: Read>(
+        map: &BitGrid,
+        from: &mut R,
+        mut load_row: impl FnMut(&mut R) -> std::io::Result<Cow<'a, CpdRow>>,
+    ) -> std::io::Result<Self> {
+        Self::CURRENT_VERSION.check(from)?;
         let mapper = GridMapper::load(from)?;
 
         let mut bytes = [0; 4];
         from.read_exact(&mut bytes)?;
-        let num_jps = u32::from_le_bytes(bytes) as usize;
+        let flags = u32::from_le_bytes(bytes);
+        let options = CpdOptions {
+            move_sets: flags & MOVE_SETS_FLAG != 0,
+            directional: flags & DIRECTIONAL_FLAG != 0,
+            bounds: None,
+        };
 
-        let mut partial_cpd = Grid::new(map.width(), map.height(), |_, _| None);
-        for _ in 0..num_jps {
+        let mut partial_cpd: Grid<Option<Cow<CpdRow>>> =
+            Grid::new_default(map.width(), map.height());
+        let mut directional_rows = HashMap::default();
+        let mut num_entries = 0u32;
+        let mut read_entries = || loop {
             from.read_exact(&mut bytes)?;
+            if u32::from_le_bytes(bytes) == END_MARKER {
+                from.read_exact(&mut bytes)?;
+                return Ok(u32::from_le_bytes(bytes));
+            }
             let x = i32::from_le_bytes(bytes);
             from.read_exact(&mut bytes)?;
             let y = i32::from_le_bytes(bytes);
@@ -124,40 +360,543 @@ impl PartialCellCpd {
             assert!(x < map.width());
             assert!(y < map.height());
 
-            partial_cpd[(x, y)] = Some(CpdRow::load(from)?);
+            let mut arrival = None;
+            if options.directional {
+                from.read_exact(&mut bytes)?;
+                let raw = u32::from_le_bytes(bytes);
+                if raw != NO_DIRECTION {
+                    arrival = Some(Direction::from_edge_id(raw as usize).ok_or_else(|| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "invalid arrival direction",
+                        )
+                    })?);
+                }
+            }
+
+            let row = load_row(from)?;
+            match arrival {
+                Some(dir) => directional_rows.insert(((x, y), dir), row),
+                None => partial_cpd[(x, y)].replace(row),
+            };
+            num_entries += 1;
+        };
+
+        // Files are written front to back as the CPD is computed, with the end marker last, so a
+        // file cut short means the build was interrupted.
+        let expected_entries =
+            read_entries().map_err(|error: std::io::Error| match error.kind() {
+                std::io::ErrorKind::UnexpectedEof => std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "CPD file is truncated after {num_entries} complete entries; the \
+                         build that wrote it was probably interrupted"
+                    ),
+                ),
+                _ => error,
+            })?;
+        if expected_entries != num_entries {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("CPD file has {num_entries} entries, but its end marker says {expected_entries}"),
+            ));
         }
 
         Ok(PartialCellCpd {
             mapper,
             partial_cpd,
+            directional_rows,
+            options,
         })
     }
 
     pub fn save(&self, to: &mut impl Write) -> std::io::Result<()> {
+        Self::CURRENT_VERSION.save(to)?;
         self.mapper.save(to)?;
-        let num_entries = self
-            .partial_cpd
-            .storage()
-            .iter()
-            .filter(|row| row.is_some())
-            .count();
-        to.write_all(&u32::to_le_bytes(num_entries as u32))?;
+        to.write_all(&self.options.flags().to_le_bytes())?;
+        let mut num_entries = 0u32;
         for y in 0..self.partial_cpd.height() {
             for x in 0..self.partial_cpd.width() {
                 let Some(row) = &self.partial_cpd[(x, y)] else {
                     continue;
                 };
-                to.write_all(&x.to_le_bytes())?;
-                to.write_all(&y.to_le_bytes())?;
-                row.save(to)?;
+                let arrivals = Direction::ALL.into_iter().map(Some);
+                for arrival in std::iter::once(None).chain(arrivals) {
+                    let row = match arrival {
+                        None => row,
+                        Some(dir) => match self.directional_rows.get(&((x, y), dir)) {
+                            Some(row) => row,
+                            None => continue,
+                        },
+                    };
+                    to.write_all(&x.to_le_bytes())?;
+                    to.write_all(&y.to_le_bytes())?;
+                    if self.options.directional {
+                        let arrival = arrival.map_or(NO_DIRECTION, |dir| dir as u32);
+                        to.write_all(&arrival.to_le_bytes())?;
+                    }
+                    row.save(to)?;
+                    num_entries += 1;
+                }
             }
         }
-        Ok(())
+        to.write_all(&END_MARKER.to_le_bytes())?;
+        to.write_all(&num_entries.to_le_bytes())
+    }
+
+    /// Reads all of the data of the CPD, so that it is loaded into memory before it is queried.
+    ///
+    /// Rows of CPDs loaded by [`PartialCellCpd::load_mmap`] are only read from disk when they are
+    /// first queried, which makes early queries much slower than later ones. Calling this first
+    /// keeps those page faults out of latency measurements.
+    pub fn prefetch(&self) {
+        self.mapper.prefetch();
+        for row in self.partial_cpd.storage().iter().flatten() {
+            row.prefetch();
+        }
+        for row in self.directional_rows.values() {
+            row.prefetch();
+        }
     }
 
+    /// Returns the first move towards `target` from `pos`, or `None` if there is none; see
+    /// [`PartialCellCpd::query_checked`] for why.
     pub fn query(&self, pos: (i32, i32), target: (i32, i32)) -> Option<Direction> {
-        self.partial_cpd[pos]
-            .as_ref()
-            .and_then(|row| row.lookup(self.mapper.state_to_id(target)).try_into().ok())
+        self.query_checked(pos, target).direction()
+    }
+
+    /// Like [`PartialCellCpd::query`], but tells apart cells without a row from rows without a
+    /// move towards `target`, for diagnosing gaps in the data.
+    pub fn query_checked(&self, pos: (i32, i32), target: (i32, i32)) -> QueryResult {
+        if !self.has_row(pos) {
+            return QueryResult::NoRow;
+        }
+        match self.query_set(pos, target).iter().next() {
+            Some(dir) => QueryResult::Move(dir),
+            None => QueryResult::NoMove,
+        }
+    }
+
+    /// Returns whether `pos` has a row of first-move data, i.e. whether it is a jump point for
+    /// which the CPD was computed.
+    ///
+    /// Rows keyed by arrival direction (see [`CpdOptions::directional`]) are only stored for cells
+    /// which also have a row for any arrival direction, so this covers them as well.
+    pub fn has_row(&self, pos: (i32, i32)) -> bool {
+        self.partial_cpd[pos].is_some()
+    }
+
+    /// Returns whether this CPD stores every tied first move, i.e. it was computed by
+    /// [`PartialCellCpd::compute_with_move_sets`].
+    pub fn has_move_sets(&self) -> bool {
+        self.options.move_sets
+    }
+
+    /// Returns whether this CPD stores rows keyed by arrival direction; see
+    /// [`CpdOptions::directional`].
+    pub fn is_directional(&self) -> bool {
+        self.options.directional
+    }
+
+    /// Returns the first moves towards `target` from `pos`, which is empty if `pos` has no
+    /// first-move data.
+    ///
+    /// If the CPD stores move sets, this is every optimal first move which is consistent with the
+    /// jump point tiebreaking rules (see [`compute_tiebreak_table`]). Otherwise, only the single
+    /// stored move is returned.
+    pub fn query_set(&self, pos: (i32, i32), target: (i32, i32)) -> EnumSet<Direction> {
+        self.query_set_from(pos, None, target)
+    }
+
+    /// Returns the first move towards `target` from `pos` when `pos` was reached travelling in
+    /// direction `arrival`.
+    ///
+    /// This uses the row for the arrival direction if the CPD is directional and has one, and
+    /// otherwise behaves like [`PartialCellCpd::query`].
+    pub fn query_from(
+        &self,
+        pos: (i32, i32),
+        arrival: Option<Direction>,
+        target: (i32, i32),
+    ) -> Option<Direction> {
+        self.query_set_from(pos, arrival, target).iter().next()
+    }
+
+    /// Returns the first moves towards `target` from `pos` when `pos` was reached travelling in
+    /// direction `arrival`; see [`PartialCellCpd::query_from`] and [`PartialCellCpd::query_set`].
+    pub fn query_set_from(
+        &self,
+        pos: (i32, i32),
+        arrival: Option<Direction>,
+        target: (i32, i32),
+    ) -> EnumSet<Direction> {
+        let row = arrival
+            .and_then(|dir| self.directional_rows.get(&(pos, dir)))
+            .or(self.partial_cpd[pos].as_ref());
+        row.map_or(EnumSet::empty(), |row| {
+            self.lookup(row, self.mapper.state_to_id(target))
+        })
+    }
+
+    fn lookup(&self, row: &CpdRow, id: usize) -> EnumSet<Direction> {
+        if self.options.move_sets {
+            EnumSet::from_u8(row.lookup_set(id))
+        } else {
+            Direction::from_edge_id(row.lookup(id)).map_or(EnumSet::empty(), EnumSet::only)
+        }
+    }
+
+    /// Returns the first move towards `target` from every cell, for visualization.
+    ///
+    /// Cells without first-move data (those which are not jump points) are `None`.
+    pub fn first_move_field(&self, target: (i32, i32)) -> Grid<Option<Direction>> {
+        let target = self.mapper.state_to_id(target);
+        let (width, height) = (self.partial_cpd.width(), self.partial_cpd.height());
+        Grid::new(width, height, |x, y| {
+            self.partial_cpd[(x, y)]
+                .as_ref()
+                .and_then(|row| self.lookup(row, target).iter().next())
+        })
+    }
+}
+
+/// Returns every `(pos, target, a_move, b_move)` for which the first moves stored by `a` and `b`
+/// differ, for comparing CPDs built with different tiebreaking rules.
+///
+/// Only positions with a row in both CPDs are compared, and only by their rows for any arrival
+/// direction, as used by [`PartialCellCpd::query`]. Pairs for which either CPD has no move, such
+/// as `pos == target`, are skipped. The differences are ordered by position, then by the id of the
+/// target.
+///
+/// Panics if `a` and `b` don't number the cells of the map the same way, as when they were
+/// computed for different maps.
+#[track_caller]
+pub fn cpd_diff<'c>(
+    a: &'c PartialCellCpd,
+    b: &'c PartialCellCpd,
+) -> impl Iterator<Item = ((i32, i32), (i32, i32), Direction, Direction)> + 'c {
+    let ids = a.mapper.num_ids();
+    assert!(
+        ids == b.mapper.num_ids()
+            && (0..ids).all(|id| a.mapper.id_to_state(id) == b.mapper.id_to_state(id)),
+        "CPDs must be computed for the same map"
+    );
+
+    let (width, height) = (a.partial_cpd.width(), a.partial_cpd.height());
+    (0..height)
+        .flat_map(move |y| (0..width).map(move |x| (x, y)))
+        .filter_map(|pos| {
+            let rows = (a.partial_cpd[pos].as_ref()?, b.partial_cpd[pos].as_ref()?);
+            Some((pos, rows))
+        })
+        .flat_map(move |(pos, (row_a, row_b))| {
+            (0..ids).filter_map(move |id| {
+                let move_a = a.lookup(row_a, id).iter().next()?;
+                let move_b = b.lookup(row_b, id).iter().next()?;
+                (move_a != move_b).then(|| (pos, a.mapper.id_to_state(id), move_a, move_b))
+            })
+        })
+}
+
+#[test]
+fn first_move_field_along_corridor() {
+    // A corridor along y = 1 with dead-end notches above every other cell, so that the corridor
+    // cells next to the notches are jump points.
+    let mut map = BitGrid::new(20, 2);
+    for x in 0..map.width() {
+        map.set(x, 0, x % 2 == 0);
+        map.set(x, 1, true);
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+
+    let field = cpd.first_move_field((0, 1));
+    let arrows: Vec<_> = (1..map.width()).filter_map(|x| field[(x, 1)]).collect();
+    assert!(!arrows.is_empty());
+    assert!(arrows.iter().all(|&dir| dir == Direction::West));
+}
+
+#[test]
+fn load_mmap_matches_load() {
+    let mut map = BitGrid::new(16, 16);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x * 7 + y * 3) % 5 != 0);
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+
+    let mut bytes = vec![];
+    cpd.save(&mut bytes).unwrap();
+
+    // Store the file in `u32`s so that the buffer is aligned and the rows are borrowed.
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+        .collect();
+    // SAFETY: `words` holds exactly the bytes of `bytes`, which is a whole number of words long.
+    let aligned = unsafe { std::slice::from_raw_parts(words.as_ptr().cast::<u8>(), bytes.len()) };
+
+    let loaded = PartialCellCpd::load(&map, &mut &bytes[..]).unwrap();
+    let mapped = PartialCellCpd::load_mmap(&map, aligned).unwrap();
+    let unaligned = [&[0][..], &bytes].concat();
+    let copied = PartialCellCpd::load_mmap(&map, &unaligned[1..]).unwrap();
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+    for &source in &cells {
+        for &target in &cells {
+            let expected = cpd.query(source, target);
+            assert_eq!(loaded.query(source, target), expected);
+            assert_eq!(mapped.query(source, target), expected);
+            assert_eq!(copied.query(source, target), expected);
+        }
+    }
+
+    assert!(PartialCellCpd::load_mmap(&map, &aligned[..aligned.len() - 1]).is_err());
+
+    // Files of other kinds are rejected by their header.
+    let mut bb = vec![];
+    let oracle = crate::PartialCellBb::compute(&map, &jump_db, |_, _, _| {});
+    oracle.save(&mut bb).unwrap();
+    let error = PartialCellCpd::load(&map, &mut &bb[..]).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn truncated_files_are_rejected() {
+    let map = BitGrid::random_obstacles(16, 16, 0.2, 5);
+    let jump_db = JumpDatabase::new(&map);
+    let mut bytes = vec![];
+    PartialCellCpd::compute_to_file(&map, &jump_db, &mut bytes, |_, _, _| {}).unwrap();
+
+    let cpd = PartialCellCpd::load(&map, &mut &bytes[..]).unwrap();
+    let mut saved = vec![];
+    cpd.save(&mut saved).unwrap();
+    assert_eq!(saved.len(), bytes.len());
+
+    // Cut the file at various points, including just before the end marker and its count.
+    for len in [
+        bytes.len() - 1,
+        bytes.len() - 4,
+        bytes.len() - 8,
+        bytes.len() / 2,
+        20,
+    ] {
+        let error = PartialCellCpd::load(&map, &mut &bytes[..len])
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.kind(),
+            std::io::ErrorKind::UnexpectedEof,
+            "cut at {len}"
+        );
+    }
+
+    // An end marker with the wrong count means entries were lost.
+    let mut wrong_count = bytes.clone();
+    let last = wrong_count.len() - 4;
+    wrong_count[last] ^= 1;
+    let error = PartialCellCpd::load(&map, &mut &wrong_count[..])
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn move_sets_contain_only_optimal_moves() {
+    use mkpath_grid::SAFE_SQRT_2;
+    use mkpath_jps::canonical_successors;
+
+    let mut map = BitGrid::new(16, 12);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, !(x % 5 == 2 && y % 4 != 1) && (x, y) != (8, 6));
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let single = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let sets = PartialCellCpd::compute_with_move_sets(&map, &jump_db, |_, _, _| {});
+    assert!(!single.has_move_sets());
+    assert!(sets.has_move_sets());
+
+    let mut bytes = vec![];
+    sets.save(&mut bytes).unwrap();
+    let loaded = PartialCellCpd::load(&map, &mut &bytes[..]).unwrap();
+    assert!(loaded.has_move_sets());
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+    let mut found_ties = false;
+    for &target in &cells {
+        // Dijkstra from the target, which gives distances to the target since moves are symmetric.
+        let mut dist = Grid::filled(map.width(), map.height(), f64::INFINITY);
+        let mut queue = vec![(0.0f64, target)];
+        dist[target] = 0.0;
+        while let Some(i) = (0..queue.len()).min_by(|&a, &b| queue[a].0.total_cmp(&queue[b].0)) {
+            let (d, (x, y)) = queue.swap_remove(i);
+            for dir in canonical_successors(map.get_neighborhood(x, y), None) {
+                let (dx, dy) = dir.vector();
+                let cost = if dx != 0 && dy != 0 { SAFE_SQRT_2 } else { 1.0 };
+                let next = (x + dx, y + dy);
+                if d + cost < dist[next] {
+                    dist[next] = d + cost;
+                    queue.push((d + cost, next));
+                }
+            }
+        }
+
+        for &pos in &cells {
+            if pos == target || single.query(pos, target).is_none() {
+                continue;
+            }
+            let moves = sets.query_set(pos, target);
+            assert_eq!(loaded.query_set(pos, target), moves);
+            assert!(moves.contains(single.query(pos, target).unwrap()));
+            assert_eq!(sets.query(pos, target), moves.iter().next());
+            found_ties |= moves.len() > 1;
+            for dir in moves {
+                let (dx, dy) = dir.vector();
+                let cost = if dx != 0 && dy != 0 { SAFE_SQRT_2 } else { 1.0 };
+                let via = cost + dist[(pos.0 + dx, pos.1 + dy)];
+                assert!((via - dist[pos]).abs() < 1e-9);
+            }
+        }
+    }
+    assert!(found_ties);
+}
+
+#[test]
+fn directional_rows_round_trip_and_stay_optimal() {
+    use crate::verify_oracle;
+
+    let mut map = BitGrid::new(20, 14);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(
+                x,
+                y,
+                !(x % 6 == 3 && y % 5 != 2) && (x * 3 + y * 7) % 11 != 0,
+            );
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let options = CpdOptions {
+        directional: true,
+        ..CpdOptions::default()
+    };
+    let plain = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let cpd = PartialCellCpd::compute_with_options(&map, &jump_db, options, |_, _, _| {});
+    assert!(cpd.is_directional() && !plain.is_directional());
+    assert!(!cpd.directional_rows.is_empty());
+    assert_eq!(verify_oracle(&map, &jump_db, &cpd, 500, 3), Ok(()));
+
+    let mut bytes = vec![];
+    cpd.save(&mut bytes).unwrap();
+    let loaded = PartialCellCpd::load(&map, &mut &bytes[..]).unwrap();
+    let mut streamed = vec![];
+    PartialCellCpd::compute_to_file_with_options(
+        &map,
+        &jump_db,
+        options,
+        &mut streamed,
+        |_, _, _| {},
+    )
+    .unwrap();
+    let streamed = PartialCellCpd::load(&map, &mut &streamed[..]).unwrap();
+    assert!(loaded.is_directional() && streamed.is_directional());
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+    for &pos in &cells {
+        for &target in &cells {
+            // Rows which aren't keyed by arrival direction are the same as in a plain CPD.
+            assert_eq!(cpd.query(pos, target), plain.query(pos, target));
+            for arrival in Direction::ALL.into_iter().map(Some).chain([None]) {
+                let expected = cpd.query_from(pos, arrival, target);
+                assert_eq!(loaded.query_from(pos, arrival, target), expected);
+                assert_eq!(streamed.query_from(pos, arrival, target), expected);
+                if arrival.is_some_and(|dir| !cpd.directional_rows.contains_key(&(pos, dir))) {
+                    assert_eq!(expected, cpd.query(pos, target));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn diff_finds_differing_first_moves() {
+    let mut map = BitGrid::new(16, 12);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x * 5 + y * 3) % 7 != 0);
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let sets = PartialCellCpd::compute_with_move_sets(&map, &jump_db, |_, _, _| {});
+    assert_eq!(cpd_diff(&cpd, &cpd).count(), 0);
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+    let mut expected = vec![];
+    for &pos in &cells {
+        for &target in &cells {
+            if let (Some(a), Some(b)) = (cpd.query(pos, target), sets.query(pos, target)) {
+                if a != b {
+                    expected.push((pos, target, a, b));
+                }
+            }
+        }
+    }
+    let mut diff: Vec<_> = cpd_diff(&cpd, &sets).collect();
+    diff.sort_unstable_by_key(|&(pos, target, _, _)| (pos.1, pos.0, target));
+    expected.sort_unstable_by_key(|&(pos, target, _, _)| (pos.1, pos.0, target));
+    assert_eq!(diff, expected);
+    assert!(!diff.is_empty());
+    // The single stored move is one of the tied moves, just not always the first of them.
+    for (pos, target, a, _) in diff {
+        assert!(sets.query_set(pos, target).contains(a));
+    }
+}
+
+#[test]
+fn every_jump_point_has_a_row() {
+    let map = BitGrid::random_obstacles(24, 20, 0.25, 17);
+    let jump_db = JumpDatabase::new(&map);
+    let options = CpdOptions {
+        bounds: Some((0, 0, 12, 20)),
+        ..CpdOptions::default()
+    };
+    let full = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let region = PartialCellCpd::compute_with_options(&map, &jump_db, options, |_, _, _| {});
+
+    let jump_points = independent_jump_points(&jump_db);
+    let target = (0..map.width())
+        .flat_map(|x| (0..map.height()).map(move |y| (x, y)))
+        .find(|&(x, y)| map.get(x, y))
+        .unwrap();
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let is_jump_point = jump_points.contains_key(&(x, y));
+            assert_eq!(full.has_row((x, y)), is_jump_point);
+            assert_eq!(region.has_row((x, y)), is_jump_point && x < 12);
+
+            let result = full.query_checked((x, y), target);
+            assert_eq!(result.direction(), full.query((x, y), target));
+            assert_eq!(result == QueryResult::NoRow, !is_jump_point);
+            if x >= 12 {
+                assert_eq!(region.query_checked((x, y), target), QueryResult::NoRow);
+            }
+        }
     }
 }
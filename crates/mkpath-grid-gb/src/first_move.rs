@@ -1,10 +1,21 @@
 use enumset::EnumSet;
-use mkpath_core::traits::{Expander, NodePool, OpenList};
+use mkpath_core::traits::{Expander, OpenList};
 use mkpath_core::{NodeBuilder, NodeMemberPointer};
 use mkpath_cpd::BucketQueueFactory;
-use mkpath_grid::{BitGrid, Direction, GridPool};
+use mkpath_grid::{BitGrid, Direction, GridEdge, GridPool};
 use mkpath_jps::{canonical_successors, CanonicalGridExpander};
 
+/// Computes the set of optimal first moves from a source to every reachable cell, using a
+/// Dijkstra search over canonical successors.
+///
+/// The recorded sets do not depend on the order in which the expander emits edges. Edges are at
+/// least as long as the bucket width of the open list, so every node is expanded only once its
+/// distance is final, and tied first moves and canonical successors are merged by set union,
+/// which is order-independent. The sets are therefore already canonical; picking a single
+/// representative move is left to the tiebreak table and [`CpdRow::compress`] when the rows are
+/// built.
+///
+/// [`CpdRow::compress`]: mkpath_cpd::CpdRow::compress
 pub struct FirstMoveComputer<'a> {
     map: &'a BitGrid,
     pool: GridPool,
@@ -36,10 +47,23 @@ impl<'a> FirstMoveComputer<'a> {
         }
     }
 
+    /// Calls `fm_cb` with each cell reachable from `source` and its set of optimal first moves,
+    /// in order of increasing distance.
     pub fn compute(
+        &mut self,
+        source: (i32, i32),
+        fm_cb: impl FnMut((i32, i32), EnumSet<Direction>),
+    ) {
+        self.compute_with_edge_order(source, fm_cb, |_| {})
+    }
+
+    /// Like [`FirstMoveComputer::compute`], but calls `reorder` on the edges of each expansion
+    /// before they are processed. Used to check that the result is independent of edge order.
+    fn compute_with_edge_order(
         &mut self,
         source: (i32, i32),
         mut fm_cb: impl FnMut((i32, i32), EnumSet<Direction>),
+        mut reorder: impl FnMut(&mut [GridEdge]),
     ) {
         let FirstMoveComputer {
             map,
@@ -60,6 +84,7 @@ impl<'a> FirstMoveComputer<'a> {
         start_node.set(g, 0.0);
 
         expander.expand(start_node, &mut edges);
+        reorder(&mut edges);
         for edge in &edges {
             let node = edge.successor;
             node.set(g, edge.cost);
@@ -79,6 +104,7 @@ impl<'a> FirstMoveComputer<'a> {
             unsafe {
                 expander.expand_unchecked(node, &mut edges, node.get(successors));
             }
+            reorder(&mut edges);
 
             let node_g = node.get(g);
             let node_first_move = node.get(first_move);
@@ -116,3 +142,54 @@ impl<'a> FirstMoveComputer<'a> {
         }
     }
 }
+
+#[test]
+fn first_moves_are_independent_of_edge_order() {
+    use mkpath_cpd::CpdRow;
+
+    let map = BitGrid::random_obstacles(24, 24, 0.25, 11);
+    let mut computer = FirstMoveComputer::new(&map);
+    let mut rng = 0x2545F4914F6CDD1Du64;
+    for (x, y) in [(0, 0), (5, 7), (12, 12), (23, 3), (17, 20)] {
+        if !map.get(x, y) {
+            continue;
+        }
+        let mut expected = vec![];
+        computer.compute((x, y), |pos, fm| expected.push((pos, fm)));
+
+        let mut reversed = vec![];
+        computer.compute_with_edge_order(
+            (x, y),
+            |pos, fm| reversed.push((pos, fm)),
+            |edges| edges.reverse(),
+        );
+
+        let mut shuffled = vec![];
+        let shuffle = |edges: &mut [GridEdge]| {
+            for i in (1..edges.len()).rev() {
+                rng ^= rng << 13;
+                rng ^= rng >> 7;
+                rng ^= rng << 17;
+                edges.swap(i, (rng % (i as u64 + 1)) as usize);
+            }
+        };
+        computer.compute_with_edge_order((x, y), |pos, fm| shuffled.push((pos, fm)), shuffle);
+
+        // Cells at equal distance may be reported in a different order, but the first-move sets,
+        // and hence the compressed rows, must be identical.
+        let row = |mut fms: Vec<((i32, i32), EnumSet<Direction>)>| {
+            fms.sort_unstable_by_key(|&(pos, _)| pos);
+            let mut row = vec![];
+            CpdRow::compress(fms.iter().map(|&(_, fm)| fm.as_u64()))
+                .save(&mut row)
+                .unwrap();
+            (fms, row)
+        };
+        let (expected, expected_row) = row(expected);
+        for other in [reversed, shuffled] {
+            let (other, other_row) = row(other);
+            assert_eq!(other, expected);
+            assert_eq!(other_row, expected_row);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::io::Write;
+
+use enumset::EnumSet;
+use mkpath_cpd::{CpdRow, FormatVersion, StateIdMapper};
+use mkpath_grid::{BitGrid, Direction};
+
+use crate::first_move::FirstMoveComputer;
+use crate::mapper::GridMapper;
+
+/// A CPD storing the first move from every traversable cell to every other, rather than only from
+/// jump points like [`PartialCellCpd`](crate::PartialCellCpd).
+///
+/// Computing it takes a Dijkstra search from every cell, and it stores a row for every cell, so it
+/// is only practical for small maps. In exchange, optimal paths can be followed one step at a time
+/// from anywhere without a search, and the whole table can be handed to other programs with
+/// [`FullCpd::export_flat`].
+pub struct FullCpd {
+    mapper: GridMapper,
+    rows: Box<[Box<CpdRow>]>,
+}
+
+impl FullCpd {
+    /// Magic number and version written by [`FullCpd::export_flat`].
+    pub const FLAT_VERSION: FormatVersion = FormatVersion {
+        magic: u32::from_le_bytes(*b"mkPF"),
+        version: 1,
+    };
+
+    pub fn compute(map: &BitGrid) -> Self {
+        let mapper = GridMapper::dfs_preorder(map);
+        let mut fm_computer = FirstMoveComputer::new(map);
+        let rows = (0..mapper.num_ids())
+            .map(|id| {
+                let mut first_moves = vec![EnumSet::all(); mapper.num_ids()];
+                fm_computer.compute(mapper.id_to_state(id), |pos, fm| {
+                    first_moves[mapper.state_to_id(pos)] = fm
+                });
+                CpdRow::compress(first_moves.iter().map(|fm| fm.as_u64()))
+            })
+            .collect();
+        FullCpd { mapper, rows }
+    }
+
+    /// Returns an optimal first move from `pos` towards `target`, or `None` if either is an
+    /// obstacle or they are the same cell.
+    ///
+    /// The result is arbitrary if `target` is traversable but can't be reached from `pos`.
+    ///
+    /// Panics if `pos` or `target` is outside the map.
+    #[track_caller]
+    pub fn query(&self, pos: (i32, i32), target: (i32, i32)) -> Option<Direction> {
+        let source = self.mapper.state_to_id(pos);
+        let target = self.mapper.state_to_id(target);
+        if source == usize::MAX || target == usize::MAX || source == target {
+            return None;
+        }
+        Direction::from_edge_id(self.rows[source].lookup(target))
+    }
+
+    /// Writes the CPD in a flat format meant to be read by other programs.
+    ///
+    /// Every value is 4 bytes and little-endian, and there is no padding. The data is, in order:
+    ///
+    /// - The header: the magic number `0x4650_6b6d` (the bytes `mkPF`) as a `u32`, then the
+    ///   format version as a `u32`, currently 1. See [`FullCpd::FLAT_VERSION`].
+    /// - The number of traversable cells `n` as a `u32`, then the width and height of the map as
+    ///   `i32`s.
+    /// - The id table: for each id from 0 to `n - 1`, the `x` and `y` coordinates of its cell as
+    ///   `i32`s.
+    /// - The rows: for each source id from 0 to `n - 1`, the number of runs `r` as a `u32`, then
+    ///   `r` run entries as `u32`s.
+    ///
+    /// Each run covers consecutive target ids which share a first move. The low 26 bits of an
+    /// entry are the first target id of the run and the high 6 bits are the move, numbered as in
+    /// [`Direction::ALL`]: 0 north, 1 west, 2 south, 3 east, 4 north-west, 5 south-west,
+    /// 6 south-east and 7 north-east. North is towards `y - 1`. The move towards a target is the
+    /// one of the run with the largest first id not greater than the target id.
+    ///
+    /// The runs of a row are not sorted; they are stored as a binary search tree in breadth-first
+    /// order, where the children of entry `i` are entries `2i + 1` and `2i + 2`. To look up
+    /// target id `t`, start with `i = 0` and, while `i < r`, go to `2i + 1` if `t` is less than
+    /// the first id of entry `i`, and otherwise remember entry `i` and go to `2i + 2`. The last
+    /// remembered entry holds the move. Entries for a source to itself and to cells it can't
+    /// reach hold arbitrary moves.
+    pub fn export_flat(&self, to: &mut impl Write) -> std::io::Result<()> {
+        Self::FLAT_VERSION.save(to)?;
+        self.mapper.save(to)?;
+        for row in self.rows.iter() {
+            row.save(to)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn moves_are_optimal_and_export_is_parseable() {
+    use mkpath_grid::{exact_distance, octile_distance};
+
+    let map = BitGrid::random_obstacles(12, 9, 0.25, 3);
+    let cpd = FullCpd::compute(&map);
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+
+    for &start in &cells {
+        for &target in &cells {
+            let optimal = exact_distance(&map, start, target);
+            if optimal.is_infinite() {
+                continue;
+            }
+            let mut pos = start;
+            let mut cost = 0.0;
+            while pos != target {
+                let dir = cpd.query(pos, target).unwrap();
+                let next = dir.step(pos);
+                assert!(map.get_neighborhood(pos.0, pos.1).contains(dir));
+                cost += octile_distance(pos, next);
+                pos = next;
+                assert!(cost < optimal + 1e-9, "{start:?} to {target:?}");
+            }
+            assert!((cost - optimal).abs() < 1e-9, "{start:?} to {target:?}");
+        }
+    }
+    assert_eq!(cpd.query(cells[0], cells[0]), None);
+    let obstacle = (0..map.width())
+        .map(|x| (x, 0))
+        .find(|&(x, y)| !map.get(x, y));
+    assert_eq!(cpd.query(cells[0], obstacle.unwrap()), None);
+
+    // Parse the export following only its documented layout.
+    let mut bytes = vec![];
+    cpd.export_flat(&mut bytes).unwrap();
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect();
+    assert_eq!(words.len() * 4, bytes.len());
+    assert_eq!(&bytes[..4], b"mkPF");
+    assert_eq!(words[1], 1);
+    let n = words[2] as usize;
+    assert_eq!(n, cells.len());
+    assert_eq!(
+        (words[3] as i32, words[4] as i32),
+        (map.width(), map.height())
+    );
+    let ids: Vec<_> = words[5..5 + 2 * n]
+        .chunks_exact(2)
+        .map(|xy| (xy[0] as i32, xy[1] as i32))
+        .collect();
+
+    let mut rest = &words[5 + 2 * n..];
+    for &source in &ids {
+        let (r, runs) = (rest[0] as usize, &rest[1..]);
+        let runs = &runs[..r];
+        rest = &rest[1 + r..];
+        for (t, &target) in ids.iter().enumerate() {
+            let mut i = 0;
+            let mut found = None;
+            while i < r {
+                if (t as u32) < runs[i] & ((1 << 26) - 1) {
+                    i = 2 * i + 1;
+                } else {
+                    found = Some(runs[i] >> 26);
+                    i = 2 * i + 2;
+                }
+            }
+            if let Some(dir) = cpd.query(source, target) {
+                assert_eq!(found, Some(dir as u32), "{source:?} to {target:?}");
+            }
+        }
+    }
+    assert!(rest.is_empty());
+}
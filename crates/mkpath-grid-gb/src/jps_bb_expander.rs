@@ -5,6 +5,12 @@ use mkpath_jps::{canonical_successors, JumpDatabase};
 
 use crate::PartialCellBb;
 
+/// JPS+BB expander, which prunes JPS+ successors using the bounding boxes stored in a
+/// [`PartialCellBb`].
+///
+/// This is [`PartialCellBb::filter`] applied to the canonical successors of each node, so it
+/// finds optimal paths with any admissible heuristic. Like [`TopsExpander`](crate::TopsExpander),
+/// the start and target need not be jump points; nodes without bounding boxes are not pruned.
 pub struct JpsBbExpander<'a, P> {
     node_pool: &'a P,
     map: &'a BitGrid,
@@ -15,14 +21,26 @@ pub struct JpsBbExpander<'a, P> {
 }
 
 impl<'a, P: GridNodePool> JpsBbExpander<'a, P> {
+    /// Creates an expander searching towards `target` on the map of `jump_db`.
+    ///
+    /// `oracle` must have been computed or loaded for the same map as `jump_db`.
+    ///
+    /// Panics if `jump_db` has no diagonal jump data, or if `node_pool` is smaller than the map.
     pub fn new(
-        map: &'a BitGrid,
         jump_db: &'a JumpDatabase,
         oracle: &'a PartialCellBb,
         node_pool: &'a P,
         state: NodeMemberPointer<(i32, i32)>,
         target: (i32, i32),
     ) -> Self {
+        let map = jump_db.map();
+
+        // Diagonal jumps are made using unchecked queries.
+        assert!(
+            jump_db.has_diagonals(),
+            "jump database must have diagonal jump data"
+        );
+
         // Establish invariant that coordinates in-bounds of the map are also in-bounds of the
         // node pool.
         assert!(
@@ -34,21 +52,6 @@ impl<'a, P: GridNodePool> JpsBbExpander<'a, P> {
             "node pool must be tall enough for the map"
         );
 
-        // Establish invariant that coordinates in-bounds of the map are in-bounds of the jump
-        // database, and vice-versa.
-        // We don't check that the content of the jump database is actually correct for the map
-        // since that's a) slow b) merely a logic error; not required for safety.
-        assert_eq!(
-            map.width(),
-            jump_db.width(),
-            "jump database has incorrect width"
-        );
-        assert_eq!(
-            map.height(),
-            jump_db.height(),
-            "jump database has incorrect height"
-        );
-
         JpsBbExpander {
             node_pool,
             map,
@@ -68,13 +71,8 @@ impl<'a, P: GridNodePool> JpsBbExpander<'a, P> {
         cost: f64,
         edges: &mut Vec<WeightedEdge<'a>>,
     ) {
-        let (dx, dy) = match dir {
-            Direction::North => (0, -1),
-            Direction::West => (-1, 0),
-            Direction::South => (0, 1),
-            Direction::East => (1, 0),
-            _ => unreachable!(),
-        };
+        debug_assert!(dir.orthogonal());
+        let (dx, dy) = dir.vector();
 
         if let Some(dist) = self.jump_db.ortho_jump_unchecked(x, y, dir, self.target) {
             edges.push(WeightedEdge {
@@ -94,11 +92,12 @@ impl<'a, P: GridNodePool> JpsBbExpander<'a, P> {
         dir: Direction,
         edges: &mut Vec<WeightedEdge<'a>>,
     ) {
-        let (dx, dy, dir_x, dir_y) = match dir {
-            Direction::NorthWest => (-1, -1, Direction::West, Direction::North),
-            Direction::SouthWest => (-1, 1, Direction::West, Direction::South),
-            Direction::SouthEast => (1, 1, Direction::East, Direction::South),
-            Direction::NorthEast => (1, -1, Direction::East, Direction::North),
+        let (dx, dy) = dir.vector();
+        let (dir_x, dir_y) = match dir {
+            Direction::NorthWest => (Direction::West, Direction::North),
+            Direction::SouthWest => (Direction::West, Direction::South),
+            Direction::SouthEast => (Direction::East, Direction::South),
+            Direction::NorthEast => (Direction::East, Direction::North),
             _ => unreachable!(),
         };
 
@@ -189,4 +188,8 @@ impl<'a, P: GridNodePool> Expander<'a> for JpsBbExpander<'a, P> {
             }
         }
     }
+
+    fn max_degree(&self) -> usize {
+        8
+    }
 }
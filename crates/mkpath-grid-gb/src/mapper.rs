@@ -1,7 +1,6 @@
 use std::io::{Read, Write};
 
 use mkpath_core::NodeBuilder;
-use mkpath_core::traits::NodePool;
 use mkpath_cpd::StateIdMapper;
 use mkpath_grid::{BitGrid, EightConnectedExpander, Grid, GridPool};
 
@@ -12,7 +11,7 @@ pub struct GridMapper {
 
 impl GridMapper {
     pub fn dfs_preorder(map: &BitGrid) -> Self {
-        let mut grid = Grid::new(map.width(), map.height(), |_, _| usize::MAX);
+        let mut grid = Grid::filled(map.width(), map.height(), usize::MAX);
         let mut array = vec![];
 
         let mut builder = NodeBuilder::new();
@@ -58,7 +57,7 @@ impl GridMapper {
         from.read_exact(&mut bytes)?;
         let height = i32::from_le_bytes(bytes);
 
-        let mut grid = Grid::new(width, height, |_, _| usize::MAX);
+        let mut grid = Grid::filled(width, height, usize::MAX);
         let mut array = vec![(0, 0); len].into_boxed_slice();
         for id in 0..len {
             from.read_exact(&mut bytes)?;
@@ -72,6 +71,15 @@ impl GridMapper {
         Ok(GridMapper { grid, array })
     }
 
+    /// Reads the whole mapping, so that its memory is loaded; see [`PartialCellCpd::prefetch`].
+    ///
+    /// [`PartialCellCpd::prefetch`]: crate::PartialCellCpd::prefetch
+    pub(crate) fn prefetch(&self) {
+        let ids = self.grid.storage().iter().fold(0, |acc, &id| acc ^ id);
+        let states = self.array.iter().fold(0, |acc, &(x, y)| acc ^ x ^ y);
+        std::hint::black_box((ids, states));
+    }
+
     pub fn save(&self, to: &mut impl Write) -> std::io::Result<()> {
         to.write_all(&(self.array.len() as u32).to_le_bytes())?;
         to.write_all(&self.grid.width().to_le_bytes())?;
@@ -2,6 +2,20 @@ use enumset::EnumSet;
 use mkpath_grid::Direction;
 use mkpath_jps::canonical_successors;
 
+/// Computes the first-move tiebreaking table for a jump point (Hu et al, 2021).
+///
+/// `nb` is the neighborhood of the cell and `jps` is the set of directions in which a search may
+/// be travelling when it reaches the cell as a jump point. The table is indexed by first-move sets
+/// (as in [`EnumSet::as_usize`]). Each first-move set is mapped to the moves in it which are
+/// canonical successors for every arrival direction in `jps` that is relevant to it (see
+/// [`is_irrelevant_jp`]). Any of these moves can be stored as the first move of the cell while
+/// remaining usable by JPS-based searches arriving from any relevant direction.
+///
+/// The empty set maps to all directions. Sets containing moves which are not legal from the cell
+/// map to themselves, since they never occur.
+///
+/// # Panics
+/// Panics if tiebreaking leaves some legal first-move set without moves.
 pub fn compute_tiebreak_table(
     nb: EnumSet<Direction>,
     jps: EnumSet<Direction>,
@@ -31,7 +45,61 @@ pub fn compute_tiebreak_table(
     result
 }
 
-fn is_irrelevant_jp(jp: Direction, fm: EnumSet<Direction>, nb: EnumSet<Direction>) -> bool {
+/// Returns whether a search arriving at a jump point travelling in direction `jp` can be ignored
+/// when tiebreaking the first-move set `fm`, given the neighborhood `nb` of the jump point.
+///
+/// A search arriving in direction `jp` only continues along canonical successors, so if none of
+/// the first moves are canonical successors, the arrival direction can't be on an optimal path
+/// using them. Otherwise, it is irrelevant if any of the first moves has one of the following
+/// forms. In each diagram, `J` is the jump point, `^` marks the cell the search arrived from,
+/// and `x` marks the moves that make the arrival direction irrelevant. A path arriving this way
+/// and continuing with such a move is either not optimal, or has an equally short alternative
+/// which does not pass through the jump point in direction `jp`.
+///
+/// Case 1, moving backwards after an orthogonal arrival (shown for `North`):
+///
+/// ```text
+/// . . .
+/// . J .
+/// x ^ x
+/// ```
+///
+/// Case 2, moving backwards after a diagonal arrival (shown for `NorthWest`):
+///
+/// ```text
+/// . . .
+/// . J .
+/// . . ^
+/// ```
+///
+/// Case 3, a switchback after a diagonal arrival (shown for `NorthWest`):
+///
+/// ```text
+/// . . .
+/// . J x
+/// . x ^
+/// ```
+///
+/// Case 4, a diagonal-to-diagonal turn after a diagonal arrival (shown for `NorthWest`):
+///
+/// ```text
+/// . . x
+/// . J .
+/// x . ^
+/// ```
+///
+/// Case 5, an orthogonal turn after an orthogonal arrival, where the cell diagonally behind the
+/// jump point on the side of the turn is traversable (shown for `North` turning `West`; `#` must
+/// be traversable). The cell arrived from could have moved diagonally to the destination instead:
+///
+/// ```text
+/// . . .
+/// x J .
+/// # ^ .
+/// ```
+///
+/// In cases 1 and 2, the move back to the cell arrived from is itself also pruned.
+pub fn is_irrelevant_jp(jp: Direction, fm: EnumSet<Direction>, nb: EnumSet<Direction>) -> bool {
     use Direction::*;
 
     let canonical = canonical_successors(nb, Some(jp));
@@ -40,7 +108,7 @@ fn is_irrelevant_jp(jp: Direction, fm: EnumSet<Direction>, nb: EnumSet<Direction
         return true;
     }
 
-    // Cases 1, 2, 3, and 4 (backwards, switchback, and diagonal-to-diagonal turn)
+    // Cases 1 and 2 (backwards), 3 (switchback), and 4 (diagonal-to-diagonal turn)
     if !fm.is_disjoint(match jp {
         North => SouthWest | South | SouthEast,
         West => NorthEast | East | SouthEast,
@@ -81,7 +149,7 @@ fn tiebreaking_is_valid() {
         let mut jp = EnumSet::empty();
 
         // find orthogonal jump points
-        for dir in [North, South, East, West] {
+        for dir in Direction::ORTHOGONAL {
             if !nb.contains(dir.backwards()) {
                 continue;
             }
@@ -93,7 +161,7 @@ fn tiebreaking_is_valid() {
         }
 
         // find potential diagonal jump points
-        for dir in [NorthWest, NorthEast, SouthWest, SouthEast] {
+        for dir in Direction::DIAGONAL {
             let dir_x = match dir {
                 NorthWest | SouthWest => West,
                 _ => East,
@@ -1,185 +1,206 @@
 use mkpath_core::traits::{Expander, NodePool};
 use mkpath_core::{HashPool, NodeBuilder, NodeMemberPointer};
-use mkpath_grid::{octile_distance, BitGrid, Direction};
-use mkpath_jps::{canonical_successors, reached_direction, JumpDatabase};
+use mkpath_grid::{octile_distance, BitGrid};
+use mkpath_jps::{canonical_successors, reached_direction, reached_direction_from, JumpDatabase};
 
-use crate::{PartialCellCpd, TopsExpander};
+use std::sync::Mutex;
+
+use crate::verify::Searcher;
+use crate::{parallel_for, PartialCellCpd, QueryResult, TopsExpander};
+
+/// A `(start, target)` pair.
+type Query = ((i32, i32), (i32, i32));
+
+/// A path as a list of cells, along with its cost.
+type Path = (Vec<(i32, i32)>, f64);
+
+/// The immutable data used by Topping+, which can be shared between threads.
+///
+/// [`ToppingPlus`] searchers own a node pool and so can't be shared; this creates one per thread
+/// instead.
+///
+/// To share a single copy of the CPD between processes, load it from a memory-mapped file using
+/// [`PartialCellCpd::load_mmap`].
+#[derive(Clone, Copy)]
+pub struct ToppingPlusOracle<'a> {
+    jump_db: &'a JumpDatabase,
+    cpd: &'a PartialCellCpd<'a>,
+}
+
+impl<'a> ToppingPlusOracle<'a> {
+    pub fn new(jump_db: &'a JumpDatabase, cpd: &'a PartialCellCpd<'a>) -> Self {
+        ToppingPlusOracle { jump_db, cpd }
+    }
+
+    /// Creates a searcher using this oracle.
+    pub fn searcher(&self) -> ToppingPlus<'a> {
+        ToppingPlus::new(self.jump_db, self.cpd)
+    }
+
+    /// Loads all of the CPD into memory before timed queries; see [`PartialCellCpd::prefetch`].
+    pub fn prefetch(&self) {
+        self.cpd.prefetch();
+    }
+
+    /// Finds paths for many `(start, target)` queries in parallel, using all available cores.
+    ///
+    /// The results are in the same order as `instances`, and are the same as those of
+    /// [`ToppingPlus::get_path`].
+    pub fn query_batch(&self, instances: &[Query]) -> Vec<Path> {
+        let results = Mutex::new(Vec::with_capacity(instances.len()));
+        parallel_for(
+            instances.iter().enumerate(),
+            || self.searcher(),
+            |searcher, (i, &(start, target))| {
+                let result = searcher.get_path(start, target);
+                results.lock().unwrap().push((i, result));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_unstable_by_key(|&(i, _)| i);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
 
 pub struct ToppingPlus<'a> {
     map: &'a BitGrid,
     jump_db: &'a JumpDatabase,
-    cpd: &'a PartialCellCpd,
+    cpd: &'a PartialCellCpd<'a>,
     node_pool: HashPool<(i32, i32)>,
     state: NodeMemberPointer<(i32, i32)>,
     cost: NodeMemberPointer<f64>,
+    /// Index of the start successor whose walk last reached the node, for detecting cycles.
+    walk: NodeMemberPointer<usize>,
+    /// Searches where the CPD has no data.
+    searcher: Searcher,
 }
 
 impl<'a> ToppingPlus<'a> {
-    pub fn new(map: &'a BitGrid, jump_db: &'a JumpDatabase, cpd: &'a PartialCellCpd) -> Self {
+    pub fn new(jump_db: &'a JumpDatabase, cpd: &'a PartialCellCpd<'a>) -> Self {
         let mut builder = NodeBuilder::new();
-        let state = builder.add_field((-1, -1));
+        let searcher = Searcher::new(&mut builder);
+        let state = searcher.state;
         let cost = builder.add_field(f64::INFINITY);
+        let walk = builder.add_field(usize::MAX);
 
-        // Establish invariant that coordinates in-bounds of the map are in-bounds of the jump
-        // database, and vice-versa.
-        // We don't check that the content of the jump database is actually correct for the map
-        // since that's a) slow b) merely a logic error; not required for safety.
-        assert_eq!(
-            map.width(),
-            jump_db.width(),
-            "jump database has incorrect width"
-        );
-        assert_eq!(
-            map.height(),
-            jump_db.height(),
-            "jump database has incorrect height"
+        assert!(
+            jump_db.has_diagonals(),
+            "jump database must have diagonal jump data"
         );
 
         ToppingPlus {
-            map,
+            map: jump_db.map(),
             jump_db,
             cpd,
             node_pool: HashPool::new(builder.build(), state),
             state,
             cost,
+            walk,
+            searcher,
         }
     }
 
+    /// Creates a new searcher with its own node pool, sharing the map, jump database and CPD with
+    /// this one.
+    ///
+    /// `ToppingPlus` can't be shared between threads since searching mutates its node pool, so
+    /// each worker thread should use its own fork.
+    pub fn fork(&self) -> ToppingPlus<'a> {
+        self.oracle().searcher()
+    }
+
+    /// Returns the shareable data used by this searcher.
+    pub fn oracle(&self) -> ToppingPlusOracle<'a> {
+        ToppingPlusOracle::new(self.jump_db, self.cpd)
+    }
+
+    /// Finds a path from `start` to `target`, returning the cells of the path and its cost.
+    ///
+    /// If `start` is `target`, the path is just `[start]` with cost 0. If either is an obstacle, or
+    /// `target` is unreachable, the path is just `[start]` with infinite cost.
+    ///
+    /// If the path passes through a jump point without first-move data, as when the CPD was
+    /// computed for a region (see [`CpdOptions::bounds`](crate::CpdOptions::bounds)), the path is
+    /// instead found by an A* search with [`TopsExpander`], which uses JPS where there is no data.
+    ///
+    /// Either way, the cost is the sum of [`octile_distance`] between consecutive cells of the
+    /// path, so it doesn't depend on which of the two found the path.
     pub fn get_path(&mut self, start: (i32, i32), target: (i32, i32)) -> (Vec<(i32, i32)>, f64) {
+        if !self.map.get(start.0, start.1) || !self.map.get(target.0, target.1) {
+            return (vec![start], f64::INFINITY);
+        }
+        if start == target {
+            return (vec![start], 0.0);
+        }
+
         self.node_pool.reset();
 
-        let state = self.state;
-        let cost = self.cost;
+        let ToppingPlus {
+            state, cost, walk, ..
+        } = *self;
 
         let start_node = self.node_pool.generate(start);
         let target_node = self.node_pool.generate(target);
         target_node.set(cost, 0.0);
 
         let mut starts = vec![];
-        TopsExpander::new(
-            self.map,
-            self.jump_db,
-            self.cpd,
-            &self.node_pool,
-            self.state,
-            target,
-        )
-        .expand(start_node, &mut starts);
+        TopsExpander::new(self.jump_db, self.cpd, &self.node_pool, self.state, target)
+            .expand(start_node, &mut starts);
 
         let mut node_stack = vec![];
 
         for edge in &starts {
             if edge.successor.ptr_eq(target_node) {
-                return (vec![start, target], edge.cost);
+                return (vec![start, target], octile_distance(start, target));
             }
         }
 
-        'start_successor: for edge in starts {
+        'start_successor: for (walk_id, edge) in starts.into_iter().enumerate() {
             let mut current_node = edge.successor;
             let mut prev_state = start;
+            let mut prev_move = None;
             node_stack.clear();
 
             while current_node.get(cost).is_infinite() {
+                // First moves of a consistent CPD never lead back to a jump point on the same
+                // walk, but those of a stale or corrupt one may go around in a cycle.
+                if current_node.get(walk) == walk_id {
+                    continue 'start_successor;
+                }
+                current_node.set(walk, walk_id);
+
                 let state = current_node.get(state);
-                let going = reached_direction(prev_state, state);
+                // Jumps we made ourselves start with a known move, so the direction they end in
+                // needn't be inferred from their endpoints.
+                let going = match prev_move {
+                    Some(first) => reached_direction_from(first, prev_state, state),
+                    None => reached_direction(prev_state, state),
+                };
                 let canonical =
                     canonical_successors(self.map.get_neighborhood(state.0, state.1), going);
 
-                let dir = self
-                    .cpd
-                    .query(state, target)
-                    .expect("cpd did not have move for jump point");
+                let dir = match self.cpd.query_checked(state, target) {
+                    QueryResult::Move(dir) => dir,
+                    QueryResult::NoRow => return self.search_online(start, target),
+                    // A row without a move towards a reachable target is stale or corrupt, so
+                    // abandon this start successor.
+                    QueryResult::NoMove => continue 'start_successor,
+                };
 
                 if !canonical.contains(dir) {
                     continue 'start_successor;
                 }
 
-                let next_state = match dir {
-                    Direction::North => unsafe {
-                        // SAFETY: We know that TopsExpander produces successors whose
-                        //         coordinates are in-bounds, and we know that jumping with the
-                        //         jump distance database gives us coordinates that are in-bounds,
-                        //         so state will always be in-bounds. Similar for below calls.
-                        let dist = self
-                            .jump_db
-                            .ortho_jump_unchecked(state.0, state.1, Direction::North, target)
-                            .unwrap();
-                        (state.0, state.1 - dist)
-                    },
-                    Direction::West => unsafe {
-                        let dist = self
-                            .jump_db
-                            .ortho_jump_unchecked(state.0, state.1, Direction::West, target)
-                            .unwrap();
-                        (state.0 - dist, state.1)
-                    },
-                    Direction::South => unsafe {
-                        let dist = self
-                            .jump_db
-                            .ortho_jump_unchecked(state.0, state.1, Direction::South, target)
-                            .unwrap();
-                        (state.0, state.1 + dist)
-                    },
-                    Direction::East => unsafe {
-                        let dist = self
-                            .jump_db
-                            .ortho_jump_unchecked(state.0, state.1, Direction::East, target)
-                            .unwrap();
-                        (state.0 + dist, state.1)
-                    },
-                    Direction::NorthWest => unsafe {
-                        let (dist, turn) = self
-                            .jump_db
-                            .diagonal_jump_unchecked(state.0, state.1, Direction::NorthWest, target)
-                            .unwrap();
-                        let (x, y) = (state.0 - dist, state.1 - dist);
-                        match turn {
-                            Some((Direction::North, dist2)) => (x, y - dist2),
-                            Some((Direction::West, dist2)) => (x - dist2, y),
-                            None => (x, y),
-                            _ => unreachable!(),
-                        }
-                    },
-                    Direction::SouthWest => unsafe {
-                        let (dist, turn) = self
-                            .jump_db
-                            .diagonal_jump_unchecked(state.0, state.1, Direction::SouthWest, target)
-                            .unwrap();
-                        let (x, y) = (state.0 - dist, state.1 + dist);
-                        match turn {
-                            Some((Direction::South, dist2)) => (x, y + dist2),
-                            Some((Direction::West, dist2)) => (x - dist2, y),
-                            None => (x, y),
-                            _ => unreachable!(),
-                        }
-                    },
-                    Direction::SouthEast => unsafe {
-                        let (dist, turn) = self
-                            .jump_db
-                            .diagonal_jump_unchecked(state.0, state.1, Direction::SouthEast, target)
-                            .unwrap();
-                        let (x, y) = (state.0 + dist, state.1 + dist);
-                        match turn {
-                            Some((Direction::South, dist2)) => (x, y + dist2),
-                            Some((Direction::East, dist2)) => (x + dist2, y),
-                            None => (x, y),
-                            _ => unreachable!(),
-                        }
-                    },
-                    Direction::NorthEast => unsafe {
-                        let (dist, turn) = self
-                            .jump_db
-                            .diagonal_jump_unchecked(state.0, state.1, Direction::NorthEast, target)
-                            .unwrap();
-                        let (x, y) = (state.0 + dist, state.1 - dist);
-                        match turn {
-                            Some((Direction::North, dist2)) => (x, y - dist2),
-                            Some((Direction::East, dist2)) => (x + dist2, y),
-                            None => (x, y),
-                            _ => unreachable!(),
-                        }
-                    },
+                // A consistent CPD only gives moves along which a jump reaches another jump point
+                // or the target, so this only fails if the CPD is stale or corrupt. Abandon this
+                // start successor as with non-canonical moves rather than panicking.
+                let Some(jump) = self.jump_db.jump_to(state, dir, target) else {
+                    continue 'start_successor;
                 };
+                let next_state = jump.end;
 
                 let next_node = self.node_pool.generate(next_state);
                 // using parent (back pointer) as successor (forward pointer) instead
@@ -187,6 +208,7 @@ impl<'a> ToppingPlus<'a> {
                 node_stack.push(current_node);
                 current_node = next_node;
                 prev_state = state;
+                prev_move = Some(dir);
             }
 
             while let Some(prev_node) = node_stack.pop() {
@@ -198,7 +220,9 @@ impl<'a> ToppingPlus<'a> {
                 current_node = prev_node;
             }
 
-            let new_cost = current_node.get(cost) + edge.cost;
+            // Costs are accumulated with octile distances like the rest of the path, rather than
+            // taken from the expander, so that all paths are costed the same way.
+            let new_cost = current_node.get(cost) + octile_distance(start, current_node.get(state));
 
             if new_cost < start_node.get(cost) {
                 start_node.set(cost, new_cost);
@@ -216,4 +240,296 @@ impl<'a> ToppingPlus<'a> {
 
         (path, start_node.get(cost))
     }
+
+    /// Finds a path visiting each of `points` in order, returning the cells of the path and its
+    /// cost.
+    ///
+    /// The path is made of the paths found by [`ToppingPlus::get_path`] between consecutive
+    /// points, with each intermediate point appearing once where two of them join. If any point is
+    /// unreachable from the previous one, the path is just `[points[0]]` with infinite cost, like
+    /// an unreachable target in `get_path`.
+    ///
+    /// Panics if `points` is empty.
+    #[track_caller]
+    pub fn get_path_through(&mut self, points: &[(i32, i32)]) -> (Vec<(i32, i32)>, f64) {
+        assert!(!points.is_empty(), "path must visit at least one point");
+
+        let mut path = vec![points[0]];
+        let mut total = 0.0;
+        for leg in points.windows(2) {
+            let (leg_path, cost) = self.get_path(leg[0], leg[1]);
+            if cost.is_infinite() {
+                return (vec![points[0]], f64::INFINITY);
+            }
+            // Each leg starts with the point the previous leg ended at.
+            path.extend_from_slice(&leg_path[1..]);
+            total += cost;
+        }
+        (path, total)
+    }
+
+    fn search_online(&mut self, start: (i32, i32), target: (i32, i32)) -> (Vec<(i32, i32)>, f64) {
+        self.node_pool.reset();
+        let expander =
+            TopsExpander::new(self.jump_db, self.cpd, &self.node_pool, self.state, target);
+        let found = self
+            .searcher
+            .search_path(&self.node_pool, expander, start, target);
+        let Some((path, _)) = found else {
+            return (vec![start], f64::INFINITY);
+        };
+        // The search used the expander's edge costs; recompute the cost the same way as
+        // `get_path` does so that the two agree exactly.
+        let cost = path_cost(&path);
+        (path, cost)
+    }
+}
+
+/// Sums the octile distance between consecutive cells of `path`, from the target backwards.
+fn path_cost(path: &[(i32, i32)]) -> f64 {
+    path.windows(2)
+        .rev()
+        .map(|segment| octile_distance(segment[0], segment[1]))
+        .fold(0.0, |cost, segment| segment + cost)
+}
+
+#[test]
+fn batch_matches_sequential() {
+    let map = walled_map();
+    let jump_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let oracle = ToppingPlusOracle::new(&jump_db, &cpd);
+
+    let mut instances = vec![];
+    for (i, start) in [(0, 0), (3, 14), (23, 15), (10, 2)].into_iter().enumerate() {
+        for j in 0..10 {
+            let target = ((i as i32 * 7 + j * 5) % 24, (j * 3) % 16);
+            if map.get(target.0, target.1) {
+                instances.push((start, target));
+            }
+        }
+    }
+
+    let batch = oracle.query_batch(&instances);
+    let mut searcher = oracle.searcher();
+    assert_eq!(batch.len(), instances.len());
+    for (&(start, target), result) in instances.iter().zip(&batch) {
+        assert_eq!(&searcher.get_path(start, target), result);
+    }
+}
+
+#[test]
+fn region_cpd_falls_back_to_search() {
+    use crate::CpdOptions;
+
+    let map = walled_map();
+    let jump_db = JumpDatabase::new(&map);
+    let options = CpdOptions {
+        bounds: Some((0, 0, 8, 16)),
+        ..CpdOptions::default()
+    };
+    let full = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let region = PartialCellCpd::compute_with_options(&map, &jump_db, options, |_, _, _| {});
+
+    let mut has_rows = false;
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let expected = full.query((x, y), (20, 15));
+            let found = region.query((x, y), (20, 15));
+            match x < 8 {
+                true => assert_eq!(found, expected),
+                false => assert_eq!(found, None),
+            }
+            has_rows |= found.is_some();
+        }
+    }
+    assert!(has_rows);
+
+    let mut full = ToppingPlus::new(&jump_db, &full);
+    let mut region = ToppingPlus::new(&jump_db, &region);
+    for start in [(0, 0), (3, 14), (23, 15), (10, 2)] {
+        for target in [(20, 15), (1, 1), (12, 4), (23, 0)] {
+            let (path, cost) = region.get_path(start, target);
+            assert!((cost - full.get_path(start, target).1).abs() < 1e-9);
+            assert_eq!((path[0], path[path.len() - 1]), (start, target));
+            let length: f64 = path.windows(2).map(|w| octile_distance(w[0], w[1])).sum();
+            assert!((length - cost).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn stale_cpd_does_not_panic() {
+    let mut map = walled_map();
+    let stale_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &stale_db, |_, _, _| {});
+
+    // Blocking a cell after the CPD was computed leaves moves whose jumps run into it.
+    map.set(12, 12, false);
+    let jump_db = JumpDatabase::new(&map);
+    let mut searcher = ToppingPlus::new(&jump_db, &cpd);
+    let (path, cost) = searcher.get_path((12, 0), (12, 13));
+    // The walks running into the blocked cell are abandoned, and another start successor still
+    // leads along an optimal path.
+    let expected = mkpath_grid::exact_distance(&map, (12, 0), (12, 13));
+    assert!((cost - expected).abs() < 1e-9, "{cost} vs {expected}");
+    assert_eq!((path[0], path[path.len() - 1]), ((12, 0), (12, 13)));
+    assert!((path_cost(&path) - cost).abs() < 1e-9);
+}
+
+#[test]
+fn cyclic_cpd_terminates() {
+    use mkpath_cpd::{CpdRow, StateIdMapper};
+    use mkpath_grid::Direction;
+
+    use crate::mapper::GridMapper;
+
+    let map = BitGrid::from_str_rows(
+        &[
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+            "....##....",
+            "....##....",
+            "..........",
+            "..........",
+            "..........",
+            "..........",
+        ],
+        &['.'],
+    );
+    let jump_db = JumpDatabase::new(&map);
+
+    // A corrupt CPD whose moves from the corners around the block go around it clockwise,
+    // whatever the target. Each move is canonical after the previous one.
+    let moves = [
+        ((3i32, 3i32), Direction::East),
+        ((6, 3), Direction::South),
+        ((6, 6), Direction::West),
+        ((3, 6), Direction::North),
+    ];
+    let mapper = GridMapper::dfs_preorder(&map);
+    let mut bytes = vec![];
+    PartialCellCpd::CURRENT_VERSION.save(&mut bytes).unwrap();
+    mapper.save(&mut bytes).unwrap();
+    bytes.extend(0u32.to_le_bytes());
+    for ((x, y), dir) in moves {
+        bytes.extend(x.to_le_bytes());
+        bytes.extend(y.to_le_bytes());
+        let row = CpdRow::compress(std::iter::repeat_n(1 << dir as u64, mapper.num_ids()));
+        row.save(&mut bytes).unwrap();
+    }
+    // End marker and entry count.
+    bytes.extend(u32::MAX.to_le_bytes());
+    bytes.extend((moves.len() as u32).to_le_bytes());
+    let cpd = PartialCellCpd::load(&map, &mut &bytes[..]).unwrap();
+
+    let mut searcher = ToppingPlus::new(&jump_db, &cpd);
+    // Every jump point is on the cycle, so every walk from the start ends up going around it. No
+    // path is found, but the search finishes.
+    let (path, cost) = searcher.get_path((0, 0), (9, 8));
+    assert_eq!(path, vec![(0, 0)]);
+    assert!(cost.is_infinite());
+}
+
+#[test]
+fn path_through_waypoints() {
+    use mkpath_grid::Direction;
+
+    let mut map = walled_map();
+    for y in 1..map.height() {
+        map.set(20, y, false);
+    }
+    for dir in Direction::ALL {
+        let (x, y) = dir.step((22, 8));
+        map.set(x, y, false);
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let mut searcher = ToppingPlus::new(&jump_db, &cpd);
+
+    let points = [(0, 0), (12, 14), (12, 14), (3, 9), (18, 1)];
+    let (path, cost) = searcher.get_path_through(&points);
+    let mut expected_cost = 0.0;
+    let mut expected_path = vec![points[0]];
+    for leg in points.windows(2) {
+        let (leg_path, leg_cost) = searcher.get_path(leg[0], leg[1]);
+        assert_eq!(leg_path[0], leg[0]);
+        expected_path.extend_from_slice(&leg_path[1..]);
+        expected_cost += leg_cost;
+    }
+    assert_eq!(path, expected_path);
+    assert!((cost - expected_cost).abs() < 1e-9);
+    for point in points {
+        assert!(path.contains(&point));
+    }
+    assert!(path.windows(2).all(|w| w[0] != w[1]));
+
+    assert_eq!(searcher.get_path_through(&[(5, 5)]), (vec![(5, 5)], 0.0));
+
+    // (22, 8) is walled in, so the last leg fails.
+    let (path, cost) = searcher.get_path_through(&[(0, 0), (12, 14), (22, 8)]);
+    assert_eq!(path, vec![(0, 0)]);
+    assert!(cost.is_infinite());
+}
+
+#[test]
+fn costs_match_dense_paths() {
+    use crate::CpdOptions;
+
+    let map = BitGrid::random_obstacles(32, 24, 0.25, 3);
+    let jump_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    // Half of the map has no first-move data, so some paths are found by searching.
+    let options = CpdOptions {
+        bounds: Some((0, 0, 16, 24)),
+        ..CpdOptions::default()
+    };
+    let region = PartialCellCpd::compute_with_options(&map, &jump_db, options, |_, _, _| {});
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+    for cpd in [&cpd, &region] {
+        let mut topping = ToppingPlus::new(&jump_db, cpd);
+        for &start in cells.iter().step_by(13) {
+            for &target in cells.iter().step_by(17) {
+                let (path, cost) = topping.get_path(start, target);
+                if cost.is_infinite() {
+                    continue;
+                }
+
+                // Walk each segment one step at a time, diagonally first.
+                let mut dense_cost = 0.0;
+                for segment in path.windows(2) {
+                    let (mut x, mut y) = segment[0];
+                    while (x, y) != segment[1] {
+                        let step = ((segment[1].0 - x).signum(), (segment[1].1 - y).signum());
+                        dense_cost += octile_distance((x, y), (x + step.0, y + step.1));
+                        (x, y) = (x + step.0, y + step.1);
+                        assert!(map.get(x, y));
+                    }
+                }
+                assert!(
+                    (cost - dense_cost).abs() < 1e-9,
+                    "{start:?} to {target:?}: {cost} vs {dense_cost}"
+                );
+            }
+        }
+    }
+}
+
+/// A map with a vertical wall with two gaps, and to its right a horizontal wall with one gap.
+#[cfg(test)]
+fn walled_map() -> BitGrid {
+    let mut map = BitGrid::new(24, 16);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x == 8 && y != 3 && y != 12) || (y == 9 && x > 12 && x != 18);
+            map.set(x, y, !wall);
+        }
+    }
+    map
 }
@@ -5,24 +5,40 @@ use mkpath_jps::{canonical_successors, JumpDatabase};
 
 use crate::PartialCellCpd;
 
+/// TOPS expander, which prunes JPS+ successors using the first moves stored in a
+/// [`PartialCellCpd`].
+///
+/// First moves are looked up by the direction each node was reached in, so directional CPDs (see
+/// [`CpdOptions::directional`](crate::CpdOptions::directional)) are used automatically.
+///
+/// The start and target need not be jump points. Nodes without a CPD row, which includes a start
+/// in open space, fall back to the full set of JPS+ successors, and jumps towards a target which is
+/// not a jump point are always generated, so searches remain optimal for any pair of endpoints.
 pub struct TopsExpander<'a, P> {
     node_pool: &'a P,
     map: &'a BitGrid,
     jump_db: &'a JumpDatabase,
-    oracle: &'a PartialCellCpd,
+    oracle: &'a PartialCellCpd<'a>,
     state: NodeMemberPointer<(i32, i32)>,
     target: (i32, i32),
 }
 
 impl<'a, P: GridNodePool> TopsExpander<'a, P> {
     pub fn new(
-        map: &'a BitGrid,
         jump_db: &'a JumpDatabase,
-        oracle: &'a PartialCellCpd,
+        oracle: &'a PartialCellCpd<'a>,
         node_pool: &'a P,
         state: NodeMemberPointer<(i32, i32)>,
         target: (i32, i32),
     ) -> Self {
+        let map = jump_db.map();
+
+        // Diagonal jumps are made using unchecked queries.
+        assert!(
+            jump_db.has_diagonals(),
+            "jump database must have diagonal jump data"
+        );
+
         // Establish invariant that coordinates in-bounds of the map are also in-bounds of the
         // node pool.
         assert!(
@@ -34,21 +50,6 @@ impl<'a, P: GridNodePool> TopsExpander<'a, P> {
             "node pool must be tall enough for the map"
         );
 
-        // Establish invariant that coordinates in-bounds of the map are in-bounds of the jump
-        // database, and vice-versa.
-        // We don't check that the content of the jump database is actually correct for the map
-        // since that's a) slow b) merely a logic error; not required for safety.
-        assert_eq!(
-            map.width(),
-            jump_db.width(),
-            "jump database has incorrect width"
-        );
-        assert_eq!(
-            map.height(),
-            jump_db.height(),
-            "jump database has incorrect height"
-        );
-
         TopsExpander {
             map,
             jump_db,
@@ -68,13 +69,8 @@ impl<'a, P: GridNodePool> TopsExpander<'a, P> {
         cost: f64,
         edges: &mut Vec<WeightedEdge<'a>>,
     ) {
-        let (dx, dy) = match dir {
-            Direction::North => (0, -1),
-            Direction::West => (-1, 0),
-            Direction::South => (0, 1),
-            Direction::East => (1, 0),
-            _ => unreachable!(),
-        };
+        debug_assert!(dir.orthogonal());
+        let (dx, dy) = dir.vector();
 
         if let Some(dist) = self.jump_db.ortho_jump_unchecked(x, y, dir, self.target) {
             edges.push(WeightedEdge {
@@ -94,11 +90,12 @@ impl<'a, P: GridNodePool> TopsExpander<'a, P> {
         dir: Direction,
         edges: &mut Vec<WeightedEdge<'a>>,
     ) {
-        let (dx, dy, dir_x, dir_y) = match dir {
-            Direction::NorthWest => (-1, -1, Direction::West, Direction::North),
-            Direction::SouthWest => (-1, 1, Direction::West, Direction::South),
-            Direction::SouthEast => (1, 1, Direction::East, Direction::South),
-            Direction::NorthEast => (1, -1, Direction::East, Direction::North),
+        let (dx, dy) = dir.vector();
+        let (dir_x, dir_y) = match dir {
+            Direction::NorthWest => (Direction::West, Direction::North),
+            Direction::SouthWest => (Direction::West, Direction::South),
+            Direction::SouthEast => (Direction::East, Direction::South),
+            Direction::NorthEast => (Direction::East, Direction::North),
             _ => unreachable!(),
         };
 
@@ -128,7 +125,7 @@ impl<'a, P: GridNodePool> TopsExpander<'a, P> {
                 break;
             }
 
-            if let Some(first_move) = self.oracle.query((x, y), self.target) {
+            if let Some(first_move) = self.oracle.query_from((x, y), Some(dir), self.target) {
                 if first_move == dir_x {
                     self.jump_ortho(x, y, dir_x, cost, edges);
                     break;
@@ -159,7 +156,7 @@ impl<'a, P: GridNodePool> Expander<'a> for TopsExpander<'a, P> {
 
         let mut successors = canonical_successors(self.map.get_neighborhood(x, y), dir);
 
-        let first_move = self.oracle.query((x, y), self.target);
+        let first_move = self.oracle.query_from((x, y), dir, self.target);
 
         if let Some(dir) = first_move {
             successors &= dir;
@@ -195,4 +192,54 @@ impl<'a, P: GridNodePool> Expander<'a> for TopsExpander<'a, P> {
             }
         }
     }
+
+    fn max_degree(&self) -> usize {
+        8
+    }
+}
+
+#[test]
+fn endpoints_in_open_space() {
+    use mkpath_core::NodeBuilder;
+    use mkpath_grid::{BidirectionalDijkstra, GridPool};
+
+    use crate::verify::Searcher;
+
+    let mut map = BitGrid::random_obstacles(48, 32, 0.2, 5);
+    for x in 10..30 {
+        map.set(x, 16, false);
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let jump_points: Vec<_> = jump_db
+        .jump_points()
+        .into_iter()
+        .map(|(jp, _)| jp)
+        .collect();
+
+    let mut builder = NodeBuilder::new();
+    let mut searcher = Searcher::new(&mut builder);
+    let state = searcher.state;
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+    let mut dijkstra = BidirectionalDijkstra::new(map.width(), map.height());
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y) && !jump_points.contains(&(x, y)))
+        .collect();
+    let mut checked = 0;
+    for (i, &start) in cells.iter().enumerate().step_by(37) {
+        for &target in cells.iter().skip(i % 23).step_by(41) {
+            let expected = Some(dijkstra.distance(&map, start, target)).filter(|d| d.is_finite());
+            pool.reset();
+            let expander = TopsExpander::new(&jump_db, &cpd, &pool, state, target);
+            let found = searcher.search(&pool, expander, start, target);
+            match (expected, found) {
+                (Some(expected), Some(found)) => assert!((expected - found).abs() < 1e-6),
+                (expected, found) => assert_eq!(expected, found, "{start:?} to {target:?}"),
+            }
+            checked += 1;
+        }
+    }
+    assert!(checked > 100);
 }
@@ -0,0 +1,225 @@
+use mkpath_core::traits::{Expander, NodePool, OpenList, WeightedEdge};
+use mkpath_core::{NodeBuilder, NodeMemberPointer, NodeRef, PriorityQueueFactory};
+use mkpath_grid::{octile_distance, BidirectionalDijkstra, BitGrid, GridPool, Rng};
+use mkpath_jps::JumpDatabase;
+
+use crate::{independent_jump_points, JpsBbExpander, PartialCellBb, PartialCellCpd, TopsExpander};
+
+/// First-move oracle to check with [`verify_oracle`].
+#[derive(Clone, Copy)]
+pub enum Oracle<'a> {
+    /// Checked by searching with [`TopsExpander`].
+    Cpd(&'a PartialCellCpd<'a>),
+    /// Checked by searching with [`JpsBbExpander`].
+    Bb(&'a PartialCellBb),
+}
+
+impl<'a> From<&'a PartialCellCpd<'a>> for Oracle<'a> {
+    fn from(value: &'a PartialCellCpd<'a>) -> Self {
+        Oracle::Cpd(value)
+    }
+}
+
+impl<'a> From<&'a PartialCellBb> for Oracle<'a> {
+    fn from(value: &'a PartialCellBb) -> Self {
+        Oracle::Bb(value)
+    }
+}
+
+/// A (start, target) pair for which search using the oracle does not find a shortest path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FailureCase {
+    pub start: (i32, i32),
+    pub target: (i32, i32),
+    /// Exact distance to the target, or `None` if the target is unreachable.
+    pub expected: Option<f64>,
+    /// Path cost found using the oracle, or `None` if no path was found.
+    pub found: Option<f64>,
+}
+
+/// Checks that searching with a first-move oracle finds optimal paths.
+///
+/// Samples `samples` pairs of an independent jump point and a traversable target using a
+/// deterministic generator seeded with `seed`. For each pair, the path cost found by TOPS (for
+/// [`PartialCellCpd`]) or JPS+BB+ (for [`PartialCellBb`]) is compared to the exact distance
+/// computed by [`BidirectionalDijkstra`]. All mismatching pairs are returned.
+///
+/// This is intended as a sanity check after building or loading an oracle, e.g. to detect oracles
+/// computed for a different version of the map.
+pub fn verify_oracle<'a>(
+    map: &BitGrid,
+    jump_db: &JumpDatabase,
+    oracle: impl Into<Oracle<'a>>,
+    samples: usize,
+    seed: u64,
+) -> Result<(), Vec<FailureCase>> {
+    let oracle = oracle.into();
+
+    let mut jump_points: Vec<_> = independent_jump_points(jump_db).into_keys().collect();
+    // Sort so that sampling is deterministic regardless of hash map iteration order.
+    jump_points.sort_unstable();
+    if jump_points.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = NodeBuilder::new();
+    let mut searcher = Searcher::new(&mut builder);
+    let state = searcher.state;
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+    let mut dijkstra = BidirectionalDijkstra::new(map.width(), map.height());
+    let mut rng = Rng::new(seed);
+    let mut failures = vec![];
+
+    for _ in 0..samples {
+        let start = jump_points[rng.below(jump_points.len() as u64) as usize];
+        let target = loop {
+            let x = rng.below(map.width() as u64) as i32;
+            let y = rng.below(map.height() as u64) as i32;
+            if map.get(x, y) {
+                break (x, y);
+            }
+        };
+
+        let expected = Some(dijkstra.distance(map, start, target)).filter(|d| d.is_finite());
+
+        pool.reset();
+        let found = match oracle {
+            Oracle::Cpd(cpd) => {
+                let expander = TopsExpander::new(jump_db, cpd, &pool, state, target);
+                searcher.search(&pool, expander, start, target)
+            }
+            Oracle::Bb(bb) => {
+                let expander = JpsBbExpander::new(jump_db, bb, &pool, state, target);
+                searcher.search(&pool, expander, start, target)
+            }
+        };
+
+        let matches = match (expected, found) {
+            (Some(expected), Some(found)) => (expected - found).abs() < 1e-6,
+            (None, None) => true,
+            _ => false,
+        };
+        if !matches {
+            failures.push(FailureCase {
+                start,
+                target,
+                expected,
+                found,
+            });
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures)
+    }
+}
+
+pub(crate) struct Searcher {
+    pub(crate) state: NodeMemberPointer<(i32, i32)>,
+    g: NodeMemberPointer<f64>,
+    f: NodeMemberPointer<f64>,
+    pqueue_factory: PriorityQueueFactory,
+}
+
+impl Searcher {
+    pub(crate) fn new(builder: &mut NodeBuilder) -> Self {
+        Searcher {
+            state: builder.add_field((-1, -1)),
+            g: builder.add_field(f64::INFINITY),
+            f: builder.add_field(f64::INFINITY),
+            pqueue_factory: PriorityQueueFactory::new(builder),
+        }
+    }
+
+    /// Returns the cost of the shortest path found by A* with the octile heuristic.
+    pub(crate) fn search<'a>(
+        &mut self,
+        pool: &'a impl NodePool<State = (i32, i32)>,
+        expander: impl Expander<'a, Edge = WeightedEdge<'a>>,
+        start: (i32, i32),
+        target: (i32, i32),
+    ) -> Option<f64> {
+        let target_node = self.search_node(pool, expander, start, target)?;
+        Some(target_node.get(self.g))
+    }
+
+    /// Like [`Searcher::search`], but also returns the cells of the path, from `start` to
+    /// `target`.
+    pub(crate) fn search_path<'a>(
+        &mut self,
+        pool: &'a impl NodePool<State = (i32, i32)>,
+        expander: impl Expander<'a, Edge = WeightedEdge<'a>>,
+        start: (i32, i32),
+        target: (i32, i32),
+    ) -> Option<(Vec<(i32, i32)>, f64)> {
+        let target_node = self.search_node(pool, expander, start, target)?;
+        let mut path = vec![target];
+        let mut node = target_node;
+        while let Some(parent) = node.get_parent() {
+            path.push(parent.get(self.state));
+            node = parent;
+        }
+        path.reverse();
+        Some((path, target_node.get(self.g)))
+    }
+
+    fn search_node<'a>(
+        &mut self,
+        pool: &'a impl NodePool<State = (i32, i32)>,
+        mut expander: impl Expander<'a, Edge = WeightedEdge<'a>>,
+        start: (i32, i32),
+        target: (i32, i32),
+    ) -> Option<NodeRef<'a>> {
+        let Searcher { state, g, f, .. } = *self;
+        let mut open = self.pqueue_factory.new_queue((f, g));
+        let mut edges = vec![];
+
+        let start = pool.generate(start);
+        start.set(g, 0.0);
+        start.set(f, octile_distance(start.get(state), target));
+        open.relaxed(start);
+
+        while let Some(node) = open.next() {
+            if node.get(state) == target {
+                return Some(node);
+            }
+
+            edges.clear();
+            expander.expand(node, &mut edges);
+
+            for edge in &edges {
+                let successor = edge.successor;
+                let new_g = node.get(g) + edge.cost;
+                if new_g < successor.get(g) {
+                    successor.set(g, new_g);
+                    successor.set(f, new_g + octile_distance(successor.get(state), target));
+                    successor.set_parent(Some(node));
+                    open.relaxed(successor);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[test]
+fn oracles_are_optimal() {
+    let mut map = BitGrid::new(24, 16);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x % 6 == 3 && y % 5 != 2) || (y == 8 && x % 4 == 1) || (x == 17 && y > 10);
+            map.set(x, y, !wall);
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    assert_eq!(verify_oracle(&map, &jump_db, &cpd, 500, 1), Ok(()));
+
+    let bb = PartialCellBb::compute(&map, &jump_db, |_, _, _| {});
+    assert_eq!(verify_oracle(&map, &jump_db, &bb, 500, 2), Ok(()));
+}
@@ -0,0 +1,257 @@
+use std::collections::BinaryHeap;
+
+use mkpath_core::traits::Expander;
+use mkpath_core::{NodeBuilder, NodeMemberPointer};
+
+use crate::lpa_star::QueueEntry;
+use crate::{EightConnectedExpander, Grid, GridMap, GridPool};
+
+/// A path and its cost.
+type Path = (Vec<(i32, i32)>, f64);
+
+/// Finds shortest paths from one start to many targets on an 8-connected grid map with a single
+/// Dijkstra search.
+///
+/// Searching to many targets separately repeats most of the exploration around the start, so a
+/// single search which stops once every target has been reached is much faster when there are
+/// more than a handful of targets. Costs are octile, as with [`EightConnectedExpander::new`].
+///
+/// A searcher can be reused for any number of queries on maps with the dimensions it was created
+/// for without reallocating its node pool; [`batch_paths`] is a shorthand for a single query.
+/// [`BatchDijkstra::distances`] runs the same search to completion to get the distance to every
+/// cell instead.
+pub struct BatchDijkstra {
+    node_pool: GridPool,
+    state: NodeMemberPointer<(i32, i32)>,
+    g: NodeMemberPointer<f64>,
+    closed: NodeMemberPointer<bool>,
+    target: NodeMemberPointer<bool>,
+    open: BinaryHeap<QueueEntry>,
+}
+
+impl BatchDijkstra {
+    pub fn new(width: i32, height: i32) -> Self {
+        let mut builder = NodeBuilder::new();
+        let state = builder.add_field((-1, -1));
+        let g = builder.add_field(f64::INFINITY);
+        let closed = builder.add_field(false);
+        let target = builder.add_field(false);
+        BatchDijkstra {
+            node_pool: GridPool::new(builder.build(), state, width, height),
+            state,
+            g,
+            closed,
+            target,
+            open: BinaryHeap::new(),
+        }
+    }
+
+    /// Returns the shortest path from `start` to each of `targets` and its cost, in the same
+    /// order as `targets`, or `None` for targets which are unreachable.
+    ///
+    /// Paths include both `start` and the target. Targets may be repeated.
+    pub fn paths(
+        &mut self,
+        map: &impl GridMap,
+        start: (i32, i32),
+        targets: &[(i32, i32)],
+    ) -> Vec<Option<Path>> {
+        if !map.get(start.0, start.1) {
+            return vec![None; targets.len()];
+        }
+
+        self.node_pool.reset();
+        let mut remaining = 0;
+        for &(x, y) in targets {
+            if !map.get(x, y) {
+                continue;
+            }
+            let node = self.node_pool.generate((x, y));
+            if !node.get(self.target) {
+                node.set(self.target, true);
+                remaining += 1;
+            }
+        }
+
+        self.search(map, start, Some(remaining));
+
+        let BatchDijkstra {
+            ref node_pool,
+            state,
+            g,
+            closed,
+            ..
+        } = *self;
+
+        targets
+            .iter()
+            .map(|&cell| {
+                if !map.get(cell.0, cell.1) {
+                    return None;
+                }
+                let node = node_pool.generate(cell);
+                if !node.get(closed) {
+                    return None;
+                }
+                let mut path = vec![cell];
+                let mut current = node;
+                while let Some(parent) = current.get_parent() {
+                    path.push(parent.get(state));
+                    current = parent;
+                }
+                path.reverse();
+                Some((path, node.get(g)))
+            })
+            .collect()
+    }
+
+    /// Returns the octile distance from `start` to every cell of `map`.
+    ///
+    /// Unreachable cells, obstacles, and every cell if `start` is not traversable, get
+    /// [`f64::INFINITY`].
+    pub fn distances(&mut self, map: &impl GridMap, start: (i32, i32)) -> Grid<f64> {
+        let mut distance = Grid::filled(map.width(), map.height(), f64::INFINITY);
+        if !map.get(start.0, start.1) {
+            return distance;
+        }
+
+        self.node_pool.reset();
+        self.search(map, start, None);
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                if map.get(x, y) {
+                    distance[(x, y)] = self.node_pool.generate((x, y)).get(self.g);
+                }
+            }
+        }
+        distance
+    }
+
+    /// Runs Dijkstra's algorithm from `start` until `remaining` of the nodes marked as targets
+    /// have been expanded, or until the open list is exhausted if `remaining` is `None`.
+    ///
+    /// The node pool must have been reset, and `start` must be traversable.
+    fn search(&mut self, map: &impl GridMap, start: (i32, i32), mut remaining: Option<usize>) {
+        let BatchDijkstra {
+            ref node_pool,
+            state,
+            g,
+            closed,
+            target,
+            ref mut open,
+        } = *self;
+
+        let mut expander = EightConnectedExpander::new(map, node_pool, state);
+        let mut edges = vec![];
+
+        open.clear();
+        node_pool.generate(start).set(g, 0.0);
+        open.push(QueueEntry {
+            key: (0.0, 0.0),
+            state: start,
+        });
+
+        while let Some(entry) = open.pop() {
+            if remaining == Some(0) {
+                break;
+            }
+            let node = node_pool.generate(entry.state);
+            if node.get(closed) {
+                continue;
+            }
+            node.set(closed, true);
+            if node.get(target) {
+                remaining = remaining.map(|remaining| remaining - 1);
+            }
+
+            edges.clear();
+            expander.expand(node, &mut edges);
+            for edge in &edges {
+                let new_g = node.get(g) + edge.cost;
+                if new_g < edge.successor.get(g) {
+                    edge.successor.set(g, new_g);
+                    edge.successor.set_parent(Some(node));
+                    open.push(QueueEntry {
+                        key: (new_g, 0.0),
+                        state: edge.successor.get(state),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Returns the shortest paths from `start` to each of `targets` on an 8-connected grid map.
+///
+/// See [`BatchDijkstra`], which should be used instead when making many queries.
+pub fn batch_paths(
+    map: &impl GridMap,
+    start: (i32, i32),
+    targets: &[(i32, i32)],
+) -> Vec<Option<Path>> {
+    BatchDijkstra::new(map.width(), map.height()).paths(map, start, targets)
+}
+
+#[test]
+fn matches_single_target_searches() {
+    use crate::{exact_distance, validate_path, BitGrid, Metric};
+
+    let map = BitGrid::from_str_rows(
+        &[
+            "...........@......",
+            "..@@@@.....@..@...",
+            ".....@.....@..@...",
+            ".....@........@...",
+            "@@@..@@@@@@@..@@@@",
+            "..@...........@...",
+            "..@..@@@@@.......@",
+            "..@......@....@..@",
+        ],
+        &['.'],
+    );
+
+    let targets = [
+        (17, 0),
+        (0, 7),
+        (3, 2),
+        (17, 5),
+        (3, 2),
+        (2, 1),
+        (4, 0),
+        (16, 7),
+    ];
+    let mut searcher = BatchDijkstra::new(map.width(), map.height());
+    for start in [(4, 0), (0, 5), (12, 6)] {
+        let results = searcher.paths(&map, start, &targets);
+        let distance = searcher.distances(&map, start);
+        assert_eq!(results.len(), targets.len());
+        for (&target, result) in targets.iter().zip(&results) {
+            let expected = exact_distance(&map, start, target);
+            assert_eq!(
+                distance[target],
+                result.as_ref().map_or(f64::INFINITY, |r| r.1)
+            );
+            let Some((path, cost)) = result else {
+                assert_eq!(expected, f64::INFINITY, "{start:?} to {target:?}");
+                continue;
+            };
+            assert!((cost - expected).abs() < 1e-9, "{start:?} to {target:?}");
+            assert_eq!((path[0], path[path.len() - 1]), (start, target));
+            let length = validate_path(&map, path, Metric::OCTILE).unwrap();
+            assert!((length - cost).abs() < 1e-9);
+        }
+    }
+
+    // The blocked target (2, 1) and the enclosed target (0, 7) are unreachable.
+    let results = batch_paths(&map, (4, 0), &targets);
+    assert!(results[1].is_none() && results[5].is_none());
+    assert_eq!(results[6], Some((vec![(4, 0)], 0.0)));
+    assert!(batch_paths(&map, (2, 1), &targets)
+        .iter()
+        .all(Option::is_none));
+    // Cells just outside the map are obstacles like any other.
+    assert_eq!(
+        batch_paths(&map, (4, 0), &[(-1, 0), (18, 3), (5, 8)]),
+        vec![None; 3]
+    );
+}
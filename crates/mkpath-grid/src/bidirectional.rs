@@ -0,0 +1,161 @@
+use std::collections::BinaryHeap;
+
+use mkpath_core::traits::Expander;
+use mkpath_core::{NodeBuilder, NodeMemberPointer};
+
+use crate::lpa_star::QueueEntry;
+use crate::{EightConnectedExpander, GridMap, GridPool};
+
+/// Bidirectional Dijkstra search for exact distances on an 8-connected grid map.
+///
+/// Searching from both ends explores far fewer cells than a unidirectional Dijkstra search to a
+/// distant target, and unlike A* it does not depend on a heuristic, which makes it suitable for
+/// computing ground truth distances when evaluating heuristics or oracles. Costs are octile, as
+/// with [`EightConnectedExpander::new`].
+///
+/// A searcher can be reused for any number of queries on maps with the dimensions it was created
+/// for; [`exact_distance`] is a shorthand for a single query.
+pub struct BidirectionalDijkstra {
+    node_pool: GridPool,
+    state: NodeMemberPointer<(i32, i32)>,
+    g: [NodeMemberPointer<f64>; 2],
+    open: [BinaryHeap<QueueEntry>; 2],
+}
+
+impl BidirectionalDijkstra {
+    pub fn new(width: i32, height: i32) -> Self {
+        let mut builder = NodeBuilder::new();
+        let state = builder.add_field((-1, -1));
+        let g = [
+            builder.add_field(f64::INFINITY),
+            builder.add_field(f64::INFINITY),
+        ];
+        BidirectionalDijkstra {
+            node_pool: GridPool::new(builder.build(), state, width, height),
+            state,
+            g,
+            open: [BinaryHeap::new(), BinaryHeap::new()],
+        }
+    }
+
+    /// Returns the length of the shortest path from `start` to `target`, or infinity if there is
+    /// none.
+    pub fn distance(&mut self, map: &impl GridMap, start: (i32, i32), target: (i32, i32)) -> f64 {
+        if !map.get(start.0, start.1) || !map.get(target.0, target.1) {
+            return f64::INFINITY;
+        }
+        if start == target {
+            return 0.0;
+        }
+
+        self.node_pool.reset();
+        let BidirectionalDijkstra {
+            ref node_pool,
+            state,
+            g,
+            ref mut open,
+        } = *self;
+
+        // Movement is symmetric, so the backward search uses the same expander.
+        let mut expander = EightConnectedExpander::new(map, node_pool, state);
+        let mut edges = vec![];
+
+        for (side, from) in [start, target].into_iter().enumerate() {
+            open[side].clear();
+            node_pool.generate(from).set(g[side], 0.0);
+            open[side].push(QueueEntry {
+                key: (0.0, 0.0),
+                state: from,
+            });
+        }
+
+        // Length of the shortest path found so far through a cell reached by both searches.
+        let mut best = f64::INFINITY;
+
+        loop {
+            let mut tops = [f64::INFINITY; 2];
+            for side in 0..2 {
+                // Discard entries of cells which were reached more cheaply after being queued.
+                while let Some(&top) = open[side].peek() {
+                    if top.key.0 <= node_pool.generate(top.state).get(g[side]) {
+                        tops[side] = top.key.0;
+                        break;
+                    }
+                    open[side].pop();
+                }
+            }
+
+            // Every path through an unexpanded cell is at least as long as the sum of the
+            // smallest distances in the two queues.
+            if tops[0] + tops[1] >= best {
+                return best;
+            }
+
+            let side = if tops[0] <= tops[1] { 0 } else { 1 };
+            let entry = open[side].pop().unwrap();
+            let dist = entry.key.0;
+
+            edges.clear();
+            expander.expand(node_pool.generate(entry.state), &mut edges);
+            for edge in &edges {
+                let new_dist = dist + edge.cost;
+                if new_dist < edge.successor.get(g[side]) {
+                    edge.successor.set(g[side], new_dist);
+                    open[side].push(QueueEntry {
+                        key: (new_dist, 0.0),
+                        state: edge.successor.get(state),
+                    });
+                    best = best.min(new_dist + edge.successor.get(g[1 - side]));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the exact length of the shortest path from `start` to `target` on an 8-connected grid
+/// map, or infinity if there is none.
+///
+/// See [`BidirectionalDijkstra`], which should be used instead when making many queries.
+pub fn exact_distance(map: &impl GridMap, start: (i32, i32), target: (i32, i32)) -> f64 {
+    BidirectionalDijkstra::new(map.width(), map.height()).distance(map, start, target)
+}
+
+#[test]
+fn matches_unidirectional_search() {
+    use crate::{reference_cost, BitGrid};
+
+    let mut map = BitGrid::new(24, 18);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x == 9 && y != 4) || (y == 12 && x > 14 && x != 20) || (x + y) % 7 == 0;
+            map.set(x, y, !wall);
+        }
+    }
+
+    let mut searcher = BidirectionalDijkstra::new(map.width(), map.height());
+    let cells = [
+        (0, 1),
+        (3, 3),
+        (8, 16),
+        (10, 5),
+        (23, 17),
+        (16, 11),
+        (21, 13),
+        (12, 12),
+    ];
+    for start in cells {
+        for target in cells {
+            let found = searcher.distance(&map, start, target);
+            match reference_cost(&map, start, target) {
+                Some(expected) => assert!(
+                    (found - expected).abs() < 1e-9,
+                    "{start:?} to {target:?}: expected {expected}, found {found}"
+                ),
+                None => assert_eq!(found, f64::INFINITY, "{start:?} to {target:?}"),
+            }
+        }
+    }
+
+    // (9, 0) is a wall.
+    assert_eq!(exact_distance(&map, (0, 1), (9, 0)), f64::INFINITY);
+}
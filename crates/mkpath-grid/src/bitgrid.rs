@@ -9,6 +9,7 @@ use crate::Direction;
 /// The grid map is padded with an additional row above and below, as well as an additional column
 /// to the left and right. Attempts to write to these cells will panic, but reads will succeed and
 /// return non-traversable.
+#[derive(Clone)]
 pub struct BitGrid {
     width: i32,
     height: i32,
@@ -44,6 +45,54 @@ impl BitGrid {
         }
     }
 
+    /// Creates a grid map from rows of characters, where cells whose character is in
+    /// `traversable` are traversable.
+    ///
+    /// Panics if the rows are not all the same length.
+    ///
+    /// ```
+    /// # use mkpath_grid::BitGrid;
+    /// let map = BitGrid::from_str_rows(&["..@", "@.."], &['.']);
+    /// assert!(map.get(0, 0));
+    /// assert!(!map.get(2, 0));
+    /// ```
+    #[track_caller]
+    pub fn from_str_rows(rows: &[&str], traversable: &[char]) -> Self {
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        let mut grid = BitGrid::new(width as i32, rows.len() as i32);
+        for (y, row) in rows.iter().enumerate() {
+            let length = row.chars().count();
+            assert!(
+                length == width,
+                "row {y} has length {length}, but row 0 has length {width}"
+            );
+            for (x, c) in row.chars().enumerate() {
+                grid.set(x as i32, y as i32, traversable.contains(&c));
+            }
+        }
+        grid
+    }
+
+    /// Creates a grid map from rows of traversability values.
+    ///
+    /// Panics if the rows are not all the same length.
+    #[track_caller]
+    pub fn from_bools(rows: &[Vec<bool>]) -> Self {
+        let width = rows.first().map_or(0, |row| row.len());
+        let mut grid = BitGrid::new(width as i32, rows.len() as i32);
+        for (y, row) in rows.iter().enumerate() {
+            let length = row.len();
+            assert!(
+                length == width,
+                "row {y} has length {length}, but row 0 has length {width}"
+            );
+            for (x, &traversable) in row.iter().enumerate() {
+                grid.set(x as i32, y as i32, traversable);
+            }
+        }
+        grid
+    }
+
     #[inline(always)]
     pub fn width(&self) -> i32 {
         self.width
@@ -73,11 +122,30 @@ impl BitGrid {
     #[track_caller]
     #[inline(always)]
     pub fn get_neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction> {
+        self.unpadded_bounds_check(x, y);
+        // SAFETY: We checked that the coordinates are unpadded in-bounds.
+        unsafe { self.get_neighborhood_unchecked(x, y) }
+    }
+
+    /// Gets the set of traversable neighbors of a cell without bounds checking.
+    ///
+    /// This is intended for loops over every cell of the map, where the bounds check in
+    /// [`BitGrid::get_neighborhood`] is redundant.
+    ///
+    /// # Safety
+    /// The coordinates must be in-bounds of the grid. Specifically:
+    /// - `x` is in `0..self.width()`
+    /// - `y` is in `0..self.height()`
+    #[inline(always)]
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub unsafe fn get_neighborhood_unchecked(&self, x: i32, y: i32) -> EnumSet<Direction> {
+        #[cfg(debug_assertions)]
         self.unpadded_bounds_check(x, y);
         let mut nbhood = EnumSet::empty();
         unsafe {
-            // SAFETY: We checked that the coordinates are unpadded in-bounds, so coordinates
-            //         within 1 cell in each direction are padded in-bounds, as required.
+            // SAFETY: The caller ensures that the coordinates are unpadded in-bounds, so
+            //         coordinates within 1 cell in each direction are padded in-bounds, as
+            //         required.
             if self.get_unchecked(x, y - 1) {
                 nbhood |= Direction::North;
             }
@@ -198,6 +266,76 @@ impl BitGrid {
         raw_bits << (7 - bit)
     }
 
+    /// Returns the transpose of the map, in which the cell `(x, y)` is the cell `(y, x)` of this
+    /// map.
+    ///
+    /// This works on blocks of 8x8 cells at a time rather than on individual cells, so it is much
+    /// faster than copying the cells one by one.
+    pub fn transpose(&self) -> BitGrid {
+        let mut transposed = BitGrid::new(self.height, self.width);
+        for y0 in (0..self.height).step_by(8) {
+            for x0 in (0..self.width).step_by(8) {
+                let columns = (self.width - x0).min(8);
+                let mask = ((1u16 << columns) - 1) as u8;
+                // Bit `8 * i + j` of the block is the cell `(x0 + j, y0 + i)`. Rows past the end of
+                // the map are left empty, as are the columns past it, by the mask.
+                let mut block = 0;
+                for i in 0..(self.height - y0).min(8) {
+                    // SAFETY: (x0, y0 + i) is in-bounds by the loop ranges.
+                    let row = unsafe { self.get_row_right(x0, y0 + i) } as u8 & mask;
+                    block |= (row as u64) << (8 * i);
+                }
+
+                let block = transpose_8x8(block);
+                for j in 0..columns {
+                    // SAFETY: (y0, x0 + j) is in-bounds of the transpose by the loop ranges, and
+                    //         only its first 8 cells are written to.
+                    unsafe { transposed.or_row_right(y0, x0 + j, (block >> (8 * j)) as u8) };
+                }
+            }
+        }
+        transposed
+    }
+
+    /// Makes the cells to the right of and including `(x, y)` traversable wherever the
+    /// corresponding bit of `bits` is set, with the bit for `(x, y)` the least significant.
+    ///
+    /// # Safety
+    /// The coordinates must be in-bounds of the grid, and every cell whose bit is set must also be
+    /// in-bounds, so that the padding cells stay non-traversable.
+    unsafe fn or_row_right(&mut self, x: i32, y: i32, bits: u8) {
+        let (byte, bit) = self.index(x, y);
+        let bits = (bits as u16) << bit;
+        unsafe {
+            // SAFETY: The bytes after that of an in-bounds cell exist, since rows are followed by
+            //         padding and the array ends with 8 bytes of zeros.
+            *self.bits.get_unchecked_mut(byte) |= bits as u8;
+            *self.bits.get_unchecked_mut(byte + 1) |= (bits >> 8) as u8;
+        }
+    }
+
+    /// Returns the bit array backing the grid, for sharing the map with code outside of Rust, such
+    /// as a GPU kernel.
+    ///
+    /// The traversability of the cell at `(x, y)`, including the padding cells, is bit `i % 8` of
+    /// byte `i / 8`, with bit 0 the least significant, where
+    /// `i = 64 + 8 * (y + 1) * self.padded_width_bytes() + (x + 1)`. That is, there are 8 bytes of
+    /// zeros first, each row (including the padding rows) takes
+    /// [`BitGrid::padded_width_bytes`] bytes, and the padding column left of the map is the first
+    /// bit of each row. Cells are traversable if their bit is set. The array ends with at least 8
+    /// bytes of zeros, so that 64-bit reads as in [`BitGrid::get_row_right`] stay in-bounds.
+    pub fn raw_bits(&self) -> &[u8] {
+        &self.bits
+    }
+
+    /// Returns the number of bytes each row takes up in [`BitGrid::raw_bits`].
+    ///
+    /// This is always more than `self.width() / 8`, so that there is at least one padding column
+    /// between the end of a row and the start of the next.
+    pub fn padded_width_bytes(&self) -> usize {
+        self.padded_width_bytes
+    }
+
     #[track_caller]
     #[inline(always)]
     fn padded_bounds_check(&self, x: i32, y: i32) {
@@ -216,6 +354,8 @@ impl BitGrid {
         assert!(y < self.height, "y out of bounds");
     }
 
+    /// Returns the byte and bit of `bits` storing the cell at `(x, y)`. This is the layout
+    /// documented on [`BitGrid::raw_bits`], and must be kept in sync with it.
     #[inline(always)]
     fn index(&self, x: i32, y: i32) -> (usize, usize) {
         let padded_y = (y + 1) as usize;
@@ -225,3 +365,172 @@ impl BitGrid {
         (byte + 8, bit)
     }
 }
+
+/// Transposes an 8x8 bit matrix, in which bit `8 * i + j` is the entry in row `i` and column `j`.
+///
+/// This is the usual three rounds of swapping the off-diagonal 1x1, 2x2 and 4x4 blocks of each
+/// 2x2, 4x4 and 8x8 block, from Hacker's Delight.
+fn transpose_8x8(mut x: u64) -> u64 {
+    let t = (x ^ (x >> 7)) & 0x00AA_00AA_00AA_00AA;
+    x ^= t ^ (t << 7);
+    let t = (x ^ (x >> 14)) & 0x0000_CCCC_0000_CCCC;
+    x ^= t ^ (t << 14);
+    let t = (x ^ (x >> 28)) & 0x0000_0000_F0F0_F0F0;
+    x ^ t ^ (t << 28)
+}
+
+/// Read-only access to the traversability of the cells of a grid map.
+///
+/// This allows expanders such as [`EightConnectedExpander`](crate::EightConnectedExpander) to
+/// search maps other than a plain [`BitGrid`], such as an [`OverlayGrid`](crate::OverlayGrid).
+///
+/// # Safety
+/// Implementations must behave like a padded [`BitGrid`]: cells outside of
+/// `0..width()`, `0..height()` are never traversable, and [`GridMap::get_unchecked`] must be sound
+/// to call on the padding cells. Expanders rely on this to generate nodes without bounds checks.
+pub unsafe trait GridMap {
+    fn width(&self) -> i32;
+
+    fn height(&self) -> i32;
+
+    /// Gets the traversability of a cell.
+    ///
+    /// Panics if the coordinates are out of bounds of the padded grid.
+    fn get(&self, x: i32, y: i32) -> bool;
+
+    /// Gets the set of traversable neighbors of a cell.
+    ///
+    /// Panics if the coordinates are out of bounds of the grid.
+    fn get_neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction>;
+
+    /// Gets the traversability of a cell without bounds checking.
+    ///
+    /// # Safety
+    /// The coordinates must be in-bounds of the padded grid. Specifically:
+    /// - `x` is in `-1..=self.width()`
+    /// - `y` is in `-1..=self.height()`
+    unsafe fn get_unchecked(&self, x: i32, y: i32) -> bool;
+}
+
+// SAFETY: BitGrid is the reference implementation of the padding behavior.
+unsafe impl GridMap for BitGrid {
+    #[inline(always)]
+    fn width(&self) -> i32 {
+        self.width()
+    }
+
+    #[inline(always)]
+    fn height(&self) -> i32 {
+        self.height()
+    }
+
+    #[inline(always)]
+    fn get(&self, x: i32, y: i32) -> bool {
+        self.get(x, y)
+    }
+
+    #[inline(always)]
+    fn get_neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction> {
+        self.get_neighborhood(x, y)
+    }
+
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, x: i32, y: i32) -> bool {
+        unsafe { self.get_unchecked(x, y) }
+    }
+}
+
+// SAFETY: Forwards to an implementation which upholds the requirements.
+unsafe impl<M: GridMap + ?Sized> GridMap for &M {
+    #[inline(always)]
+    fn width(&self) -> i32 {
+        (**self).width()
+    }
+
+    #[inline(always)]
+    fn height(&self) -> i32 {
+        (**self).height()
+    }
+
+    #[inline(always)]
+    fn get(&self, x: i32, y: i32) -> bool {
+        (**self).get(x, y)
+    }
+
+    #[inline(always)]
+    fn get_neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction> {
+        (**self).get_neighborhood(x, y)
+    }
+
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, x: i32, y: i32) -> bool {
+        unsafe { (**self).get_unchecked(x, y) }
+    }
+}
+
+#[test]
+fn builds_from_rows() {
+    let map = BitGrid::from_str_rows(&["..@", "@.T"], &['.', 'T']);
+    assert_eq!((map.width(), map.height()), (3, 2));
+    let cells: Vec<_> = (0..2)
+        .flat_map(|y| (0..3).map(move |x| (x, y)))
+        .map(|(x, y)| map.get(x, y))
+        .collect();
+    assert_eq!(cells, [true, true, false, false, true, true]);
+
+    let bools = BitGrid::from_bools(&[vec![true, true, false], vec![false, true, true]]);
+    for y in -1..=2 {
+        for x in -1..=3 {
+            assert_eq!(bools.get(x, y), map.get(x, y));
+        }
+    }
+
+    let empty = BitGrid::from_str_rows(&[], &['.']);
+    assert_eq!((empty.width(), empty.height()), (0, 0));
+
+    let ragged = std::panic::catch_unwind(|| BitGrid::from_str_rows(&["...", ".."], &['.']));
+    assert!(ragged.is_err());
+    let ragged = std::panic::catch_unwind(|| BitGrid::from_bools(&[vec![true], vec![]]));
+    assert!(ragged.is_err());
+}
+
+#[test]
+fn raw_bits_follow_documented_layout() {
+    let map = BitGrid::random_obstacles(21, 5, 0.4, 2);
+    let bits = map.raw_bits();
+    let row_bytes = map.padded_width_bytes();
+    assert_eq!(row_bytes, 3);
+    for y in -1..=map.height() {
+        for x in -1..=map.width() {
+            let i = 64 + 8 * (y + 1) as usize * row_bytes + (x + 1) as usize;
+            assert_eq!(bits[i / 8] & 1 << (i % 8) != 0, map.get(x, y), "({x}, {y})");
+        }
+    }
+    assert!(bits[..8].iter().all(|&b| b == 0));
+    assert!(bits[bits.len() - 8..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn transpose_matches_cellwise_copy() {
+    for (width, height, seed) in [
+        (0, 0, 1),
+        (0, 5, 1),
+        (1, 1, 2),
+        (7, 9, 3),
+        (8, 8, 4),
+        (61, 23, 5),
+    ] {
+        let map = BitGrid::random_obstacles(width, height, 0.4, seed);
+        let transposed = map.transpose();
+        assert_eq!((transposed.width(), transposed.height()), (height, width));
+        for y in -1..=height {
+            for x in -1..=width {
+                assert_eq!(
+                    transposed.get(y, x),
+                    map.get(x, y),
+                    "({x}, {y}) of {width}x{height}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::BinaryHeap;
+
+use mkpath_core::traits::Expander;
+use mkpath_core::{NodeBuilder, NodeMemberPointer, NodeRef};
+
+use crate::lpa_star::{key_less, QueueEntry};
+use crate::{octile_distance, BitGrid, EightConnectedExpander, GridPool};
+
+/// D* Lite (Koenig & Likhachev, 2002) for an agent moving on an 8-connected grid map.
+///
+/// The search runs backwards from the goal, so when the agent moves and discovers changed cells
+/// near it, only the affected part of the search is repaired. A typical loop alternates
+/// [`DStarLite::step`] to get the next cell, [`DStarLite::move_to`] once the agent has moved
+/// there, and [`DStarLite::update_cells`] whenever the agent observes changes to the map.
+///
+/// Costs and the heuristic are octile, as with [`EightConnectedExpander::new`] and
+/// [`octile_distance`].
+pub struct DStarLite {
+    map: BitGrid,
+    node_pool: GridPool,
+    state: NodeMemberPointer<(i32, i32)>,
+    g: NodeMemberPointer<f64>,
+    rhs: NodeMemberPointer<f64>,
+    key: NodeMemberPointer<(f64, f64)>,
+    queued: NodeMemberPointer<bool>,
+    open: BinaryHeap<QueueEntry>,
+    start: (i32, i32),
+    goal: (i32, i32),
+    last: (i32, i32),
+    km: f64,
+}
+
+impl DStarLite {
+    pub fn new(map: BitGrid, start: (i32, i32), goal: (i32, i32)) -> Self {
+        let mut builder = NodeBuilder::new();
+        let state = builder.add_field((-1, -1));
+        let g = builder.add_field(f64::INFINITY);
+        let rhs = builder.add_field(f64::INFINITY);
+        let key = builder.add_field((f64::INFINITY, f64::INFINITY));
+        let queued = builder.add_field(false);
+        let node_pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+        let mut dstar = DStarLite {
+            map,
+            node_pool,
+            state,
+            g,
+            rhs,
+            key,
+            queued,
+            open: BinaryHeap::new(),
+            start,
+            goal,
+            last: start,
+            km: 0.0,
+        };
+        dstar.update_vertex(goal);
+        dstar
+    }
+
+    pub fn map(&self) -> &BitGrid {
+        &self.map
+    }
+
+    /// Returns the current position of the agent.
+    pub fn position(&self) -> (i32, i32) {
+        self.start
+    }
+
+    /// Returns the next cell the agent should move to, or `None` if the agent is at the goal or
+    /// the goal is unreachable.
+    pub fn step(&mut self) -> Option<(i32, i32)> {
+        if self.start == self.goal {
+            return None;
+        }
+        self.compute_shortest_path();
+        if self
+            .node_pool
+            .generate(self.start)
+            .get(self.g)
+            .is_infinite()
+        {
+            return None;
+        }
+        self.best_successor(self.start).map(|(next, _)| next)
+    }
+
+    /// Informs the planner that the agent has moved to `next`.
+    pub fn move_to(&mut self, next: (i32, i32)) {
+        self.start = next;
+    }
+
+    /// Changes the traversability of cells, given as `(x, y, traversable)`.
+    pub fn update_cells(&mut self, changes: impl IntoIterator<Item = (i32, i32, bool)>) {
+        let mut changed = false;
+        for (x, y, traversable) in changes {
+            if self.map.get(x, y) == traversable {
+                continue;
+            }
+            if !changed {
+                // Keys are lower bounds relative to the agent position at the time they were
+                // computed, so account for the distance moved since the last change.
+                self.km += octile_distance(self.last, self.start);
+                self.last = self.start;
+                changed = true;
+            }
+            self.map.set(x, y, traversable);
+
+            // Both the edges to the cell and diagonal edges cutting its corners are affected, all
+            // of which end in the 3x3 neighborhood of the cell.
+            for cell in self.neighborhood((x, y)) {
+                self.update_vertex(cell);
+            }
+        }
+    }
+
+    fn compute_shortest_path(&mut self) {
+        loop {
+            while let Some(&top) = self.open.peek() {
+                let node = self.node_pool.generate(top.state);
+                if node.get(self.queued) && node.get(self.key) == top.key {
+                    break;
+                }
+                // Stale entry of a node which was removed or reinserted with a different key.
+                self.open.pop();
+            }
+            let Some(&top) = self.open.peek() else {
+                break;
+            };
+
+            let start = self.node_pool.generate(self.start);
+            if !key_less(top.key, self.calculate_key(start))
+                && start.get(self.rhs) == start.get(self.g)
+            {
+                break;
+            }
+
+            self.open.pop();
+            let node = self.node_pool.generate(top.state);
+            let new_key = self.calculate_key(node);
+
+            if key_less(top.key, new_key) {
+                node.set(self.key, new_key);
+                self.open.push(QueueEntry {
+                    key: new_key,
+                    state: top.state,
+                });
+                continue;
+            }
+
+            node.set(self.queued, false);
+            if node.get(self.g) > node.get(self.rhs) {
+                node.set(self.g, node.get(self.rhs));
+            } else {
+                node.set(self.g, f64::INFINITY);
+                self.update_vertex(top.state);
+            }
+            for neighbor in self.neighborhood(top.state) {
+                if neighbor != top.state {
+                    self.update_vertex(neighbor);
+                }
+            }
+        }
+    }
+
+    fn update_vertex(&mut self, cell: (i32, i32)) {
+        let rhs = if cell == self.goal {
+            0.0
+        } else {
+            self.best_successor(cell)
+                .map_or(f64::INFINITY, |(_, cost)| cost)
+        };
+
+        let node = self.node_pool.generate(cell);
+        node.set(self.rhs, rhs);
+        node.set(self.queued, false);
+        if node.get(self.g) != rhs {
+            let key = self.calculate_key(node);
+            node.set(self.key, key);
+            node.set(self.queued, true);
+            self.open.push(QueueEntry { key, state: cell });
+        }
+    }
+
+    /// Returns the successor of `cell` minimizing `cost + g`, along with that value.
+    fn best_successor(&self, cell: (i32, i32)) -> Option<((i32, i32), f64)> {
+        if !self.map.get(cell.0, cell.1) {
+            return None;
+        }
+
+        let mut expander = EightConnectedExpander::new(&self.map, &self.node_pool, self.state);
+        let mut edges = Vec::with_capacity(8);
+        expander.expand(self.node_pool.generate(cell), &mut edges);
+
+        edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.successor.get(self.state),
+                    edge.successor.get(self.g) + edge.cost,
+                )
+            })
+            .filter(|&(_, cost)| cost.is_finite())
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    fn calculate_key(&self, node: NodeRef) -> (f64, f64) {
+        let g = node.get(self.g).min(node.get(self.rhs));
+        (
+            g + octile_distance(self.start, node.get(self.state)) + self.km,
+            g,
+        )
+    }
+
+    fn neighborhood(&self, (x, y): (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+        let (width, height) = (self.map.width(), self.map.height());
+        (-1..=1)
+            .flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            .filter(move |&(x, y)| x >= 0 && y >= 0 && x < width && y < height)
+    }
+}
+
+#[test]
+fn agent_reaches_goal_around_discovered_walls() {
+    use crate::LpaStar;
+
+    let mut map = BitGrid::new(20, 12);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, true);
+        }
+    }
+    let (start, goal) = ((1, 5), (18, 5));
+
+    // The agent only knows about the wall once it gets close.
+    let wall: Vec<_> = (0..11).map(|y| (10, y)).collect();
+
+    let mut dstar = DStarLite::new(map.clone(), start, goal);
+    let mut steps = 0;
+    while let Some(next) = dstar.step() {
+        let pos = dstar.position();
+        assert!((next.0 - pos.0).abs() <= 1 && (next.1 - pos.1).abs() <= 1);
+        assert!(map.get(next.0, next.1));
+        dstar.move_to(next);
+
+        let visible: Vec<_> = wall
+            .iter()
+            .filter(|&&(x, y)| (x - next.0).abs() <= 2 && (y - next.1).abs() <= 2)
+            .filter(|&&(x, y)| map.get(x, y))
+            .copied()
+            .collect();
+        for &(x, y) in &visible {
+            map.set(x, y, false);
+        }
+        dstar.update_cells(visible.iter().map(|&(x, y)| (x, y, false)));
+
+        dstar.compute_shortest_path();
+        let expected = LpaStar::new(map.clone(), next, goal)
+            .replan()
+            .map(|(_, cost)| cost);
+        let remaining = dstar.node_pool.generate(next).get(dstar.g);
+        assert!((remaining - expected.unwrap()).abs() < 1e-9);
+
+        steps += 1;
+        assert!(steps < 100);
+    }
+    assert_eq!(dstar.position(), goal);
+}
@@ -0,0 +1,169 @@
+use enumset::EnumSet;
+
+use crate::{BitGrid, Direction, Grid};
+
+/// Record of the cells removed by [`BitGrid::prune_dead_ends`].
+///
+/// Every pruned cell had a single remaining neighbor when it was pruned, so the pruned cells form
+/// trees hanging off the pruned map. The path from a pruned cell back into the pruned map is
+/// unique, and is given by [`DeadEnds::path_out`].
+pub struct DeadEnds {
+    /// For pruned cells, the direction of the neighbor the cell was attached to when it was
+    /// pruned, if any.
+    exits: Grid<Option<Direction>>,
+    pruned: Grid<bool>,
+}
+
+impl DeadEnds {
+    pub fn is_pruned(&self, x: i32, y: i32) -> bool {
+        self.pruned[(x, y)]
+    }
+
+    /// Returns the direction leading from a pruned cell towards the pruned map, if there is one.
+    ///
+    /// This is `None` for cells which aren't pruned, and for the last cell pruned from a connected
+    /// component which was pruned entirely.
+    pub fn exit(&self, x: i32, y: i32) -> Option<Direction> {
+        self.exits[(x, y)]
+    }
+
+    /// Returns the path from `cell` to the nearest cell of the pruned map, including both ends.
+    ///
+    /// If `cell` isn't pruned, the path consists of only `cell`. If `cell` is in a connected
+    /// component which was pruned entirely, the path ends at the last cell pruned from it.
+    ///
+    /// To find a path between cells at least one of which is pruned, connect the paths out of them
+    /// with a path found on the pruned map. When both cells lead out through the same cell, the
+    /// shortest path is instead the path between them in the tree of pruned cells.
+    pub fn path_out(&self, cell: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut path = vec![cell];
+        let mut current = cell;
+        while self.is_pruned(current.0, current.1) {
+            let Some(dir) = self.exit(current.0, current.1) else {
+                break;
+            };
+            current = dir.step(current);
+            path.push(current);
+        }
+        path
+    }
+}
+
+impl BitGrid {
+    /// Removes dead ends from the map.
+    ///
+    /// Repeatedly blocks traversable cells which can be moved to from fewer than two cells, until
+    /// no such cells remain. A shortest path between two cells never passes through such a cell,
+    /// so distances between the cells remaining in the pruned map are unchanged, while searches on
+    /// it no longer explore dead-end corridors. Dead-end rooms, which contain cycles, are kept.
+    ///
+    /// Returns the pruned map along with a [`DeadEnds`] to recover paths to and from pruned cells.
+    pub fn prune_dead_ends(&self) -> (BitGrid, DeadEnds) {
+        let mut map = self.clone();
+        let mut dead_ends = DeadEnds {
+            exits: Grid::new_default(self.width(), self.height()),
+            pruned: Grid::new_default(self.width(), self.height()),
+        };
+
+        let mut stack = vec![];
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                stack.push((x, y));
+            }
+        }
+
+        while let Some((x, y)) = stack.pop() {
+            if !map.get(x, y) {
+                continue;
+            }
+            let moves = moves(map.get_neighborhood(x, y));
+            if moves.len() >= 2 {
+                continue;
+            }
+
+            // A cell with a single neighbor can't be a corner that a diagonal move between two
+            // other cells depends on, since such a corner is adjacent to both of them. So pruning
+            // only removes the move between this cell and its neighbor.
+            map.set(x, y, false);
+            dead_ends.pruned[(x, y)] = true;
+            dead_ends.exits[(x, y)] = moves.iter().next();
+            for dir in moves {
+                stack.push(dir.step((x, y)));
+            }
+        }
+
+        (map, dead_ends)
+    }
+}
+
+/// Returns the directions which can be moved in given a cell's neighborhood, without cutting
+/// corners.
+fn moves(nb: EnumSet<Direction>) -> EnumSet<Direction> {
+    use Direction::*;
+
+    let mut moves = nb & Direction::ORTHO;
+    for (dir, corners) in [
+        (NorthWest, North | West),
+        (SouthWest, South | West),
+        (SouthEast, South | East),
+        (NorthEast, North | East),
+    ] {
+        if nb.is_superset(dir | corners) {
+            moves |= dir;
+        }
+    }
+    moves
+}
+
+#[test]
+fn pruning_preserves_distances() {
+    use crate::exact_distance;
+
+    // A room with a dead-end corridor leading off it, a room with a single-cell entrance, and an
+    // isolated cell.
+    let rows = [
+        "##########",
+        "#....#...#",
+        "#....#...#",
+        "#........#",
+        "#....#####",
+        "#.......##",
+        "#....#####",
+        "#######.##",
+        "##.####..#",
+        "##########",
+    ];
+    let map = BitGrid::from_str_rows(&rows, &['.']);
+
+    let (pruned, dead_ends) = map.prune_dead_ends();
+
+    // The corridor and the isolated cell are pruned, but the rooms aren't.
+    for x in 5..8 {
+        assert!(dead_ends.is_pruned(x, 5));
+    }
+    assert!(dead_ends.is_pruned(2, 8));
+    assert_eq!(dead_ends.exit(2, 8), None);
+    assert!(!dead_ends.is_pruned(4, 5));
+    assert!(!dead_ends.is_pruned(7, 2));
+    // The small component at the bottom is pruned entirely.
+    assert!(dead_ends.is_pruned(7, 7) && dead_ends.is_pruned(8, 8));
+
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            assert_eq!(
+                pruned.get(x, y),
+                map.get(x, y) && !dead_ends.is_pruned(x, y)
+            );
+        }
+    }
+
+    assert_eq!(dead_ends.path_out((7, 5)), [(7, 5), (6, 5), (5, 5), (4, 5)]);
+    assert_eq!(dead_ends.path_out((1, 1)), [(1, 1)]);
+
+    let cells = [(1, 1), (4, 5), (8, 1), (6, 2), (1, 6), (4, 3)];
+    for s in cells {
+        for t in cells {
+            assert_eq!(exact_distance(&pruned, s, t), exact_distance(&map, s, t));
+        }
+    }
+}
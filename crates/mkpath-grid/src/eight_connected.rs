@@ -1,18 +1,136 @@
 //! Types and utilities for working with 8-connected grid maps.
 
+use enumset::EnumSet;
 use mkpath_core::traits::Expander;
-use mkpath_core::{NodeMemberPointer, NodeRef};
+use mkpath_core::{NodeAllocator, NodeBuilder, NodeMemberPointer, NodeRef};
+use mkpath_ess::ExplicitStateSpace;
 
-use crate::{BitGrid, Direction, GridEdge, GridNodePool, SAFE_SQRT_2};
+use crate::{BitGrid, Direction, Grid, GridEdge, GridMap, GridNodePool, GridPool, SAFE_SQRT_2};
 
-pub struct EightConnectedExpander<'a, P> {
-    map: &'a BitGrid,
+/// Cost of an orthogonal move in the scaled integer approximation of the octile metric.
+pub const SCALED_ORTHOGONAL_COST: i64 = 5;
+/// Cost of a diagonal move in the scaled integer approximation of the octile metric.
+pub const SCALED_DIAGONAL_COST: i64 = 7;
+
+/// Move costs of an 8-connected grid.
+///
+/// Horizontal and vertical moves may have different costs, e.g. for isometric maps where tiles are
+/// wider than they are tall. The default is octile movement, `(1, 1, SAFE_SQRT_2)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metric {
+    /// Cost of a west or east move.
+    pub ortho_x: f64,
+    /// Cost of a north or south move.
+    pub ortho_y: f64,
+    /// Cost of a diagonal move.
+    pub diagonal: f64,
+}
+
+impl Metric {
+    pub const OCTILE: Metric = Metric {
+        ortho_x: 1.0,
+        ortho_y: 1.0,
+        diagonal: SAFE_SQRT_2,
+    };
+
+    /// Integer approximation of octile movement, with costs [`SCALED_ORTHOGONAL_COST`] and
+    /// [`SCALED_DIAGONAL_COST`].
+    ///
+    /// Every path cost is an exact integer, so with costs stored as `i64` (e.g.
+    /// `AStarSearcher::<i64>::with_cost_type`), ties are detected exactly and searches are
+    /// perfectly reproducible. [`octile_distance_scaled`] is the matching heuristic. This changes
+    /// absolute costs, and paths are optimal with respect to the approximation, which in rare cases
+    /// differs from the true octile metric.
+    pub const SCALED_OCTILE: Metric = Metric {
+        ortho_x: SCALED_ORTHOGONAL_COST as f64,
+        ortho_y: SCALED_ORTHOGONAL_COST as f64,
+        diagonal: SCALED_DIAGONAL_COST as f64,
+    };
+
+    /// Returns the cost of the shortest path between two cells on an empty map without borders.
+    ///
+    /// This is the heuristic matching an [`EightConnectedExpander`] using this metric. When
+    /// diagonal moves are cheaper than orthogonal ones, the shortest path may zig-zag diagonally
+    /// rather than move straight, and this is taken into account. Borders can only make paths
+    /// longer, so the heuristic is admissible on any map.
+    pub fn distance(&self, from: (i32, i32), to: (i32, i32)) -> f64 {
+        let dx = (from.0 - to.0).abs();
+        let dy = (from.1 - to.1).abs();
+        let (short, long, short_cost, long_cost) = match dx <= dy {
+            true => (dx, dy, self.ortho_x, self.ortho_y),
+            false => (dy, dx, self.ortho_y, self.ortho_x),
+        };
+        let (short_f, long_f) = (short as f64, long as f64);
+
+        // Only orthogonal moves.
+        let straight = short_f * short_cost + long_f * long_cost;
+        // One diagonal for each step along the short axis, then orthogonal moves.
+        let octile = short_f * self.diagonal + (long_f - short_f) * long_cost;
+        // One diagonal for each step along the long axis, zig-zagging along the short axis. If
+        // the parities differ, one move must be orthogonal, either in place of a diagonal or in
+        // addition to them.
+        let zigzag = match (long - short) % 2 {
+            0 => long_f * self.diagonal,
+            _ => (long_f * self.diagonal + short_cost)
+                .min((long_f - 1.0) * self.diagonal + long_cost),
+        };
+        straight.min(octile).min(zigzag)
+    }
+}
+
+impl Default for Metric {
+    fn default() -> Self {
+        Metric::OCTILE
+    }
+}
+
+pub struct EightConnectedExpander<'a, P, M = BitGrid> {
+    map: &'a M,
     node_pool: &'a P,
     state: NodeMemberPointer<(i32, i32)>,
+    metric: Metric,
+    restrictions: Option<&'a Grid<EnumSet<Direction>>>,
+    movement_mask: EnumSet<Direction>,
 }
 
-impl<'a, P: GridNodePool> EightConnectedExpander<'a, P> {
-    pub fn new(map: &'a BitGrid, node_pool: &'a P, state: NodeMemberPointer<(i32, i32)>) -> Self {
+impl<'a, P: GridNodePool, M: GridMap> EightConnectedExpander<'a, P, M> {
+    pub fn new(map: &'a M, node_pool: &'a P, state: NodeMemberPointer<(i32, i32)>) -> Self {
+        Self::with_metric(map, node_pool, state, Metric::OCTILE)
+    }
+
+    /// Creates an expander with custom orthogonal and diagonal move costs.
+    ///
+    /// Diagonal cost 1 gives Chebyshev movement, for which [`chebyshev_distance`] is the matching
+    /// heuristic. The heuristic must be chosen to match the costs; [`octile_distance`] is not
+    /// admissible with cheaper diagonals. JPS-based expanders always use octile costs.
+    ///
+    /// For integer costs approximating the octile metric, use [`Metric::SCALED_OCTILE`] with
+    /// [`EightConnectedExpander::with_metric`].
+    pub fn with_costs(
+        map: &'a M,
+        node_pool: &'a P,
+        state: NodeMemberPointer<(i32, i32)>,
+        orthogonal_cost: f64,
+        diagonal_cost: f64,
+    ) -> Self {
+        let metric = Metric {
+            ortho_x: orthogonal_cost,
+            ortho_y: orthogonal_cost,
+            diagonal: diagonal_cost,
+        };
+        Self::with_metric(map, node_pool, state, metric)
+    }
+
+    /// Creates an expander whose move costs are given by `metric`.
+    ///
+    /// The matching heuristic is [`metric_distance`]. JPS-based expanders only support octile
+    /// costs and can't be used with other metrics.
+    pub fn with_metric(
+        map: &'a M,
+        node_pool: &'a P,
+        state: NodeMemberPointer<(i32, i32)>,
+        metric: Metric,
+    ) -> Self {
         // Establish invariant that coordinates in-bounds of the map are also in-bounds of the
         // node pool.
         assert!(
@@ -28,11 +146,49 @@ impl<'a, P: GridNodePool> EightConnectedExpander<'a, P> {
             map,
             node_pool,
             state,
+            metric,
+            restrictions: None,
+            movement_mask: EnumSet::all(),
         }
     }
+
+    /// Doesn't leave a cell in any of the directions forbidden for it by `restrictions`.
+    ///
+    /// This models one-way movement such as conveyor belts or ledges that can be dropped from but
+    /// not climbed, and works with any metric. Note that the costs between two cells may then
+    /// differ by direction, so heuristics and preprocessed data assuming symmetric movement must
+    /// not be used. JPS-based expanders don't support restrictions.
+    ///
+    /// # Panics
+    /// Panics if `restrictions` does not have the same dimensions as the map.
+    #[track_caller]
+    pub fn with_restrictions(mut self, restrictions: &'a Grid<EnumSet<Direction>>) -> Self {
+        assert!(
+            restrictions.width() == self.map.width() && restrictions.height() == self.map.height(),
+            "restrictions must have the same dimensions as the map"
+        );
+        self.restrictions = Some(restrictions);
+        self
+    }
+
+    /// Only allows moves in the directions in `mask`, in addition to any restrictions.
+    ///
+    /// For example, [`Direction::ORTHO`] gives 4-connected movement, so the same map and node
+    /// pool can be used for units with different movement rules by creating the expander for each
+    /// search with the appropriate mask. Diagonal moves still may not cut corners. The default is
+    /// to allow all directions; heuristics such as [`octile_distance`] remain admissible with any
+    /// mask, though less informed.
+    pub fn with_movement_mask(mut self, mask: EnumSet<Direction>) -> Self {
+        self.movement_mask = mask;
+        self
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
 }
 
-impl<'a, P: GridNodePool> Expander<'a> for EightConnectedExpander<'a, P> {
+impl<'a, P: GridNodePool, M: GridMap> Expander<'a> for EightConnectedExpander<'a, P, M> {
     type Edge = GridEdge<'a>;
 
     fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<GridEdge<'a>>) {
@@ -43,6 +199,11 @@ impl<'a, P: GridNodePool> Expander<'a> for EightConnectedExpander<'a, P> {
             "attempt to expand node at untraversable location"
         );
 
+        let mut allowed = self.movement_mask;
+        if let Some(restrictions) = self.restrictions {
+            allowed -= restrictions[(x, y)];
+        }
+
         unsafe {
             // Since x, y is traversable, these are all padded in-bounds, as required by
             // get_unchecked.
@@ -51,71 +212,148 @@ impl<'a, P: GridNodePool> Expander<'a> for EightConnectedExpander<'a, P> {
             // therefore is also in-bounds of the node pool.
 
             let north_traversable = self.map.get_unchecked(x, y - 1);
-            if north_traversable {
+            if north_traversable && allowed.contains(Direction::North) {
                 edges.push(GridEdge {
                     successor: self.node_pool.generate_unchecked((x, y - 1)),
-                    cost: 1.0,
+                    cost: self.metric.ortho_y,
                     direction: Direction::North,
                 });
             }
 
             let south_traversable = self.map.get_unchecked(x, y + 1);
-            if south_traversable {
+            if south_traversable && allowed.contains(Direction::South) {
                 edges.push(GridEdge {
                     successor: self.node_pool.generate_unchecked((x, y + 1)),
-                    cost: 1.0,
+                    cost: self.metric.ortho_y,
                     direction: Direction::South,
                 });
             }
 
             if self.map.get_unchecked(x - 1, y) {
-                edges.push(GridEdge {
-                    successor: self.node_pool.generate_unchecked((x - 1, y)),
-                    cost: 1.0,
-                    direction: Direction::West,
-                });
+                if allowed.contains(Direction::West) {
+                    edges.push(GridEdge {
+                        successor: self.node_pool.generate_unchecked((x - 1, y)),
+                        cost: self.metric.ortho_x,
+                        direction: Direction::West,
+                    });
+                }
 
-                if north_traversable && self.map.get_unchecked(x - 1, y - 1) {
+                if north_traversable
+                    && allowed.contains(Direction::NorthWest)
+                    && self.map.get_unchecked(x - 1, y - 1)
+                {
                     edges.push(GridEdge {
                         successor: self.node_pool.generate_unchecked((x - 1, y - 1)),
-                        cost: SAFE_SQRT_2,
+                        cost: self.metric.diagonal,
                         direction: Direction::NorthWest,
                     });
                 }
 
-                if south_traversable && self.map.get_unchecked(x - 1, y + 1) {
+                if south_traversable
+                    && allowed.contains(Direction::SouthWest)
+                    && self.map.get_unchecked(x - 1, y + 1)
+                {
                     edges.push(GridEdge {
                         successor: self.node_pool.generate_unchecked((x - 1, y + 1)),
-                        cost: SAFE_SQRT_2,
+                        cost: self.metric.diagonal,
                         direction: Direction::SouthWest,
                     });
                 }
             }
             if self.map.get_unchecked(x + 1, y) {
-                edges.push(GridEdge {
-                    successor: self.node_pool.generate_unchecked((x + 1, y)),
-                    cost: 1.0,
-                    direction: Direction::East,
-                });
+                if allowed.contains(Direction::East) {
+                    edges.push(GridEdge {
+                        successor: self.node_pool.generate_unchecked((x + 1, y)),
+                        cost: self.metric.ortho_x,
+                        direction: Direction::East,
+                    });
+                }
 
-                if north_traversable && self.map.get_unchecked(x + 1, y - 1) {
+                if north_traversable
+                    && allowed.contains(Direction::NorthEast)
+                    && self.map.get_unchecked(x + 1, y - 1)
+                {
                     edges.push(GridEdge {
                         successor: self.node_pool.generate_unchecked((x + 1, y - 1)),
-                        cost: SAFE_SQRT_2,
+                        cost: self.metric.diagonal,
                         direction: Direction::NorthEast,
                     });
                 }
 
-                if south_traversable && self.map.get_unchecked(x + 1, y + 1) {
+                if south_traversable
+                    && allowed.contains(Direction::SouthEast)
+                    && self.map.get_unchecked(x + 1, y + 1)
+                {
                     edges.push(GridEdge {
                         successor: self.node_pool.generate_unchecked((x + 1, y + 1)),
-                        cost: SAFE_SQRT_2,
+                        cost: self.metric.diagonal,
                         direction: Direction::SouthEast,
                     });
                 }
             }
         }
     }
+
+    fn max_degree(&self) -> usize {
+        8
+    }
+}
+
+/// An 8-connected grid map with the octile costs of [`EightConnectedExpander::new`], as an
+/// explicit state space for building differential heuristics and other preprocessed data.
+///
+/// Unlike [`SixteenConnectedGrid`](crate::SixteenConnectedGrid), this borrows the map, so it is
+/// free to create for a map which is also used for other things.
+#[derive(Clone, Copy)]
+pub struct EightConnectedGrid<'m>(pub &'m BitGrid);
+
+impl ExplicitStateSpace for EightConnectedGrid<'_> {
+    type State = (i32, i32);
+
+    type Auxiliary<T> = Grid<T>;
+
+    type NodePool = GridPool;
+
+    type Expander<'a>
+        = EightConnectedExpander<'a, GridPool>
+    where
+        Self: 'a;
+
+    fn new_auxiliary<T>(&self, mut init: impl FnMut((i32, i32)) -> T) -> Grid<T> {
+        Grid::new(self.0.width(), self.0.height(), |x, y| init((x, y)))
+    }
+
+    fn add_state_field(&self, builder: &mut NodeBuilder) -> NodeMemberPointer<(i32, i32)> {
+        builder.add_field((-1, -1))
+    }
+
+    fn new_node_pool(
+        &self,
+        alloc: NodeAllocator,
+        state: NodeMemberPointer<(i32, i32)>,
+    ) -> GridPool {
+        GridPool::new(alloc, state, self.0.width(), self.0.height())
+    }
+
+    fn new_expander<'a>(
+        &'a self,
+        node_pool: &'a GridPool,
+        state: NodeMemberPointer<(i32, i32)>,
+    ) -> Self::Expander<'a> {
+        EightConnectedExpander::new(self.0, node_pool, state)
+    }
+
+    fn list_valid_states(&self) -> Vec<(i32, i32)> {
+        let mut res = vec![];
+        for y in 0..self.0.height() {
+            for x in 0..self.0.width() {
+                if self.0.get(x, y) {
+                    res.push((x, y));
+                }
+            }
+        }
+        res
+    }
 }
 
 pub fn octile_distance(from: (i32, i32), to: (i32, i32)) -> f64 {
@@ -125,3 +363,142 @@ pub fn octile_distance(from: (i32, i32), to: (i32, i32)) -> f64 {
     let orthos = dx.max(dy) - diagonals;
     orthos as f64 + diagonals as f64 * SAFE_SQRT_2
 }
+
+/// Distance when diagonal moves cost the same as orthogonal moves, i.e. `max(dx, dy)`.
+///
+/// This is the heuristic matching an [`EightConnectedExpander`] with orthogonal and diagonal cost
+/// 1; see [`EightConnectedExpander::with_costs`].
+pub fn chebyshev_distance(from: (i32, i32), to: (i32, i32)) -> f64 {
+    let dx = (from.0 - to.0).abs();
+    let dy = (from.1 - to.1).abs();
+    dx.max(dy) as f64
+}
+
+/// Distance when only orthogonal moves of cost 1 are allowed, i.e. `dx + dy`.
+///
+/// This is the heuristic matching an [`EightConnectedExpander`] whose movement mask only allows
+/// orthogonal moves; see [`EightConnectedExpander::with_movement_mask`].
+pub fn manhattan_distance(from: (i32, i32), to: (i32, i32)) -> f64 {
+    let dx = (from.0 - to.0).abs();
+    let dy = (from.1 - to.1).abs();
+    (dx + dy) as f64
+}
+
+/// Straight-line distance between cell centers, i.e. `sqrt(dx² + dy²)`, scaled down very slightly
+/// so that it never exceeds [`octile_distance`].
+///
+/// This is the heuristic for any-angle searches such as [`ThetaStar`](crate::ThetaStar), whose
+/// path costs are Euclidean. Diagonal moves cost [`SAFE_SQRT_2`], which is about `2.4e-8` below
+/// `sqrt(2)`, so the exact straight-line distance would overestimate diagonal paths. Scaling by
+/// `SAFE_SQRT_2 / sqrt(2)`, less a margin for rounding, makes this admissible for 8-connected
+/// searches with the default octile costs, though it is less informed than [`octile_distance`]
+/// there. It is not admissible for expanders with cheaper moves, e.g. with diagonal cost 1 as for
+/// [`chebyshev_distance`].
+pub fn euclidean_distance(from: (i32, i32), to: (i32, i32)) -> f64 {
+    /// Ratio of [`SAFE_SQRT_2`] to `sqrt(2)`, less a few ulps to spare for rounding.
+    const SCALE: f64 = SAFE_SQRT_2 / std::f64::consts::SQRT_2 * (1.0 - 4.0 * f64::EPSILON);
+
+    let dx = (from.0 - to.0) as f64;
+    let dy = (from.1 - to.1) as f64;
+    dx.hypot(dy) * SCALE
+}
+
+/// Shortest distance on an empty map under `metric`; see [`Metric::distance`].
+pub fn metric_distance(metric: Metric, from: (i32, i32), to: (i32, i32)) -> f64 {
+    metric.distance(from, to)
+}
+
+/// Computes [`octile_distance`] from `from` to each of `targets`, storing the results in `out`.
+///
+/// This is faster than calling [`octile_distance`] in a loop when there are many targets, as when
+/// taking the minimum heuristic over several goals, since the loop can be vectorized.
+///
+/// Panics if `targets` and `out` have different lengths.
+#[track_caller]
+pub fn octile_distances(from: (i32, i32), targets: &[(i32, i32)], out: &mut [f64]) {
+    bulk_distances(octile_distance, from, targets, out);
+}
+
+/// Computes [`chebyshev_distance`] from `from` to each of `targets`; see [`octile_distances`].
+///
+/// Panics if `targets` and `out` have different lengths.
+#[track_caller]
+pub fn chebyshev_distances(from: (i32, i32), targets: &[(i32, i32)], out: &mut [f64]) {
+    bulk_distances(chebyshev_distance, from, targets, out);
+}
+
+/// Computes [`manhattan_distance`] from `from` to each of `targets`; see [`octile_distances`].
+///
+/// Panics if `targets` and `out` have different lengths.
+#[track_caller]
+pub fn manhattan_distances(from: (i32, i32), targets: &[(i32, i32)], out: &mut [f64]) {
+    bulk_distances(manhattan_distance, from, targets, out);
+}
+
+#[track_caller]
+#[inline(always)]
+fn bulk_distances(
+    distance: impl Fn((i32, i32), (i32, i32)) -> f64,
+    from: (i32, i32),
+    targets: &[(i32, i32)],
+    out: &mut [f64],
+) {
+    assert_eq!(
+        targets.len(),
+        out.len(),
+        "output must have one entry per target"
+    );
+    for (out, &target) in out.iter_mut().zip(targets) {
+        *out = distance(from, target);
+    }
+}
+
+/// Octile distance using the scaled integer costs [`SCALED_ORTHOGONAL_COST`] and
+/// [`SCALED_DIAGONAL_COST`], the heuristic matching [`Metric::SCALED_OCTILE`].
+pub fn octile_distance_scaled(from: (i32, i32), to: (i32, i32)) -> i64 {
+    let dx = (from.0 - to.0).abs() as i64;
+    let dy = (from.1 - to.1).abs() as i64;
+    let diagonals = dx.min(dy);
+    let orthos = dx.max(dy) - diagonals;
+    orthos * SCALED_ORTHOGONAL_COST + diagonals * SCALED_DIAGONAL_COST
+}
+
+#[test]
+fn bulk_distances_match_scalar() {
+    let from = (7, -3);
+    let targets: Vec<_> = (0..37).map(|i| (i * 5 % 23 - 4, i * 3 % 17 - 9)).collect();
+    let mut out = vec![0.0; targets.len()];
+
+    octile_distances(from, &targets, &mut out);
+    for (&target, &d) in targets.iter().zip(&out) {
+        assert_eq!(d, octile_distance(from, target));
+    }
+    chebyshev_distances(from, &targets, &mut out);
+    for (&target, &d) in targets.iter().zip(&out) {
+        assert_eq!(d, chebyshev_distance(from, target));
+    }
+    manhattan_distances(from, &targets, &mut out);
+    for (&target, &d) in targets.iter().zip(&out) {
+        assert_eq!(d, manhattan_distance(from, target));
+    }
+    assert_eq!(manhattan_distance((1, 2), (4, -2)), 7.0);
+}
+
+#[test]
+fn euclidean_distance_is_admissible() {
+    let from = (3, -2);
+    for dx in -40..=40 {
+        for dy in -40..=40 {
+            let target = (from.0 + dx, from.1 + dy);
+            let h = euclidean_distance(from, target);
+            let cost = octile_distance(from, target);
+            assert!(h <= cost, "({dx}, {dy}): {h} > {cost}");
+        }
+    }
+    // Diagonal runs are where the exact straight-line distance would overestimate.
+    for d in [1, 2, 3, 7, 100, 1000, 12345, 1 << 20] {
+        for target in [(d, d), (-d, d), (d, -d)] {
+            assert!(euclidean_distance((0, 0), target) <= octile_distance((0, 0), target));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use mkpath_core::traits::Expander;
+use mkpath_core::NodeBuilder;
+
+use crate::{BatchDijkstra, BitGrid, Direction, EightConnectedExpander, Grid, GridPool};
+
+/// Computes a flow field towards `goal` on an 8-connected grid.
+///
+/// Each traversable cell from which `goal` is reachable gets the direction of the first move of
+/// an optimal path to `goal`, so any number of agents can reach the goal by following the arrows
+/// without searching individually. The goal itself and unreachable cells are `None`. When several
+/// moves are optimal, orthogonal moves are preferred over diagonal ones.
+///
+/// This runs a single Dijkstra search backwards from the goal, in contrast to CPDs, which store
+/// first moves towards every target.
+pub fn flow_field(map: &BitGrid, goal: (i32, i32)) -> Grid<Option<Direction>> {
+    let mut field = Grid::new_default(map.width(), map.height());
+    let distance = distance_from(map, goal);
+
+    let mut builder = NodeBuilder::new();
+    let state = builder.add_field((-1, -1));
+    let pool = GridPool::new(builder.build(), state, map.width(), map.height());
+    let mut expander = EightConnectedExpander::new(map, &pool, state);
+    let mut edges = vec![];
+
+    // Movement is symmetric, so distances from the goal are distances to the goal.
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            if (x, y) == goal || distance[(x, y)].is_infinite() {
+                continue;
+            }
+
+            edges.clear();
+            expander.expand(pool.generate((x, y)), &mut edges);
+
+            let mut best: Option<(f64, Direction)> = None;
+            for edge in &edges {
+                let value = distance[edge.successor.get(state)] + edge.cost;
+                let better = match best {
+                    None => true,
+                    Some((best_value, best_dir)) => {
+                        value < best_value - 1e-9
+                            || value <= best_value + 1e-9
+                                && edge.direction.orthogonal()
+                                && best_dir.is_diagonal()
+                    }
+                };
+                if better {
+                    best = Some((value, edge.direction));
+                }
+            }
+            field[(x, y)] = best.map(|(_, dir)| dir);
+        }
+    }
+
+    field
+}
+
+/// Computes the octile distance from `source` to every cell of an 8-connected grid.
+///
+/// Unreachable cells, obstacles, and every cell if `source` is not traversable, get
+/// [`f64::INFINITY`]. This runs a single Dijkstra search, so it is much cheaper than searching for
+/// each cell separately, e.g. to get the ground truth for many queries from the same source.
+///
+/// See [`BatchDijkstra::distances`], which reuses its node pool across queries.
+pub fn distance_from(map: &BitGrid, source: (i32, i32)) -> Grid<f64> {
+    BatchDijkstra::new(map.width(), map.height()).distances(map, source)
+}
+
+#[test]
+fn distances_match_octile_on_open_map() {
+    use crate::{exact_distance, octile_distance};
+
+    let mut map = BitGrid::new(12, 9);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, true);
+        }
+    }
+    let distance = distance_from(&map, (3, 5));
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            assert!((distance[(x, y)] - octile_distance((3, 5), (x, y))).abs() < 1e-9);
+        }
+    }
+
+    let walled = BitGrid::random_obstacles(20, 14, 0.3, 4);
+    let source = ((0..20).find(|&x| walled.get(x, 7)).unwrap(), 7);
+    let distance = distance_from(&walled, source);
+    let field = flow_field(&walled, source);
+    for y in 0..walled.height() {
+        for x in 0..walled.width() {
+            let expected = exact_distance(&walled, source, (x, y));
+            assert!(expected == distance[(x, y)] || (expected - distance[(x, y)]).abs() < 1e-9);
+            // Following the flow field gets closer to the source.
+            if let Some(dir) = field[(x, y)] {
+                assert!(distance[dir.step((x, y))] < distance[(x, y)]);
+            }
+        }
+    }
+
+    let blocked = ((0..20).find(|&x| !walled.get(x, 7)).unwrap(), 7);
+    let distance = distance_from(&walled, blocked);
+    assert!(distance.storage().iter().all(|d| d.is_infinite()));
+}
@@ -19,6 +19,28 @@ impl<T> Grid<T> {
         }
     }
 
+    /// Creates a grid with every cell set to `value`.
+    pub fn filled(width: i32, height: i32, value: T) -> Self
+    where
+        T: Clone,
+    {
+        let w: usize = width.try_into().expect("width must be non-negative");
+        let h: usize = height.try_into().expect("height must be non-negative");
+        Grid {
+            width,
+            height,
+            cells: vec![value; w * h].into_boxed_slice(),
+        }
+    }
+
+    /// Creates a grid with every cell set to its default.
+    pub fn new_default(width: i32, height: i32) -> Self
+    where
+        T: Default,
+    {
+        Grid::new(width, height, |_, _| T::default())
+    }
+
     pub fn width(&self) -> i32 {
         self.width
     }
@@ -27,6 +49,58 @@ impl<T> Grid<T> {
         self.height
     }
 
+    /// Returns whether the coordinates are in-bounds of the grid.
+    #[inline(always)]
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    /// Returns a reference to a cell of the grid, or `None` if the coordinates are out of bounds.
+    #[inline(always)]
+    pub fn get(&self, x: i32, y: i32) -> Option<&T> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        // SAFETY: The coordinates were just checked to be in-bounds.
+        Some(unsafe { self.get_unchecked(x, y) })
+    }
+
+    /// Returns a mutable reference to a cell of the grid, or `None` if the coordinates are out of
+    /// bounds.
+    #[inline(always)]
+    pub fn get_mut(&mut self, x: i32, y: i32) -> Option<&mut T> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        // SAFETY: The coordinates were just checked to be in-bounds.
+        Some(unsafe { self.get_unchecked_mut(x, y) })
+    }
+
+    /// Sets every cell of the grid to `value`.
+    ///
+    /// Reusing a grid this way avoids reallocating its storage, which matters when auxiliary grids
+    /// are needed for many searches or oracles over the same map.
+    pub fn fill(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        self.cells.fill(value);
+    }
+
+    /// Creates a grid with the same dimensions as this one with every cell set to its default.
+    pub fn clone_with_default<U: Default>(&self) -> Grid<U> {
+        Grid::new_default(self.width, self.height)
+    }
+
+    /// Returns the transpose of the grid, in which the cell `(x, y)` is the cell `(y, x)` of this
+    /// grid.
+    pub fn transpose(&self) -> Grid<T>
+    where
+        T: Clone,
+    {
+        Grid::new(self.height, self.width, |x, y| self[(y, x)].clone())
+    }
+
     pub fn storage(&self) -> &[T] {
         &self.cells
     }
@@ -94,3 +168,49 @@ impl<T> std::ops::IndexMut<(i32, i32)> for Grid<T> {
         unsafe { self.get_unchecked_mut(x, y) }
     }
 }
+
+#[test]
+fn checked_access_near_edges() {
+    let mut grid = Grid::new(3, 2, |x, y| x + 10 * y);
+    assert!(grid.in_bounds(0, 0));
+    assert!(grid.in_bounds(2, 1));
+    assert!(!grid.in_bounds(-1, 0));
+    assert!(!grid.in_bounds(3, 0));
+    assert!(!grid.in_bounds(0, 2));
+
+    assert_eq!(grid.get(2, 1), Some(&12));
+    assert_eq!(grid.get(3, 1), None);
+    assert_eq!(grid.get(0, -1), None);
+
+    *grid.get_mut(1, 1).unwrap() = 0;
+    assert_eq!(grid[(1, 1)], 0);
+    assert!(grid.get_mut(-1, -1).is_none());
+}
+
+#[test]
+fn constant_constructors() {
+    let filled = Grid::filled(4, 3, usize::MAX);
+    assert_eq!((filled.width(), filled.height()), (4, 3));
+    assert!(filled.storage().iter().all(|&v| v == usize::MAX));
+    assert_eq!(filled.storage().len(), 12);
+
+    let default: Grid<Option<u8>> = Grid::new_default(2, 5);
+    assert_eq!((default.width(), default.height()), (2, 5));
+    assert!(default.storage().iter().all(Option::is_none));
+
+    assert!(Grid::filled(0, 7, 1).storage().is_empty());
+}
+
+#[test]
+fn transpose_swaps_coordinates() {
+    let grid = Grid::new(5, 3, |x, y| x * 10 + y);
+    let transposed = grid.transpose();
+    assert_eq!((transposed.width(), transposed.height()), (3, 5));
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            assert_eq!(transposed[(y, x)], grid[(x, y)]);
+        }
+    }
+    assert_eq!(transposed.transpose().storage(), grid.storage());
+    assert_eq!(Grid::<u8>::new_default(0, 4).transpose().width(), 4);
+}
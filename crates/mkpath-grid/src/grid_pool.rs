@@ -7,6 +7,25 @@ use mkpath_core::{Node, NodeAllocator, NodeMemberPointer, NodeRef};
 use super::grid::Grid;
 use super::GridNodePool;
 
+/// Node pool for grid maps which deduplicates states using a dense per-cell table.
+///
+/// Lookups are a single array access, and resetting the pool is O(1), but the table uses memory
+/// proportional to the size of the map regardless of how many nodes a search generates. For
+/// searches which only touch a small part of a large map, a `HashPool` may be preferable.
+///
+/// ```
+/// # use mkpath_core::NodeBuilder;
+/// # use mkpath_grid::GridPool;
+/// let mut builder = NodeBuilder::new();
+/// let state = builder.add_field((-1, -1));
+/// let mut pool = GridPool::new(builder.build(), state, 10, 10);
+///
+/// let a = pool.generate((3, 4));
+/// assert!(a.ptr_eq(pool.generate((3, 4))));
+///
+/// pool.reset();
+/// assert!(pool.get((3, 4)).is_none());
+/// ```
 pub struct GridPool {
     state_map: Grid<Cell<(u64, *mut Node)>>,
     search_number: u64,
@@ -29,12 +48,42 @@ impl GridPool {
 
         GridPool {
             search_number: 1,
-            state_map: Grid::new(width, height, |_, _| Cell::new((0, std::ptr::null_mut()))),
+            state_map: Grid::filled(width, height, Cell::new((0, std::ptr::null_mut()))),
             state_field,
             allocator,
         }
     }
 
+    /// Resets the pool, invalidating all nodes generated since the last reset.
+    ///
+    /// This is the same as [`NodePool::reset`], but doesn't require importing the trait.
+    pub fn reset(&mut self) {
+        self.search_number = self.search_number.checked_add(1).unwrap_or_else(|| {
+            self.state_map
+                .storage_mut()
+                .fill(Cell::new((0, std::ptr::null_mut())));
+            1
+        });
+        self.allocator.reset();
+    }
+
+    /// Resets the pool, ensuring that at least `capacity` nodes can be generated before the node
+    /// allocator needs to request more memory.
+    ///
+    /// See [`NodeBuilder::build_with_capacity`](mkpath_core::NodeBuilder::build_with_capacity).
+    pub fn reset_with_capacity(&mut self, capacity: usize) {
+        self.reset();
+        self.allocator.reset_with_capacity(capacity);
+    }
+
+    /// Resets the pool, releasing node memory beyond what is needed to generate `capacity` nodes.
+    ///
+    /// See [`NodeAllocator::shrink_to`](mkpath_core::NodeAllocator::shrink_to).
+    pub fn shrink_to(&mut self, capacity: usize) {
+        self.reset();
+        self.allocator.shrink_to(capacity);
+    }
+
     #[inline(always)]
     pub fn width(&self) -> i32 {
         self.state_map.width()
@@ -45,6 +94,21 @@ impl GridPool {
         self.state_map.height()
     }
 
+    /// Returns the field which nodes generated by this pool store their state in.
+    pub fn state_field(&self) -> NodeMemberPointer<(i32, i32)> {
+        self.state_field
+    }
+
+    /// Retrieves the node for the specified state or generates one if it does not exist.
+    ///
+    /// This is the same as [`NodePool::generate`], but doesn't require importing the trait.
+    #[track_caller]
+    #[inline(always)]
+    pub fn generate(&self, state: (i32, i32)) -> NodeRef<'_> {
+        let _ = self.state_map[state];
+        unsafe { self.generate_unchecked(state) }
+    }
+
     #[track_caller]
     #[inline(always)]
     pub fn get(&self, state: (i32, i32)) -> Option<NodeRef> {
@@ -100,18 +164,11 @@ impl NodePool for GridPool {
     type State = (i32, i32);
 
     fn reset(&mut self) {
-        self.search_number = self.search_number.checked_add(1).unwrap_or_else(|| {
-            self.state_map
-                .storage_mut()
-                .fill(Cell::new((0, std::ptr::null_mut())));
-            1
-        });
-        self.allocator.reset();
+        self.reset()
     }
 
     fn generate(&self, state: Self::State) -> NodeRef {
-        let _ = self.state_map[state];
-        unsafe { self.generate_unchecked(state) }
+        self.generate(state)
     }
 }
 
@@ -1,23 +1,52 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 //! 2D grid types and algorithms for `mkpath`.
 
+mod batch;
+mod bidirectional;
 mod bitgrid;
+mod d_star_lite;
+mod dead_ends;
 mod eight_connected;
+mod flow_field;
 mod grid;
 mod grid_pool;
+mod lpa_star;
+mod overlay;
+mod random;
+mod reference;
+mod sixteen_connected;
+mod stats;
+mod theta_star;
+mod validate;
 
-use enumset::EnumSetType;
+use enumset::{enum_set, EnumSet, EnumSetIter, EnumSetType};
 use mkpath_core::traits::{Cost, EdgeId, NodePool, Successor};
 use mkpath_core::{HashPool, NodeRef, NullPool};
 
+pub use self::batch::*;
+pub use self::bidirectional::*;
 pub use self::bitgrid::*;
+pub use self::d_star_lite::*;
+pub use self::dead_ends::*;
 pub use self::eight_connected::*;
+pub use self::flow_field::*;
 pub use self::grid::*;
 pub use self::grid_pool::*;
+pub use self::lpa_star::*;
+pub use self::overlay::*;
+pub use self::random::*;
+pub use self::reference::*;
+pub use self::sixteen_connected::*;
+pub use self::stats::*;
+pub use self::theta_star::*;
+pub use self::validate::*;
 
 pub const SAFE_SQRT_2: f64 = std::f32::consts::SQRT_2 as f64;
+/// [`SAFE_SQRT_2`] as an `f32`. The two have the same value, so diagonal costs agree exactly
+/// between searches storing costs as `f32` and as `f64`.
+pub const SAFE_SQRT_2_F32: f32 = std::f32::consts::SQRT_2;
 
-#[derive(EnumSetType, Debug)]
+#[derive(EnumSetType, Debug, Hash)]
 pub enum Direction {
     North,
     West,
@@ -33,21 +62,93 @@ impl TryFrom<usize> for Direction {
     type Error = ();
 
     fn try_from(value: usize) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(Direction::North),
-            1 => Ok(Direction::West),
-            2 => Ok(Direction::South),
-            3 => Ok(Direction::East),
-            4 => Ok(Direction::NorthWest),
-            5 => Ok(Direction::SouthWest),
-            6 => Ok(Direction::SouthEast),
-            7 => Ok(Direction::NorthEast),
-            _ => Err(()),
-        }
+        Direction::ALL.get(value).copied().ok_or(())
     }
 }
 
 impl Direction {
+    /// All directions, in the order of their edge ids. `Direction::ALL[d as usize] == d` holds for
+    /// every direction `d`.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::West,
+        Direction::South,
+        Direction::East,
+        Direction::NorthWest,
+        Direction::SouthWest,
+        Direction::SouthEast,
+        Direction::NorthEast,
+    ];
+
+    /// The orthogonal directions, in the order of their edge ids. These are the first four
+    /// entries of [`Direction::ALL`].
+    pub const ORTHOGONAL: [Direction; 4] = [
+        Direction::North,
+        Direction::West,
+        Direction::South,
+        Direction::East,
+    ];
+
+    /// The diagonal directions, in the order of their edge ids. These are the last four entries
+    /// of [`Direction::ALL`].
+    pub const DIAGONAL: [Direction; 4] = [
+        Direction::NorthWest,
+        Direction::SouthWest,
+        Direction::SouthEast,
+        Direction::NorthEast,
+    ];
+
+    /// The set of orthogonal directions.
+    pub const ORTHO: EnumSet<Direction> =
+        enum_set!(Direction::North | Direction::West | Direction::South | Direction::East);
+
+    /// The set of diagonal directions.
+    pub const DIAG: EnumSet<Direction> = enum_set!(
+        Direction::NorthWest | Direction::SouthWest | Direction::SouthEast | Direction::NorthEast
+    );
+
+    /// Converts an edge id, as produced by [`GridEdge`] or stored in CPDs, back into a direction.
+    pub fn from_edge_id(id: usize) -> Option<Direction> {
+        Direction::ALL.get(id).copied()
+    }
+
+    /// Returns the cell one step in this direction from `(x, y)`.
+    pub fn step(self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (dx, dy) = self.vector();
+        (x + dx, y + dy)
+    }
+
+    /// Returns the direction of the step from `from` to `to`, or `None` if `to` is not one of the
+    /// 8 neighbors of `from`.
+    ///
+    /// Unlike `reached_direction` in `mkpath-jps`, which infers the direction of travel between
+    /// cells which may be far apart, this only accepts adjacent cells, as when walking a path one
+    /// step at a time.
+    pub fn between(from: (i32, i32), to: (i32, i32)) -> Option<Direction> {
+        let dx = to.0.checked_sub(from.0)?;
+        let dy = to.1.checked_sub(from.1)?;
+        Direction::from_vector((dx, dy))
+    }
+
+    /// Returns the direction whose [`Direction::vector`] is `(dx, dy)`, or `None` if it is not
+    /// one of the 8 unit vectors.
+    ///
+    /// To find the direction of a straight segment of any length, pass the signs of its offsets,
+    /// e.g. `Direction::from_vector((dx.signum(), dy.signum()))`.
+    pub fn from_vector((dx, dy): (i32, i32)) -> Option<Direction> {
+        match (dx, dy) {
+            (0, -1) => Some(Direction::North),
+            (-1, 0) => Some(Direction::West),
+            (0, 1) => Some(Direction::South),
+            (1, 0) => Some(Direction::East),
+            (-1, -1) => Some(Direction::NorthWest),
+            (-1, 1) => Some(Direction::SouthWest),
+            (1, 1) => Some(Direction::SouthEast),
+            (1, -1) => Some(Direction::NorthEast),
+            _ => None,
+        }
+    }
+
     pub fn backwards(self) -> Direction {
         match self {
             Direction::North => Direction::South,
@@ -75,10 +176,38 @@ impl Direction {
     }
 
     pub fn orthogonal(self) -> bool {
-        matches!(
-            self,
-            Direction::North | Direction::East | Direction::South | Direction::West
-        )
+        Direction::ORTHO.contains(self)
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        !self.orthogonal()
+    }
+}
+
+/// Extension methods for sets of directions.
+pub trait DirectionSetExt {
+    /// Iterates over the `(dx, dy)` offsets of the directions in the set.
+    fn vectors(self) -> DirectionVectors;
+}
+
+impl DirectionSetExt for EnumSet<Direction> {
+    fn vectors(self) -> DirectionVectors {
+        DirectionVectors(self.iter())
+    }
+}
+
+/// Iterator over the offsets of a set of directions, created by [`DirectionSetExt::vectors`].
+pub struct DirectionVectors(EnumSetIter<Direction>);
+
+impl Iterator for DirectionVectors {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<(i32, i32)> {
+        self.0.next().map(Direction::vector)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
     }
 }
 
@@ -154,3 +283,50 @@ impl GridNodePool for HashPool<(i32, i32)> {
         self.generate(state)
     }
 }
+
+#[test]
+fn edge_ids_round_trip() {
+    for (i, &dir) in Direction::ALL.iter().enumerate() {
+        assert_eq!(dir as usize, i);
+        assert_eq!(Direction::from_edge_id(i), Some(dir));
+        assert_eq!(Direction::try_from(i), Ok(dir));
+    }
+    assert_eq!(Direction::from_edge_id(8), None);
+    assert_eq!(EnumSet::<Direction>::all().iter().collect::<Vec<_>>(), Direction::ALL);
+
+    assert_eq!(Direction::ORTHOGONAL, Direction::ALL[..4]);
+    assert_eq!(Direction::DIAGONAL, Direction::ALL[4..]);
+    assert_eq!(Direction::ORTHO.iter().collect::<Vec<_>>(), Direction::ORTHOGONAL);
+    assert_eq!(Direction::DIAG.iter().collect::<Vec<_>>(), Direction::DIAGONAL);
+    assert!(Direction::ORTHOGONAL.iter().all(|d| d.orthogonal()));
+    assert!(Direction::DIAGONAL.iter().all(|d| d.is_diagonal()));
+}
+
+#[test]
+fn direction_between_adjacent_cells() {
+    for dir in Direction::ALL {
+        assert_eq!(Direction::between((3, 5), dir.step((3, 5))), Some(dir));
+        assert_eq!(Direction::between(dir.step((3, 5)), (3, 5)), Some(dir.backwards()));
+    }
+
+    assert_eq!(Direction::between((3, 5), (3, 5)), None);
+    for far in [(5, 5), (3, 7), (1, 3), (4, 7), (0, 0)] {
+        assert_eq!(Direction::between((3, 5), far), None);
+    }
+    assert_eq!(Direction::between((i32::MAX, 0), (i32::MIN, 0)), None);
+}
+
+#[test]
+fn direction_from_vector_round_trips() {
+    for dir in Direction::ALL {
+        assert_eq!(Direction::from_vector(dir.vector()), Some(dir));
+    }
+    for dy in -2..=2 {
+        for dx in -2..=2 {
+            let expected = Direction::ALL.into_iter().find(|d| d.vector() == (dx, dy));
+            assert_eq!(Direction::from_vector((dx, dy)), expected);
+        }
+    }
+    assert_eq!(Direction::from_vector((0, 0)), None);
+    assert_eq!(Direction::from_vector((i32::MIN, 1)), None);
+}
@@ -0,0 +1,272 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use mkpath_core::traits::Expander;
+use mkpath_core::{NodeBuilder, NodeMemberPointer, NodeRef};
+
+use crate::{octile_distance, BitGrid, EightConnectedExpander, GridPool};
+
+/// Lifelong Planning A* (Koenig et al, 2004) on an 8-connected grid map.
+///
+/// LPA* repeatedly finds shortest paths between a fixed start and goal while cells of the map
+/// change traversability. After [`LpaStar::update_cell`], [`LpaStar::replan`] reuses the `g` and
+/// `rhs` values of the previous search and only repairs the part of the search affected by the
+/// change, which is typically much cheaper than searching from scratch.
+///
+/// Costs and the heuristic are octile, as with [`EightConnectedExpander::new`] and
+/// [`octile_distance`].
+pub struct LpaStar {
+    map: BitGrid,
+    node_pool: GridPool,
+    state: NodeMemberPointer<(i32, i32)>,
+    g: NodeMemberPointer<f64>,
+    rhs: NodeMemberPointer<f64>,
+    key: NodeMemberPointer<(f64, f64)>,
+    queued: NodeMemberPointer<bool>,
+    open: BinaryHeap<QueueEntry>,
+    start: (i32, i32),
+    goal: (i32, i32),
+}
+
+impl LpaStar {
+    pub fn new(map: BitGrid, start: (i32, i32), goal: (i32, i32)) -> Self {
+        let mut builder = NodeBuilder::new();
+        let state = builder.add_field((-1, -1));
+        let g = builder.add_field(f64::INFINITY);
+        let rhs = builder.add_field(f64::INFINITY);
+        let key = builder.add_field((f64::INFINITY, f64::INFINITY));
+        let queued = builder.add_field(false);
+        let node_pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+        let mut lpa = LpaStar {
+            map,
+            node_pool,
+            state,
+            g,
+            rhs,
+            key,
+            queued,
+            open: BinaryHeap::new(),
+            start,
+            goal,
+        };
+        lpa.update_vertex(start);
+        lpa
+    }
+
+    pub fn map(&self) -> &BitGrid {
+        &self.map
+    }
+
+    /// Changes the traversability of a cell.
+    ///
+    /// The change takes effect on the next call to [`LpaStar::replan`].
+    pub fn update_cell(&mut self, x: i32, y: i32, traversable: bool) {
+        if self.map.get(x, y) == traversable {
+            return;
+        }
+        self.map.set(x, y, traversable);
+
+        // Both the edges to the cell and diagonal edges cutting its corners are affected, all of
+        // which end in the 3x3 neighborhood of the cell.
+        for (nx, ny) in self.neighborhood((x, y)) {
+            self.update_vertex((nx, ny));
+        }
+    }
+
+    /// Finds a shortest path from the start to the goal on the current map.
+    ///
+    /// Returns the path as a list of cells and its cost, or `None` if the goal is unreachable.
+    pub fn replan(&mut self) -> Option<(Vec<(i32, i32)>, f64)> {
+        self.compute_shortest_path();
+
+        let cost = self.node_pool.generate(self.goal).get(self.g);
+        if cost.is_infinite() {
+            return None;
+        }
+
+        let mut path = vec![self.goal];
+        let mut current = self.goal;
+        while current != self.start {
+            let (predecessor, _) = self
+                .best_predecessor(current)
+                .expect("consistent node has no predecessor");
+            path.push(predecessor);
+            current = predecessor;
+        }
+        path.reverse();
+
+        Some((path, cost))
+    }
+
+    fn compute_shortest_path(&mut self) {
+        loop {
+            while let Some(&top) = self.open.peek() {
+                let node = self.node_pool.generate(top.state);
+                if node.get(self.queued) && node.get(self.key) == top.key {
+                    break;
+                }
+                // Stale entry of a node which was removed or reinserted with a different key.
+                self.open.pop();
+            }
+            let Some(&top) = self.open.peek() else {
+                break;
+            };
+
+            let goal = self.node_pool.generate(self.goal);
+            if !key_less(top.key, self.calculate_key(goal))
+                && goal.get(self.rhs) == goal.get(self.g)
+            {
+                break;
+            }
+
+            self.open.pop();
+            let node = self.node_pool.generate(top.state);
+            node.set(self.queued, false);
+
+            if node.get(self.g) > node.get(self.rhs) {
+                node.set(self.g, node.get(self.rhs));
+            } else {
+                node.set(self.g, f64::INFINITY);
+                self.update_vertex(top.state);
+            }
+            for neighbor in self.neighborhood(top.state) {
+                if neighbor != top.state {
+                    self.update_vertex(neighbor);
+                }
+            }
+        }
+    }
+
+    fn update_vertex(&mut self, cell: (i32, i32)) {
+        let rhs = if cell == self.start {
+            0.0
+        } else {
+            self.best_predecessor(cell)
+                .map_or(f64::INFINITY, |(_, cost)| cost)
+        };
+
+        let node = self.node_pool.generate(cell);
+        node.set(self.rhs, rhs);
+        node.set(self.queued, false);
+        if node.get(self.g) != rhs {
+            let key = self.calculate_key(node);
+            node.set(self.key, key);
+            node.set(self.queued, true);
+            self.open.push(QueueEntry { key, state: cell });
+        }
+    }
+
+    /// Returns the predecessor of `cell` minimizing `g + cost`, along with that value.
+    fn best_predecessor(&self, cell: (i32, i32)) -> Option<((i32, i32), f64)> {
+        if !self.map.get(cell.0, cell.1) {
+            return None;
+        }
+
+        // Movement is symmetric, so the predecessors of a cell are its successors.
+        let mut expander = EightConnectedExpander::new(&self.map, &self.node_pool, self.state);
+        let mut edges = Vec::with_capacity(8);
+        expander.expand(self.node_pool.generate(cell), &mut edges);
+
+        edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.successor.get(self.state),
+                    edge.successor.get(self.g) + edge.cost,
+                )
+            })
+            .filter(|&(_, cost)| cost.is_finite())
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    fn calculate_key(&self, node: NodeRef) -> (f64, f64) {
+        let g = node.get(self.g).min(node.get(self.rhs));
+        (g + octile_distance(node.get(self.state), self.goal), g)
+    }
+
+    fn neighborhood(&self, (x, y): (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+        let (width, height) = (self.map.width(), self.map.height());
+        (-1..=1)
+            .flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            .filter(move |&(x, y)| x >= 0 && y >= 0 && x < width && y < height)
+    }
+}
+
+pub(crate) fn key_less(a: (f64, f64), b: (f64, f64)) -> bool {
+    a.0 < b.0 || a.0 == b.0 && a.1 < b.1
+}
+
+/// Entry of a lazily-updated min-heap of incremental search keys.
+///
+/// Entries are not removed when their node's key changes; instead, entries whose key does not
+/// match the node's current key are discarded when they reach the top of the heap.
+#[derive(Clone, Copy)]
+pub(crate) struct QueueEntry {
+    pub key: (f64, f64),
+    pub state: (i32, i32),
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, since BinaryHeap is a max-heap.
+        other
+            .key
+            .0
+            .total_cmp(&self.key.0)
+            .then(other.key.1.total_cmp(&self.key.1))
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueueEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueueEntry {}
+
+#[test]
+fn replanning_matches_fresh_search() {
+    let mut map = BitGrid::new(16, 12);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, !(x == 7 && y < 9));
+        }
+    }
+    let (start, goal) = ((1, 1), (14, 2));
+
+    let mut lpa = LpaStar::new(map.clone(), start, goal);
+    let changes = [
+        vec![],
+        vec![(7, 9, false), (7, 10, false)],
+        vec![(7, 11, false)],
+        vec![(7, 2, true)],
+        vec![(6, 2, false), (8, 1, false), (8, 3, false)],
+        vec![(7, 2, false), (7, 11, true)],
+    ];
+    for change in changes {
+        for (x, y, traversable) in change {
+            lpa.update_cell(x, y, traversable);
+            map.set(x, y, traversable);
+        }
+        let expected = LpaStar::new(map.clone(), start, goal).replan();
+
+        let result = lpa.replan();
+        assert_eq!(
+            result.as_ref().map(|&(_, cost)| cost),
+            expected.map(|(_, cost)| cost)
+        );
+        if let Some((path, _)) = result {
+            assert_eq!(path.first(), Some(&start));
+            assert_eq!(path.last(), Some(&goal));
+            assert!(path.iter().all(|&(x, y)| map.get(x, y)));
+        }
+    }
+}
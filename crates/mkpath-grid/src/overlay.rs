@@ -0,0 +1,129 @@
+use ahash::HashSet;
+use enumset::EnumSet;
+
+use crate::{BitGrid, Direction, GridMap};
+
+/// A [`BitGrid`] with some cells temporarily blocked.
+///
+/// This is intended for transient obstacles such as other units, which change too often to rebuild
+/// preprocessed structures like jump databases or CPDs for. The base map is left untouched, so such
+/// structures built for it stay valid, while searches using the overlay through [`GridMap`] (e.g.
+/// [`EightConnectedExpander`](crate::EightConnectedExpander)) avoid the blocked cells.
+///
+/// Lookups cost a hash set probe when any cells are blocked, so this is best suited to small
+/// numbers of blocked cells.
+pub struct OverlayGrid<'a> {
+    base: &'a BitGrid,
+    blocked: HashSet<(i32, i32)>,
+}
+
+impl<'a> OverlayGrid<'a> {
+    pub fn new(base: &'a BitGrid) -> Self {
+        OverlayGrid {
+            base,
+            blocked: HashSet::default(),
+        }
+    }
+
+    pub fn base(&self) -> &'a BitGrid {
+        self.base
+    }
+
+    /// Temporarily blocks a cell.
+    #[track_caller]
+    pub fn block(&mut self, x: i32, y: i32) {
+        assert!(x >= 0 && x < self.base.width(), "x out of bounds");
+        assert!(y >= 0 && y < self.base.height(), "y out of bounds");
+        self.blocked.insert((x, y));
+    }
+
+    /// Removes a temporary block from a cell, restoring the traversability of the base map.
+    pub fn unblock(&mut self, x: i32, y: i32) {
+        self.blocked.remove(&(x, y));
+    }
+
+    /// Removes all temporary blocks.
+    pub fn clear(&mut self) {
+        self.blocked.clear();
+    }
+
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        self.blocked.contains(&(x, y))
+    }
+
+    /// Returns the temporarily blocked cells, in arbitrary order.
+    pub fn blocked(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.blocked.iter().copied()
+    }
+
+    #[inline(always)]
+    pub fn width(&self) -> i32 {
+        self.base.width()
+    }
+
+    #[inline(always)]
+    pub fn height(&self) -> i32 {
+        self.base.height()
+    }
+
+    #[track_caller]
+    #[inline(always)]
+    pub fn get(&self, x: i32, y: i32) -> bool {
+        self.base.get(x, y) && !self.is_blocked(x, y)
+    }
+
+    #[track_caller]
+    pub fn get_neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction> {
+        let mut nbhood = self.base.get_neighborhood(x, y);
+        if !self.blocked.is_empty() {
+            for dir in nbhood {
+                let (dx, dy) = dir.vector();
+                if self.is_blocked(x + dx, y + dy) {
+                    nbhood.remove(dir);
+                }
+            }
+        }
+        nbhood
+    }
+
+    /// Gets the traversability of a cell without bounds checking.
+    ///
+    /// # Safety
+    /// The coordinates must be in-bounds of the padded grid. Specifically:
+    /// - `x` is in `-1..=self.width()`
+    /// - `y` is in `-1..=self.height()`
+    #[inline(always)]
+    pub unsafe fn get_unchecked(&self, x: i32, y: i32) -> bool {
+        // SAFETY: The caller is responsible for ensuring that the coordinates are in-bounds.
+        unsafe { self.base.get_unchecked(x, y) && !self.is_blocked(x, y) }
+    }
+}
+
+// SAFETY: Blocking cells only ever removes traversability from the base map, and blocks are only
+//         placed on unpadded cells.
+unsafe impl GridMap for OverlayGrid<'_> {
+    #[inline(always)]
+    fn width(&self) -> i32 {
+        self.width()
+    }
+
+    #[inline(always)]
+    fn height(&self) -> i32 {
+        self.height()
+    }
+
+    #[inline(always)]
+    fn get(&self, x: i32, y: i32) -> bool {
+        self.get(x, y)
+    }
+
+    #[inline(always)]
+    fn get_neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction> {
+        self.get_neighborhood(x, y)
+    }
+
+    #[inline(always)]
+    unsafe fn get_unchecked(&self, x: i32, y: i32) -> bool {
+        unsafe { self.get_unchecked(x, y) }
+    }
+}
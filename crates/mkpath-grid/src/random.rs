@@ -0,0 +1,140 @@
+use crate::{BitGrid, Direction};
+
+impl BitGrid {
+    /// Generates a map where each cell is an obstacle with probability `density`.
+    ///
+    /// The map is determined by `seed`, so the same inputs always produce the same map, which
+    /// makes this suitable for reproducible tests and benchmarks.
+    ///
+    /// Panics if `density` is not in `0.0..=1.0`.
+    #[track_caller]
+    pub fn random_obstacles(width: i32, height: i32, density: f64, seed: u64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&density),
+            "density must be in 0..=1, but is {density}"
+        );
+        let mut rng = Rng::new(seed);
+        let mut map = BitGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                map.set(x, y, rng.next_f64() >= density);
+            }
+        }
+        map
+    }
+
+    /// Generates a perfect maze, i.e. one with exactly one path between any two traversable cells,
+    /// using recursive backtracking.
+    ///
+    /// Cells with both coordinates odd are the junctions of the maze, and corridors between them
+    /// are 1 cell wide. The outer rows and columns are obstacles, and so are the second-to-last
+    /// row or column if `height` or `width` is even. Diagonal moves are never possible, since they
+    /// would cut the corner of a wall. The maze is determined by `seed`.
+    pub fn random_maze(width: i32, height: i32, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut map = BitGrid::new(width, height);
+        if width < 3 || height < 3 {
+            return map;
+        }
+
+        map.set(1, 1, true);
+        let mut stack = vec![(1, 1)];
+        while let Some(&(x, y)) = stack.last() {
+            let mut unvisited = [(0, 0); 4];
+            let mut count = 0;
+            for dir in Direction::ORTHOGONAL {
+                let (dx, dy) = dir.vector();
+                let (nx, ny) = (x + 2 * dx, y + 2 * dy);
+                if nx > 0 && ny > 0 && nx < width - 1 && ny < height - 1 && !map.get(nx, ny) {
+                    unvisited[count] = (nx, ny);
+                    count += 1;
+                }
+            }
+
+            if count == 0 {
+                stack.pop();
+                continue;
+            }
+
+            let (nx, ny) = unvisited[rng.below(count as u64) as usize];
+            map.set((x + nx) / 2, (y + ny) / 2, true);
+            map.set(nx, ny, true);
+            stack.push((nx, ny));
+        }
+        map
+    }
+}
+
+/// Small xorshift generator, so that map generation and other reproducible sampling need no
+/// dependencies. Its output is part of the behavior of the map generators, so it must not be
+/// changed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed with a splitmix64 step, so that similar seeds give unrelated streams
+        // and a seed of 0 (a fixed point of xorshift) is usable.
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    /// Returns the next value of the stream.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a value uniformly distributed in `0.0..1.0`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a value in `0..n`. The bias is negligible when `n` is much smaller than `2^64`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+#[test]
+fn random_maps_are_reproducible() {
+    let cells = |map: &BitGrid| {
+        let mut cells = vec![];
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                cells.push(map.get(x, y));
+            }
+        }
+        cells
+    };
+
+    let map = BitGrid::random_obstacles(64, 48, 0.3, 7);
+    let same = BitGrid::random_obstacles(64, 48, 0.3, 7);
+    let other = BitGrid::random_obstacles(64, 48, 0.3, 8);
+    assert_eq!(cells(&map), cells(&same));
+    assert_ne!(cells(&map), cells(&other));
+    let density = map.statistics().obstacle_density();
+    assert!((density - 0.3).abs() < 0.05, "density {density}");
+
+    let open = BitGrid::random_obstacles(8, 8, 0.0, 1);
+    let blocked = BitGrid::random_obstacles(8, 8, 1.0, 1);
+    assert!(cells(&open).iter().all(|&cell| cell));
+    assert!(cells(&blocked).iter().all(|&cell| !cell));
+
+    let maze = BitGrid::random_maze(31, 21, 7);
+    assert_eq!(cells(&maze), cells(&BitGrid::random_maze(31, 21, 7)));
+    assert_ne!(cells(&maze), cells(&BitGrid::random_maze(31, 21, 8)));
+    // A perfect maze is a spanning tree of the 15 * 10 junctions, so it has a corridor cell for
+    // each of the 149 edges of the tree.
+    let stats = maze.statistics();
+    assert_eq!(stats.components, 1);
+    assert_eq!(stats.traversable, 150 + 149);
+
+    let even = BitGrid::random_maze(8, 6, 3);
+    assert_eq!(even.statistics().traversable, 3 * 2 + 5);
+    assert!((0..even.height()).all(|y| !even.get(7, y)));
+    assert_eq!(BitGrid::random_maze(2, 9, 3).statistics().traversable, 0);
+}
@@ -0,0 +1,72 @@
+use mkpath_core::traits::{Expander, OpenList};
+use mkpath_core::{NodeBuilder, PriorityQueueFactory};
+
+use crate::{octile_distance, BitGrid, EightConnectedExpander, GridPool};
+
+/// Returns the cost of the shortest path from `start` to `target` on an 8-connected grid map, or
+/// `None` if there is none.
+///
+/// This is a plain A* search with [`EightConnectedExpander`] and [`octile_distance`], so diagonal
+/// moves cost [`SAFE_SQRT_2`](crate::SAFE_SQRT_2) and corners can't be cut. It is deliberately the
+/// simplest correct search, to serve as the ground truth that faster algorithms are tested
+/// against; they use the same constants, so their costs should match it to within rounding.
+pub fn reference_cost(map: &BitGrid, start: (i32, i32), target: (i32, i32)) -> Option<f64> {
+    if !map.get(start.0, start.1) || !map.get(target.0, target.1) {
+        return None;
+    }
+
+    let mut builder = NodeBuilder::new();
+    let state = builder.add_field((-1, -1));
+    let g = builder.add_field(f64::INFINITY);
+    let f = builder.add_field(f64::INFINITY);
+    let mut pqueue_factory = PriorityQueueFactory::new(&mut builder);
+    let pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+    let mut expander = EightConnectedExpander::new(map, &pool, state);
+    let mut open = pqueue_factory.new_queue((f, g));
+    let mut edges = vec![];
+
+    let start = pool.generate(start);
+    start.set(g, 0.0);
+    start.set(f, octile_distance(start.get(state), target));
+    open.relaxed(start);
+
+    while let Some(node) = open.next() {
+        if node.get(state) == target {
+            return Some(node.get(g));
+        }
+        edges.clear();
+        expander.expand(node, &mut edges);
+        for edge in &edges {
+            let successor = edge.successor;
+            let new_g = node.get(g) + edge.cost;
+            if new_g < successor.get(g) {
+                successor.set(g, new_g);
+                successor.set(f, new_g + octile_distance(successor.get(state), target));
+                open.relaxed(successor);
+            }
+        }
+    }
+
+    None
+}
+
+#[test]
+fn reference_matches_exact_distance() {
+    use crate::exact_distance;
+
+    let map = BitGrid::random_obstacles(30, 20, 0.3, 21);
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .step_by(7)
+        .collect();
+    for &start in &cells {
+        for &target in &cells {
+            let expected = exact_distance(&map, start, target);
+            match reference_cost(&map, start, target) {
+                Some(cost) => assert!((cost - expected).abs() < 1e-9),
+                None => assert!(expected.is_infinite() || !map.get(start.0, start.1)),
+            }
+        }
+    }
+}
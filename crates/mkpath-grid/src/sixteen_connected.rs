@@ -0,0 +1,397 @@
+//! Types for 16-connected grid maps, which allow knight moves in addition to the 8 standard
+//! moves.
+//!
+//! This movement model is only supported by searches which use an expander directly, such as A*
+//! and CPD construction. JPS and the preprocessing built on it assume 8-connected movement and
+//! must not be used with it.
+
+use enumset::EnumSetType;
+use mkpath_core::traits::{Cost, EdgeId, Expander, Successor};
+use mkpath_core::{NodeAllocator, NodeBuilder, NodeMemberPointer, NodeRef};
+use mkpath_ess::ExplicitStateSpace;
+
+use crate::{BitGrid, Grid, GridMap, GridNodePool, GridPool, Metric};
+
+/// Direction of a move on a 16-connected grid.
+///
+/// The first 8 directions are the moves of an 8-connected grid, in the same order as
+/// [`Direction`](crate::Direction), so their edge ids agree. The remaining 8 are knight moves,
+/// which move two cells along one axis and one cell along the other, and are named after the
+/// compass point they head towards, e.g. `NorthNorthWest` is `(-1, -2)`.
+#[derive(EnumSetType, Debug, Hash)]
+pub enum Direction16 {
+    North,
+    West,
+    South,
+    East,
+    NorthWest,
+    SouthWest,
+    SouthEast,
+    NorthEast,
+    NorthNorthWest,
+    WestNorthWest,
+    WestSouthWest,
+    SouthSouthWest,
+    SouthSouthEast,
+    EastSouthEast,
+    EastNorthEast,
+    NorthNorthEast,
+}
+
+impl Direction16 {
+    /// All directions, in the order of their edge ids. `Direction16::ALL[d as usize] == d` holds
+    /// for every direction `d`.
+    pub const ALL: [Direction16; 16] = [
+        Direction16::North,
+        Direction16::West,
+        Direction16::South,
+        Direction16::East,
+        Direction16::NorthWest,
+        Direction16::SouthWest,
+        Direction16::SouthEast,
+        Direction16::NorthEast,
+        Direction16::NorthNorthWest,
+        Direction16::WestNorthWest,
+        Direction16::WestSouthWest,
+        Direction16::SouthSouthWest,
+        Direction16::SouthSouthEast,
+        Direction16::EastSouthEast,
+        Direction16::EastNorthEast,
+        Direction16::NorthNorthEast,
+    ];
+
+    /// Converts an edge id, as produced by [`SixteenConnectedEdge`] or stored in CPDs, back into a
+    /// direction.
+    pub fn from_edge_id(id: usize) -> Option<Direction16> {
+        Direction16::ALL.get(id).copied()
+    }
+
+    /// Returns the cell one move in this direction from `(x, y)`.
+    pub fn step(self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (dx, dy) = self.vector();
+        (x + dx, y + dy)
+    }
+
+    pub fn vector(self) -> (i32, i32) {
+        match self {
+            Direction16::North => (0, -1),
+            Direction16::West => (-1, 0),
+            Direction16::South => (0, 1),
+            Direction16::East => (1, 0),
+            Direction16::NorthWest => (-1, -1),
+            Direction16::SouthWest => (-1, 1),
+            Direction16::SouthEast => (1, 1),
+            Direction16::NorthEast => (1, -1),
+            Direction16::NorthNorthWest => (-1, -2),
+            Direction16::WestNorthWest => (-2, -1),
+            Direction16::WestSouthWest => (-2, 1),
+            Direction16::SouthSouthWest => (-1, 2),
+            Direction16::SouthSouthEast => (1, 2),
+            Direction16::EastSouthEast => (2, 1),
+            Direction16::EastNorthEast => (2, -1),
+            Direction16::NorthNorthEast => (1, -2),
+        }
+    }
+
+    pub fn backwards(self) -> Direction16 {
+        let (dx, dy) = self.vector();
+        *Direction16::ALL
+            .iter()
+            .find(|d| d.vector() == (-dx, -dy))
+            .unwrap()
+    }
+
+    pub fn is_knight(self) -> bool {
+        self as usize >= 8
+    }
+}
+
+pub struct SixteenConnectedEdge<'a> {
+    pub successor: NodeRef<'a>,
+    pub cost: f64,
+    pub direction: Direction16,
+}
+
+impl<'a> Successor<'a> for SixteenConnectedEdge<'a> {
+    fn successor(&self) -> NodeRef<'a> {
+        self.successor
+    }
+}
+
+impl Cost for SixteenConnectedEdge<'_> {
+    fn cost(&self) -> f64 {
+        self.cost
+    }
+}
+
+impl EdgeId for SixteenConnectedEdge<'_> {
+    fn edge_id(&self) -> usize {
+        self.direction as usize
+    }
+}
+
+/// Expander for 16-connected grid maps.
+///
+/// Moves may not pass through blocked cells: diagonal moves require both orthogonally adjacent
+/// cells to be traversable, as with [`EightConnectedExpander`](crate::EightConnectedExpander),
+/// and knight moves require the two cells the straight line between the cell centers crosses,
+/// e.g. `(1, 0)` and `(1, 1)` for a move to `(2, 1)`.
+///
+/// With the default costs, [`euclidean_distance`](crate::euclidean_distance) is an admissible
+/// heuristic, since it is scaled to allow for diagonal moves costing slightly less than
+/// `sqrt(2)`. This movement model can't be used with JPS; see the [module documentation](self).
+pub struct SixteenConnectedExpander<'a, P, M = BitGrid> {
+    map: &'a M,
+    node_pool: &'a P,
+    state: NodeMemberPointer<(i32, i32)>,
+    metric: Metric,
+    knight_cost: f64,
+}
+
+impl<'a, P: GridNodePool, M: GridMap> SixteenConnectedExpander<'a, P, M> {
+    /// Creates an expander with octile costs for the standard moves and `sqrt(5)` for knight
+    /// moves, i.e. the length of each move.
+    pub fn new(map: &'a M, node_pool: &'a P, state: NodeMemberPointer<(i32, i32)>) -> Self {
+        Self::with_costs(map, node_pool, state, Metric::OCTILE, 5.0f64.sqrt())
+    }
+
+    /// Creates an expander whose standard move costs are given by `metric`, and whose knight moves
+    /// cost `knight_cost`.
+    pub fn with_costs(
+        map: &'a M,
+        node_pool: &'a P,
+        state: NodeMemberPointer<(i32, i32)>,
+        metric: Metric,
+        knight_cost: f64,
+    ) -> Self {
+        // Establish invariant that coordinates in-bounds of the map are also in-bounds of the
+        // node pool.
+        assert!(
+            node_pool.width() >= map.width(),
+            "node pool must be wide enough for the map"
+        );
+        assert!(
+            node_pool.height() >= map.height(),
+            "node pool must be tall enough for the map"
+        );
+
+        SixteenConnectedExpander {
+            map,
+            node_pool,
+            state,
+            metric,
+            knight_cost,
+        }
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    pub fn knight_cost(&self) -> f64 {
+        self.knight_cost
+    }
+
+    /// Returns whether the move in `dir` from `(x, y)` is legal, and its cost.
+    ///
+    /// # Safety
+    /// `(x, y)` must be a traversable cell.
+    unsafe fn check(&self, x: i32, y: i32, dir: Direction16) -> Option<f64> {
+        let (dx, dy) = dir.vector();
+        let (sx, sy) = (dx.signum(), dy.signum());
+        // Cells are checked nearest first, so each cell is adjacent to the traversable cell
+        // before it, and therefore in-bounds of the padded grid, as required by get_unchecked.
+        let free = |cx: i32, cy: i32| unsafe { self.map.get_unchecked(x + cx, y + cy) };
+        let (legal, cost) = match (dx.abs(), dy.abs()) {
+            (0, _) => (free(0, dy), self.metric.ortho_y),
+            (_, 0) => (free(dx, 0), self.metric.ortho_x),
+            (1, 1) => (
+                free(dx, 0) && free(0, dy) && free(dx, dy),
+                self.metric.diagonal,
+            ),
+            (2, _) => (
+                free(sx, 0) && free(sx, sy) && free(dx, dy),
+                self.knight_cost,
+            ),
+            _ => (
+                free(0, sy) && free(sx, sy) && free(dx, dy),
+                self.knight_cost,
+            ),
+        };
+        legal.then_some(cost)
+    }
+}
+
+impl<'a, P: GridNodePool, M: GridMap> Expander<'a> for SixteenConnectedExpander<'a, P, M> {
+    type Edge = SixteenConnectedEdge<'a>;
+
+    fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<SixteenConnectedEdge<'a>>) {
+        let (x, y) = node.get(self.state);
+
+        assert!(
+            self.map.get(x, y),
+            "attempt to expand node at untraversable location"
+        );
+
+        for dir in Direction16::ALL {
+            unsafe {
+                // (x, y) is traversable, as required by check.
+                // Since the destination is verified to be traversable, it is in-bounds of the
+                // map, and therefore is also in-bounds of the node pool.
+                if let Some(cost) = self.check(x, y, dir) {
+                    edges.push(SixteenConnectedEdge {
+                        successor: self.node_pool.generate_unchecked(dir.step((x, y))),
+                        cost,
+                        direction: dir,
+                    });
+                }
+            }
+        }
+    }
+
+    fn max_degree(&self) -> usize {
+        16
+    }
+}
+
+/// A 16-connected grid map with the default costs of [`SixteenConnectedExpander::new`], as an
+/// explicit state space for building differential heuristics and other preprocessed data.
+pub struct SixteenConnectedGrid(pub BitGrid);
+
+impl ExplicitStateSpace for SixteenConnectedGrid {
+    type State = (i32, i32);
+
+    type Auxiliary<T> = Grid<T>;
+
+    type NodePool = GridPool;
+
+    type Expander<'a> = SixteenConnectedExpander<'a, GridPool>;
+
+    fn new_auxiliary<T>(&self, mut init: impl FnMut((i32, i32)) -> T) -> Grid<T> {
+        Grid::new(self.0.width(), self.0.height(), |x, y| init((x, y)))
+    }
+
+    fn add_state_field(&self, builder: &mut NodeBuilder) -> NodeMemberPointer<(i32, i32)> {
+        builder.add_field((-1, -1))
+    }
+
+    fn new_node_pool(
+        &self,
+        alloc: NodeAllocator,
+        state: NodeMemberPointer<(i32, i32)>,
+    ) -> GridPool {
+        GridPool::new(alloc, state, self.0.width(), self.0.height())
+    }
+
+    fn new_expander<'a>(
+        &'a self,
+        node_pool: &'a GridPool,
+        state: NodeMemberPointer<(i32, i32)>,
+    ) -> Self::Expander<'a> {
+        SixteenConnectedExpander::new(&self.0, node_pool, state)
+    }
+
+    fn list_valid_states(&self) -> Vec<(i32, i32)> {
+        let mut res = vec![];
+        for y in 0..self.0.height() {
+            for x in 0..self.0.width() {
+                if self.0.get(x, y) {
+                    res.push((x, y));
+                }
+            }
+        }
+        res
+    }
+}
+
+#[test]
+fn knight_moves_need_clear_lines() {
+    let map = BitGrid::from_str_rows(&[".....", ".....", "..@..", "....."], &['.']);
+    let mut builder = NodeBuilder::new();
+    let state = builder.add_field((-1, -1));
+    let pool = GridPool::new(builder.build(), state, map.width(), map.height());
+    let mut expander = SixteenConnectedExpander::new(&map, &pool, state);
+
+    let mut edges = vec![];
+    expander.expand(pool.generate((1, 1)), &mut edges);
+    let found: Vec<_> = edges.iter().map(|e| e.direction).collect();
+
+    // (2, 2) is blocked, which rules out the move to it and the knight moves to (3, 2) and
+    // (2, 3), which cross it. Knight moves off the map are also ruled out.
+    use Direction16::*;
+    assert_eq!(
+        found,
+        [
+            North,
+            West,
+            South,
+            East,
+            NorthWest,
+            SouthWest,
+            NorthEast,
+            SouthSouthWest,
+            EastNorthEast,
+        ]
+    );
+
+    for edge in &edges {
+        assert_eq!(edge.successor.get(state), edge.direction.step((1, 1)));
+        let (dx, dy) = edge.direction.vector();
+        assert!((edge.cost - ((dx * dx + dy * dy) as f64).sqrt()).abs() < 1e-6);
+    }
+
+    for (i, &dir) in Direction16::ALL.iter().enumerate() {
+        assert_eq!(dir as usize, i);
+        assert_eq!(Direction16::from_edge_id(i), Some(dir));
+        assert_eq!(dir.backwards().backwards(), dir);
+        assert_eq!(dir.backwards().vector(), (-dir.vector().0, -dir.vector().1));
+        assert_eq!(dir.is_knight(), i >= 8);
+    }
+}
+
+#[test]
+fn knight_moves_shorten_paths() {
+    use mkpath_core::traits::OpenList;
+    use mkpath_core::PriorityQueueFactory;
+    use mkpath_ess::Mapper;
+
+    use crate::euclidean_distance;
+
+    let open = BitGrid::from_str_rows(&[".......", ".......", ".......", "......."], &['.']);
+    let domain = SixteenConnectedGrid(open);
+    let mapper = Mapper::dfs_preorder(&domain);
+    assert_eq!(mapper.states(), 28);
+    assert_eq!(mapper.components(), 1);
+
+    // Knight moves reach (6, 3) from the corner in 3 moves, which is shorter than any
+    // 8-connected path.
+    let mut builder = NodeBuilder::new();
+    let state = domain.add_state_field(&mut builder);
+    let g = builder.add_field(f64::INFINITY);
+    let mut pqueue_factory = PriorityQueueFactory::new(&mut builder);
+    let pool = domain.new_node_pool(builder.build(), state);
+    let mut expander = domain.new_expander(&pool, state);
+    let mut open_list = pqueue_factory.new_queue(g);
+    let start = pool.generate((0, 0));
+    start.set(g, 0.0);
+    open_list.relaxed(start);
+    let mut edges = vec![];
+    while let Some(node) = open_list.next() {
+        edges.clear();
+        expander.expand(node, &mut edges);
+        for edge in &edges {
+            if node.get(g) + edge.cost < edge.successor.get(g) {
+                edge.successor.set(g, node.get(g) + edge.cost);
+                open_list.relaxed(edge.successor);
+            }
+        }
+    }
+    let dist = pool.generate((6, 3)).get(g);
+    assert!((dist - 3.0 * 5.0f64.sqrt()).abs() < 1e-9);
+    for y in 0..4 {
+        for x in 0..7 {
+            let h = euclidean_distance((0, 0), (x, y));
+            assert!(h <= pool.generate((x, y)).get(g));
+        }
+    }
+}
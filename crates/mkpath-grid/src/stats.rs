@@ -0,0 +1,125 @@
+use std::fmt::{Display, Formatter};
+
+use mkpath_ess::Mapper;
+
+use crate::{BitGrid, EightConnectedGrid};
+
+/// Summary of a grid map, as computed by [`BitGrid::statistics`].
+///
+/// The [`Display`] implementation prints one statistic per line, for a quick profile of a map
+/// when choosing an algorithm or investigating performance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapStats {
+    pub width: i32,
+    pub height: i32,
+    /// Number of traversable cells.
+    pub traversable: usize,
+    /// Number of connected components of traversable cells under 8-connected movement.
+    pub components: usize,
+    /// Number of jump points, if known. Computing this needs a jump database, so it is only
+    /// filled in by `JumpDatabase::statistics` in `mkpath-jps`.
+    pub jump_points: Option<usize>,
+}
+
+impl MapStats {
+    /// Returns the fraction of cells which are obstacles, or 0 for an empty map.
+    pub fn obstacle_density(&self) -> f64 {
+        let cells = self.width as usize * self.height as usize;
+        match cells {
+            0 => 0.0,
+            _ => (cells - self.traversable) as f64 / cells as f64,
+        }
+    }
+}
+
+impl Display for MapStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "size: {}x{}", self.width, self.height)?;
+        writeln!(f, "traversable cells: {}", self.traversable)?;
+        writeln!(
+            f,
+            "obstacle density: {:.2}%",
+            self.obstacle_density() * 100.0
+        )?;
+        write!(f, "connected components: {}", self.components)?;
+        if let Some(jump_points) = self.jump_points {
+            write!(f, "\njump points: {jump_points}")?;
+        }
+        Ok(())
+    }
+}
+
+impl BitGrid {
+    /// Computes a summary of the map; see [`MapStats`].
+    ///
+    /// This takes time linear in the size of the map, since the connected components are found by
+    /// searching the whole map.
+    pub fn statistics(&self) -> MapStats {
+        let mapper = Mapper::dfs_preorder(&EightConnectedGrid(self));
+        MapStats {
+            width: self.width(),
+            height: self.height(),
+            traversable: mapper.states(),
+            components: mapper.components(),
+            jump_points: None,
+        }
+    }
+}
+
+#[test]
+fn counts_cells_and_components() {
+    let map = BitGrid::from_str_rows(
+        &[
+            "..#...", //
+            "..#.#.", //
+            "###.#.", //
+            "...#..", //
+        ],
+        &['.'],
+    );
+    let stats = map.statistics();
+    assert_eq!((stats.width, stats.height), (6, 4));
+    assert_eq!(stats.traversable, 16);
+    // Diagonal moves can't cut corners, so (2, 3) is not connected to (3, 2).
+    assert_eq!(stats.components, 3);
+    assert_eq!(stats.jump_points, None);
+    assert!((stats.obstacle_density() - 8.0 / 24.0).abs() < 1e-9);
+
+    let text = stats.to_string();
+    assert!(text.contains("traversable cells: 16"));
+    assert!(text.contains("obstacle density: 33.33%"));
+    assert!(!text.contains("jump points"));
+
+    assert_eq!(BitGrid::new(0, 0).statistics().obstacle_density(), 0.0);
+}
+
+#[test]
+fn component_states_partition_the_map() {
+    use mkpath_ess::ExplicitStateSpace;
+
+    let map = BitGrid::from_str_rows(
+        &[
+            "..#..", //
+            "..#..", //
+            "#####", //
+            "...#.", //
+        ],
+        &['.'],
+    );
+    let mapper = Mapper::dfs_preorder(&EightConnectedGrid(&map));
+    assert_eq!(mapper.components(), 4);
+
+    let mut seen = vec![];
+    for component in 0..mapper.components() {
+        let states: Vec<_> = mapper.component_states(component).collect();
+        assert_eq!(states.len(), mapper.component_size(component));
+        for &state in &states {
+            assert_eq!(mapper.component_id(state), component);
+        }
+        seen.extend(states);
+    }
+    seen.sort_unstable();
+    let mut expected = EightConnectedGrid(&map).list_valid_states();
+    expected.sort_unstable();
+    assert_eq!(seen, expected);
+}
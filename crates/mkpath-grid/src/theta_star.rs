@@ -0,0 +1,219 @@
+use std::collections::BinaryHeap;
+
+use mkpath_core::traits::Expander;
+use mkpath_core::{NodeBuilder, NodeMemberPointer, NodeRef};
+
+use crate::lpa_star::QueueEntry;
+use crate::{euclidean_distance, EightConnectedExpander, GridMap, GridPool};
+
+/// Theta* any-angle search on an 8-connected grid map.
+///
+/// Daniel, K., Nash, A., Koenig, S., & Felner, A. (2010). Theta*: Any-angle path planning on
+/// grids. Journal of Artificial Intelligence Research, 39, 533-579.
+///
+/// Paths are sequences of cells joined by straight lines between their centers, each of which
+/// passes only through traversable cells (see [`line_of_sight`]). Path costs are Euclidean
+/// lengths as measured by [`euclidean_distance`], so they can be compared with paths planned in
+/// continuous space. Paths found are usually, but not always, the shortest any-angle paths.
+pub struct ThetaStar {
+    node_pool: GridPool,
+    state: NodeMemberPointer<(i32, i32)>,
+    g: NodeMemberPointer<f64>,
+    closed: NodeMemberPointer<bool>,
+    open: BinaryHeap<QueueEntry>,
+}
+
+impl ThetaStar {
+    pub fn new(width: i32, height: i32) -> Self {
+        let mut builder = NodeBuilder::new();
+        let state = builder.add_field((-1, -1));
+        let g = builder.add_field(f64::INFINITY);
+        let closed = builder.add_field(false);
+        ThetaStar {
+            node_pool: GridPool::new(builder.build(), state, width, height),
+            state,
+            g,
+            closed,
+            open: BinaryHeap::new(),
+        }
+    }
+
+    /// Searches for a path from `start` to `target`, returning the cells at which the path turns
+    /// (including both ends) and its Euclidean length.
+    pub fn search(
+        &mut self,
+        map: &impl GridMap,
+        start: (i32, i32),
+        target: (i32, i32),
+    ) -> Option<(Vec<(i32, i32)>, f64)> {
+        if !map.get(start.0, start.1) || !map.get(target.0, target.1) {
+            return None;
+        }
+
+        self.node_pool.reset();
+        let ThetaStar {
+            ref node_pool,
+            state,
+            g,
+            closed,
+            ref mut open,
+        } = *self;
+
+        let mut expander = EightConnectedExpander::new(map, node_pool, state);
+        let mut edges = vec![];
+
+        open.clear();
+        node_pool.generate(start).set(g, 0.0);
+        let h = euclidean_distance(start, target);
+        open.push(QueueEntry {
+            key: (h, h),
+            state: start,
+        });
+
+        while let Some(entry) = open.pop() {
+            let node = node_pool.generate(entry.state);
+            if node.get(closed) {
+                continue;
+            }
+            node.set(closed, true);
+
+            if entry.state == target {
+                let mut path = vec![target];
+                let mut current = node;
+                while let Some(parent) = current.get_parent() {
+                    path.push(parent.get(state));
+                    current = parent;
+                }
+                path.reverse();
+                return Some((path, node.get(g)));
+            }
+
+            edges.clear();
+            expander.expand(node, &mut edges);
+            for edge in &edges {
+                let successor = edge.successor;
+                if successor.get(closed) {
+                    continue;
+                }
+                let pos = successor.get(state);
+
+                // Path 2 of Theta*: connect directly to the parent when it is visible, otherwise
+                // fall back to the grid edge.
+                let from = match node.get_parent() {
+                    Some(parent) if line_of_sight(map, parent.get(state), pos) => parent,
+                    _ => node,
+                };
+                let new_g = from.get(g) + euclidean_distance(from.get(state), pos);
+                if new_g < successor.get(g) {
+                    successor.set(g, new_g);
+                    successor.set_parent(Some(from));
+                    let h = euclidean_distance(pos, target);
+                    open.push(QueueEntry {
+                        key: (new_g + h, h),
+                        state: pos,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the node of a cell from the last search, for inspecting its `g` value or parent.
+    pub fn node(&self, cell: (i32, i32)) -> NodeRef<'_> {
+        self.node_pool.generate(cell)
+    }
+
+    pub fn g(&self) -> NodeMemberPointer<f64> {
+        self.g
+    }
+}
+
+/// Returns whether the straight line between the centers of two cells passes only through
+/// traversable cells.
+///
+/// A line passing exactly through the corner shared by four cells requires all of them to be
+/// traversable, so a line of sight never squeezes between diagonally adjacent blocked cells, or
+/// cuts a corner as a diagonal move would with one of them blocked.
+pub fn line_of_sight(map: &impl GridMap, from: (i32, i32), to: (i32, i32)) -> bool {
+    let free = |(x, y): (i32, i32)| {
+        x >= 0 && y >= 0 && x < map.width() && y < map.height() && map.get(x, y)
+    };
+
+    let dx = (to.0 - from.0).unsigned_abs() as i64;
+    let dy = (to.1 - from.1).unsigned_abs() as i64;
+    let sx = (to.0 - from.0).signum();
+    let sy = (to.1 - from.1).signum();
+
+    let (mut x, mut y) = from;
+    if !free((x, y)) {
+        return false;
+    }
+
+    let (mut ix, mut iy) = (0, 0);
+    while ix < dx || iy < dy {
+        // Compare the distances along the line to the next vertical and horizontal cell border.
+        let decision = (1 + 2 * ix) * dy - (1 + 2 * iy) * dx;
+        if decision == 0 {
+            if !free((x + sx, y)) || !free((x, y + sy)) {
+                return false;
+            }
+            x += sx;
+            y += sy;
+            ix += 1;
+            iy += 1;
+        } else if decision < 0 {
+            x += sx;
+            ix += 1;
+        } else {
+            y += sy;
+            iy += 1;
+        }
+        if !free((x, y)) {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[test]
+fn any_angle_paths_are_visible_and_short() {
+    use crate::{exact_distance, BitGrid};
+
+    let mut map = BitGrid::new(20, 14);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x == 8 && y < 10) || (x == 13 && y > 3) || (x, y) == (4, 6);
+            map.set(x, y, !wall);
+        }
+    }
+
+    let mut theta_star = ThetaStar::new(map.width(), map.height());
+
+    // Unobstructed paths are straight lines.
+    let (path, cost) = theta_star.search(&map, (0, 0), (7, 3)).unwrap();
+    assert_eq!(path, [(0, 0), (7, 3)]);
+    assert_eq!(cost, euclidean_distance((0, 0), (7, 3)));
+
+    for (start, target) in [((1, 2), (18, 12)), ((2, 12), (19, 0)), ((3, 5), (5, 7))] {
+        let (path, cost) = theta_star.search(&map, start, target).unwrap();
+        assert_eq!((path[0], path[path.len() - 1]), (start, target));
+
+        let mut length = 0.0;
+        for pair in path.windows(2) {
+            assert!(line_of_sight(&map, pair[0], pair[1]));
+            length += euclidean_distance(pair[0], pair[1]);
+        }
+        assert!((length - cost).abs() < 1e-9);
+        assert!(cost >= euclidean_distance(start, target));
+        assert!(cost <= exact_distance(&map, start, target) + 1e-6);
+    }
+
+    // The line between (3, 5) and (5, 7) passes through the blocked cell (4, 6).
+    assert!(!line_of_sight(&map, (3, 5), (5, 7)));
+    assert!(!line_of_sight(&map, (0, 0), (19, 13)));
+    assert!(line_of_sight(&map, (9, 0), (19, 2)));
+
+    assert_eq!(theta_star.search(&map, (0, 0), (8, 0)), None);
+}
@@ -0,0 +1,168 @@
+use crate::{Direction, GridMap, Metric};
+
+/// Reason a path was rejected by [`validate_path`].
+///
+/// Segments are numbered by the index of the cell they start at, so segment `i` goes from
+/// `path[i]` to `path[i + 1]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathError {
+    /// The path contains no cells.
+    Empty,
+    /// The segment is neither horizontal, vertical, nor at 45 degrees, or it has zero length.
+    NotStraight { segment: usize },
+    /// The segment passes through a cell which is blocked or out of bounds. A blocked starting
+    /// cell is reported as part of segment 0.
+    Blocked { segment: usize, cell: (i32, i32) },
+    /// The segment makes a diagonal move from `cell` past a blocked corner.
+    CornerCut { segment: usize, cell: (i32, i32) },
+}
+
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            PathError::Empty => write!(f, "path is empty"),
+            PathError::NotStraight { segment } => {
+                write!(f, "segment {segment} is not a straight or diagonal line")
+            }
+            PathError::Blocked { segment, cell } => {
+                write!(f, "segment {segment} passes through blocked cell {cell:?}")
+            }
+            PathError::CornerCut { segment, cell } => {
+                write!(f, "segment {segment} cuts a corner moving from {cell:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Checks that `path` is a valid path on `map` and returns its cost under `metric`.
+///
+/// Consecutive cells of the path may be any distance apart, as long as the segment between them is
+/// horizontal, vertical, or diagonal, so both paths of single steps and paths of jump points which
+/// include the turn points of diagonal jumps are accepted. Every cell along each segment must be
+/// traversable, and diagonal moves may not cut corners, matching
+/// [`EightConnectedExpander`](crate::EightConnectedExpander).
+pub fn validate_path(
+    map: &impl GridMap,
+    path: &[(i32, i32)],
+    metric: Metric,
+) -> Result<f64, PathError> {
+    let in_bounds = |(x, y): (i32, i32)| x >= 0 && y >= 0 && x < map.width() && y < map.height();
+    let traversable = |cell: (i32, i32)| in_bounds(cell) && map.get(cell.0, cell.1);
+
+    let &start = path.first().ok_or(PathError::Empty)?;
+    if !traversable(start) {
+        return Err(PathError::Blocked {
+            segment: 0,
+            cell: start,
+        });
+    }
+
+    let mut cost = 0.0;
+    for (segment, pair) in path.windows(2).enumerate() {
+        let (from, to) = (pair[0], pair[1]);
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let dir = match Direction::from_vector((dx.signum(), dy.signum())) {
+            Some(dir) if dx == 0 || dy == 0 || dx.abs() == dy.abs() => dir,
+            _ => return Err(PathError::NotStraight { segment }),
+        };
+
+        let step_cost = match dir {
+            Direction::North | Direction::South => metric.ortho_y,
+            Direction::West | Direction::East => metric.ortho_x,
+            _ => metric.diagonal,
+        };
+
+        let mut cell = from;
+        while cell != to {
+            let next = dir.step(cell);
+            if dir.is_diagonal()
+                && !(traversable((next.0, cell.1)) && traversable((cell.0, next.1)))
+            {
+                return Err(PathError::CornerCut { segment, cell });
+            }
+            if !traversable(next) {
+                return Err(PathError::Blocked {
+                    segment,
+                    cell: next,
+                });
+            }
+            cost += step_cost;
+            cell = next;
+        }
+    }
+
+    Ok(cost)
+}
+
+#[test]
+fn rejects_illegal_segments() {
+    use crate::{BitGrid, SAFE_SQRT_2};
+
+    // A wall at x = 4 with a gap at the bottom, and a blocked cell at (1, 1).
+    let mut map = BitGrid::new(8, 6);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x != 4 || y == 5) && (x, y) != (1, 1));
+        }
+    }
+    let octile = Metric::OCTILE;
+
+    assert_eq!(validate_path(&map, &[(0, 0)], octile), Ok(0.0));
+    assert_eq!(
+        validate_path(&map, &[(0, 2), (3, 5), (7, 5)], octile),
+        Ok(3.0 * SAFE_SQRT_2 + 4.0)
+    );
+    assert_eq!(
+        validate_path(&map, &[(2, 0), (3, 0), (3, 3)], Metric::default()),
+        Ok(4.0)
+    );
+    let weighted = Metric {
+        ortho_x: 2.0,
+        ortho_y: 3.0,
+        diagonal: 4.0,
+    };
+    assert_eq!(
+        validate_path(&map, &[(5, 0), (7, 0), (7, 1), (6, 2)], weighted),
+        Ok(11.0)
+    );
+
+    assert_eq!(validate_path(&map, &[], octile), Err(PathError::Empty));
+    assert_eq!(
+        validate_path(&map, &[(1, 1), (2, 1)], octile),
+        Err(PathError::Blocked {
+            segment: 0,
+            cell: (1, 1)
+        })
+    );
+    assert_eq!(
+        validate_path(&map, &[(0, 2), (3, 3)], octile),
+        Err(PathError::NotStraight { segment: 0 })
+    );
+    assert_eq!(
+        validate_path(&map, &[(0, 2), (0, 2)], octile),
+        Err(PathError::NotStraight { segment: 0 })
+    );
+    assert_eq!(
+        validate_path(&map, &[(3, 5), (3, 0), (6, 0)], octile),
+        Err(PathError::Blocked {
+            segment: 1,
+            cell: (4, 0)
+        })
+    );
+    assert_eq!(
+        validate_path(&map, &[(0, 0), (0, 1), (2, 3)], octile),
+        Err(PathError::CornerCut {
+            segment: 1,
+            cell: (0, 1)
+        })
+    );
+    assert_eq!(
+        validate_path(&map, &[(7, 0), (8, 0)], octile),
+        Err(PathError::Blocked {
+            segment: 0,
+            cell: (8, 0)
+        })
+    );
+}
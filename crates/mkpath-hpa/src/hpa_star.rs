@@ -0,0 +1,266 @@
+use ahash::HashMap;
+use mkpath_core::traits::{NodePool, OpenList};
+use mkpath_core::{HashPool, NodeBuilder, NodeMemberPointer, PriorityQueueFactory};
+use mkpath_grid::{octile_distance, BitGrid};
+
+use crate::{LocalSearcher, SectorGraph};
+
+/// HPA* path planner using a precomputed [`SectorGraph`].
+pub struct HpaStar<'a> {
+    map: &'a BitGrid,
+    graph: &'a SectorGraph,
+    local: LocalSearcher,
+    node_pool: HashPool<usize>,
+    pqueue_factory: PriorityQueueFactory,
+    state: NodeMemberPointer<usize>,
+    g: NodeMemberPointer<f64>,
+    f: NodeMemberPointer<f64>,
+}
+
+impl<'a> HpaStar<'a> {
+    pub fn new(map: &'a BitGrid, graph: &'a SectorGraph) -> Self {
+        assert_eq!(
+            map.width(),
+            graph.width(),
+            "sector graph has incorrect width"
+        );
+        assert_eq!(
+            map.height(),
+            graph.height(),
+            "sector graph has incorrect height"
+        );
+
+        let mut builder = NodeBuilder::new();
+        let state = builder.add_field(usize::MAX);
+        let g = builder.add_field(f64::INFINITY);
+        let f = builder.add_field(f64::INFINITY);
+        let pqueue_factory = PriorityQueueFactory::new(&mut builder);
+
+        HpaStar {
+            map,
+            graph,
+            local: LocalSearcher::new(),
+            node_pool: HashPool::new(builder.build(), state),
+            pqueue_factory,
+            state,
+            g,
+            f,
+        }
+    }
+
+    /// Finds a path from `start` to `target`.
+    ///
+    /// The start and target are temporarily connected to the abstract nodes of their sectors, the
+    /// abstract graph is searched with A*, and each abstract edge is refined into cells by a search
+    /// restricted to a single sector. Returns the path as a list of cells and its cost, or `None`
+    /// if the target is unreachable.
+    pub fn get_path(
+        &mut self,
+        start: (i32, i32),
+        target: (i32, i32),
+    ) -> Option<(Vec<(i32, i32)>, f64)> {
+        let graph = self.graph;
+
+        if !self.map.get(start.0, start.1) || !self.map.get(target.0, target.1) {
+            return None;
+        }
+        if start == target {
+            return Some((vec![start], 0.0));
+        }
+
+        let start_id = graph.num_nodes();
+        let target_id = start_id + 1;
+        let cell = |id: usize| match id {
+            _ if id == start_id => start,
+            _ if id == target_id => target,
+            _ => graph.node(id),
+        };
+
+        let start_sector = graph.sector_of(start);
+        let mut start_edges = vec![];
+        self.local
+            .search(self.map, graph.sector_bounds(start_sector), start, None);
+        for &to in graph.sector_nodes(start_sector) {
+            if let Some(node) = self.local.get(graph.node(to)) {
+                start_edges.push((to, node.get(self.local.g())));
+            }
+        }
+        if graph.sector_of(target) == start_sector {
+            if let Some(node) = self.local.get(target) {
+                start_edges.push((target_id, node.get(self.local.g())));
+            }
+        }
+
+        // Grid movement is symmetric, so the cost from a node to the target is the cost from the
+        // target to the node.
+        let target_sector = graph.sector_of(target);
+        let mut target_edges = HashMap::default();
+        self.local
+            .search(self.map, graph.sector_bounds(target_sector), target, None);
+        for &from in graph.sector_nodes(target_sector) {
+            if let Some(node) = self.local.get(graph.node(from)) {
+                target_edges.insert(from, node.get(self.local.g()));
+            }
+        }
+
+        let abstract_path = self.abstract_search((start, target), &start_edges, &target_edges)?;
+
+        let mut path = vec![start];
+        let mut cost = 0.0;
+        let mut segment = vec![];
+        for pair in abstract_path.windows(2) {
+            let (from, to) = (cell(pair[0]), cell(pair[1]));
+            if graph.sector_of(from) != graph.sector_of(to) {
+                path.push(to);
+                cost += 1.0;
+                continue;
+            }
+
+            let sector = graph.sector_of(from);
+            self.local
+                .search(self.map, graph.sector_bounds(sector), from, Some(to));
+            let mut node = self
+                .local
+                .get(to)
+                .expect("abstract edge could not be refined");
+            cost += node.get(self.local.g());
+            segment.clear();
+            while let Some(parent) = node.get_parent() {
+                segment.push(node.get(self.local.state()));
+                node = parent;
+            }
+            path.extend(segment.iter().rev());
+        }
+
+        Some((path, cost))
+    }
+
+    /// A* over the abstract graph extended with the start and target nodes. Returns the ids of
+    /// the nodes on the path.
+    fn abstract_search(
+        &mut self,
+        (start, target): ((i32, i32), (i32, i32)),
+        start_edges: &[(usize, f64)],
+        target_edges: &HashMap<usize, f64>,
+    ) -> Option<Vec<usize>> {
+        let HpaStar {
+            graph,
+            ref mut node_pool,
+            ref mut pqueue_factory,
+            state,
+            g,
+            f,
+            ..
+        } = *self;
+
+        let start_id = graph.num_nodes();
+        let target_id = start_id + 1;
+        let h = |id: usize| match id {
+            _ if id == start_id => octile_distance(start, target),
+            _ if id == target_id => 0.0,
+            _ => octile_distance(graph.node(id), target),
+        };
+
+        node_pool.reset();
+        let mut open = pqueue_factory.new_queue((f, g));
+
+        let start_node = node_pool.generate(start_id);
+        start_node.set(g, 0.0);
+        start_node.set(f, h(start_id));
+        open.relaxed(start_node);
+
+        while let Some(node) = open.next() {
+            let id = node.get(state);
+            if id == target_id {
+                let mut path = vec![id];
+                let mut node = node;
+                while let Some(parent) = node.get_parent() {
+                    path.push(parent.get(state));
+                    node = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let edges = match id {
+                _ if id == start_id => start_edges,
+                _ if id == target_id => &[],
+                _ => graph.edges(id),
+            };
+            let to_target = target_edges.get(&id).map(|&cost| (target_id, cost));
+
+            for &(successor, cost) in edges.iter().chain(to_target.as_ref()) {
+                let successor = node_pool.generate(successor);
+                let new_g = node.get(g) + cost;
+                if new_g < successor.get(g) {
+                    successor.set(g, new_g);
+                    successor.set(f, new_g + h(successor.get(state)));
+                    successor.set_parent(Some(node));
+                    open.relaxed(successor);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[test]
+fn paths_are_valid() {
+    use mkpath_grid::SAFE_SQRT_2;
+
+    let mut map = BitGrid::new(40, 30);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x == 12 && y != 4 && y != 25) || (y == 17 && x > 20 && x != 33);
+            map.set(x, y, !wall);
+        }
+    }
+    // Enclosed cell which is unreachable from everywhere else.
+    map.set(38, 1, true);
+    for (x, y) in [
+        (37, 0),
+        (37, 1),
+        (37, 2),
+        (38, 2),
+        (39, 2),
+        (38, 0),
+        (39, 0),
+        (39, 1),
+    ] {
+        map.set(x, y, false);
+    }
+
+    let graph = SectorGraph::build(&map, 8);
+    let mut hpa = HpaStar::new(&map, &graph);
+
+    for (start, target) in [
+        ((0, 0), (39, 29)),
+        ((3, 28), (30, 20)),
+        ((25, 10), (25, 25)),
+        ((5, 5), (6, 6)),
+        ((13, 4), (11, 25)),
+    ] {
+        let (path, cost) = hpa.get_path(start, target).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&target));
+
+        let mut path_cost = 0.0;
+        for step in path.windows(2) {
+            let (dx, dy) = (step[1].0 - step[0].0, step[1].1 - step[0].1);
+            assert!(dx.abs() <= 1 && dy.abs() <= 1 && (dx, dy) != (0, 0));
+            assert!(map.get(step[1].0, step[1].1));
+            if dx != 0 && dy != 0 {
+                assert!(map.get(step[0].0 + dx, step[0].1) && map.get(step[0].0, step[0].1 + dy));
+                path_cost += SAFE_SQRT_2;
+            } else {
+                path_cost += 1.0;
+            }
+        }
+        assert!((path_cost - cost).abs() < 1e-9);
+        assert!(cost >= octile_distance(start, target) - 1e-9);
+    }
+
+    assert_eq!(hpa.get_path((0, 0), (38, 1)), None);
+    assert_eq!(hpa.get_path((0, 0), (12, 0)), None);
+}
@@ -0,0 +1,113 @@
+//! # `mkpath-hpa`
+//!
+//! Hierarchical pathfinding for grid maps.
+//!
+//! This crate implements HPA* (Botea et al, 2004). The map is partitioned into square sectors, and
+//! entrances on the borders between adjacent sectors become nodes of an abstract graph whose edges
+//! are the costs of paths within a single sector. Queries search the small abstract graph and then
+//! refine each abstract edge with a search restricted to one sector, which scales to maps where
+//! full preprocessing such as CPDs is too large. Paths are near-optimal, not optimal.
+//!
+//! ## References
+//!
+//! - Botea, A., Müller, M., & Schaeffer, J. (2004). Near optimal hierarchical path-finding. Journal of Game Development, 1(1), 7-28.
+
+use mkpath_core::traits::{Expander, NodePool, OpenList};
+use mkpath_core::{HashPool, NodeBuilder, NodeMemberPointer, NodeRef, PriorityQueueFactory};
+use mkpath_grid::{BitGrid, EightConnectedExpander};
+
+mod hpa_star;
+mod sector_graph;
+
+pub use self::hpa_star::*;
+pub use self::sector_graph::*;
+
+/// Dijkstra search over an 8-connected grid restricted to a rectangle of the map.
+struct LocalSearcher {
+    node_pool: HashPool<(i32, i32)>,
+    pqueue_factory: PriorityQueueFactory,
+    state: NodeMemberPointer<(i32, i32)>,
+    g: NodeMemberPointer<f64>,
+}
+
+impl LocalSearcher {
+    fn new() -> Self {
+        let mut builder = NodeBuilder::new();
+        let state = builder.add_field((-1, -1));
+        let g = builder.add_field(f64::INFINITY);
+        let pqueue_factory = PriorityQueueFactory::new(&mut builder);
+
+        LocalSearcher {
+            node_pool: HashPool::new(builder.build(), state),
+            pqueue_factory,
+            state,
+            g,
+        }
+    }
+
+    /// Searches from `start` to every cell in `low..high`, stopping early once `goal` is settled.
+    ///
+    /// Results are read back with [`LocalSearcher::get`].
+    fn search(
+        &mut self,
+        map: &BitGrid,
+        (low, high): ((i32, i32), (i32, i32)),
+        start: (i32, i32),
+        goal: Option<(i32, i32)>,
+    ) {
+        let LocalSearcher {
+            ref mut node_pool,
+            ref mut pqueue_factory,
+            state,
+            g,
+        } = *self;
+
+        node_pool.reset();
+
+        let mut expander = EightConnectedExpander::new(map, &*node_pool, state);
+        let mut pqueue = pqueue_factory.new_queue(g);
+        let mut edges = vec![];
+
+        let start = node_pool.generate(start);
+        start.set(g, 0.0);
+        pqueue.relaxed(start);
+
+        while let Some(node) = pqueue.next() {
+            if Some(node.get(state)) == goal {
+                return;
+            }
+
+            edges.clear();
+            expander.expand(node, &mut edges);
+
+            let node_g = node.get(g);
+            for edge in &edges {
+                let (x, y) = edge.successor.get(state);
+                if x < low.0 || y < low.1 || x >= high.0 || y >= high.1 {
+                    continue;
+                }
+                let new_g = node_g + edge.cost;
+                if new_g < edge.successor.get(g) {
+                    edge.successor.set(g, new_g);
+                    edge.successor.set_parent(Some(node));
+                    pqueue.relaxed(edge.successor);
+                }
+            }
+        }
+    }
+
+    /// Returns the node for `cell` if it was reached by the last search.
+    fn get(&self, cell: (i32, i32)) -> Option<NodeRef<'_>> {
+        self.node_pool
+            .get(&cell)
+            .filter(|node| node.get(self.g).is_finite())
+    }
+
+    fn g(&self) -> NodeMemberPointer<f64> {
+        self.g
+    }
+
+    fn state(&self) -> NodeMemberPointer<(i32, i32)> {
+        self.state
+    }
+}
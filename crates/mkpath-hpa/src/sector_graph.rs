@@ -0,0 +1,161 @@
+use ahash::HashMap;
+use mkpath_grid::{BitGrid, Grid};
+
+use crate::LocalSearcher;
+
+/// A pair of orthogonally adjacent cells on opposite sides of a sector border.
+type Crossing = ((i32, i32), (i32, i32));
+
+/// Abstract graph over the sectors of a grid map.
+///
+/// Nodes are cells on either side of a sector border, placed at the middle of each maximal run of
+/// cells where the border can be crossed orthogonally. Nodes on opposite sides of a border are
+/// connected by an edge of cost 1, and nodes in the same sector are connected by edges with the
+/// cost of the shortest path between them which stays inside the sector.
+pub struct SectorGraph {
+    width: i32,
+    height: i32,
+    sector_size: i32,
+    nodes: Vec<(i32, i32)>,
+    node_ids: HashMap<(i32, i32), usize>,
+    edges: Vec<Vec<(usize, f64)>>,
+    sector_nodes: Grid<Vec<usize>>,
+}
+
+impl SectorGraph {
+    /// Builds the abstract graph for `map` using square sectors `sector_size` cells wide.
+    ///
+    /// This runs one Dijkstra search restricted to a sector for every abstract node.
+    pub fn build(map: &BitGrid, sector_size: i32) -> Self {
+        assert!(sector_size > 0, "sector size must be positive");
+
+        let sectors_x = (map.width() + sector_size - 1) / sector_size;
+        let sectors_y = (map.height() + sector_size - 1) / sector_size;
+
+        let mut graph = SectorGraph {
+            width: map.width(),
+            height: map.height(),
+            sector_size,
+            nodes: vec![],
+            node_ids: HashMap::default(),
+            edges: vec![],
+            sector_nodes: Grid::new_default(sectors_x, sectors_y),
+        };
+
+        for sy in 0..sectors_y {
+            for sx in 0..sectors_x {
+                let (low, high) = graph.sector_bounds((sx, sy));
+                if high.0 < map.width() {
+                    let border: Vec<_> = (low.1..high.1)
+                        .map(|y| ((high.0 - 1, y), (high.0, y)))
+                        .collect();
+                    graph.add_entrances(map, &border);
+                }
+                if high.1 < map.height() {
+                    let border: Vec<_> = (low.0..high.0)
+                        .map(|x| ((x, high.1 - 1), (x, high.1)))
+                        .collect();
+                    graph.add_entrances(map, &border);
+                }
+            }
+        }
+
+        let mut searcher = LocalSearcher::new();
+        for sy in 0..sectors_y {
+            for sx in 0..sectors_x {
+                let bounds = graph.sector_bounds((sx, sy));
+                for &from in &graph.sector_nodes[(sx, sy)] {
+                    searcher.search(map, bounds, graph.nodes[from], None);
+                    for &to in &graph.sector_nodes[(sx, sy)] {
+                        if to == from {
+                            continue;
+                        }
+                        if let Some(node) = searcher.get(graph.nodes[to]) {
+                            graph.edges[from].push((to, node.get(searcher.g())));
+                        }
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn sector_size(&self) -> i32 {
+        self.sector_size
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the cell of abstract node `id`.
+    pub fn node(&self, id: usize) -> (i32, i32) {
+        self.nodes[id]
+    }
+
+    /// Returns the abstract node at `cell`, if there is one.
+    pub fn node_at(&self, cell: (i32, i32)) -> Option<usize> {
+        self.node_ids.get(&cell).copied()
+    }
+
+    /// Returns the outgoing edges of abstract node `id` as `(successor, cost)` pairs.
+    pub fn edges(&self, id: usize) -> &[(usize, f64)] {
+        &self.edges[id]
+    }
+
+    /// Returns the coordinates of the sector containing `cell`.
+    pub fn sector_of(&self, cell: (i32, i32)) -> (i32, i32) {
+        (cell.0 / self.sector_size, cell.1 / self.sector_size)
+    }
+
+    /// Returns the inclusive lower and exclusive upper corner of `sector`.
+    pub fn sector_bounds(&self, sector: (i32, i32)) -> ((i32, i32), (i32, i32)) {
+        let low = (sector.0 * self.sector_size, sector.1 * self.sector_size);
+        let high = (
+            (low.0 + self.sector_size).min(self.width),
+            (low.1 + self.sector_size).min(self.height),
+        );
+        (low, high)
+    }
+
+    /// Returns the abstract nodes inside `sector`.
+    pub fn sector_nodes(&self, sector: (i32, i32)) -> &[usize] {
+        &self.sector_nodes[sector]
+    }
+
+    fn add_entrances(&mut self, map: &BitGrid, border: &[Crossing]) {
+        let blocked = |&(a, b): &Crossing| !map.get(a.0, a.1) || !map.get(b.0, b.1);
+        for entrance in border.split(blocked) {
+            if entrance.is_empty() {
+                continue;
+            }
+            let (a, b) = entrance[entrance.len() / 2];
+            let a = self.node_id(a);
+            let b = self.node_id(b);
+            self.edges[a].push((b, 1.0));
+            self.edges[b].push((a, 1.0));
+        }
+    }
+
+    fn node_id(&mut self, cell: (i32, i32)) -> usize {
+        if let Some(&id) = self.node_ids.get(&cell) {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(cell);
+        self.edges.push(vec![]);
+        self.node_ids.insert(cell, id);
+        let sector = self.sector_of(cell);
+        self.sector_nodes[sector].push(id);
+        id
+    }
+}
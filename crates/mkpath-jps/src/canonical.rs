@@ -5,6 +5,20 @@ use mkpath_grid::{BitGrid, Direction, GridEdge, GridNodePool, SAFE_SQRT_2};
 
 use crate::canonical_successors;
 
+/// Expander for 8-connected grid maps which only generates canonical successors.
+///
+/// Like [`EightConnectedExpander`](mkpath_grid::EightConnectedExpander), every successor is an
+/// adjacent cell, but moves which cannot be part of a canonical (diagonal-first) path given the
+/// direction the node was reached from are pruned, as in JPS. Unlike JPS, no jumping is done, so
+/// this sits between the two: it generates far fewer redundant nodes than plain 8-connected
+/// expansion while every edge is still a single step. Costs are the usual octile costs.
+///
+/// The direction a node was reached from is derived from its parent, so nodes must have their
+/// parent pointer set as usual by the search.
+///
+/// Edges are generated orthogonal moves first (north, west, south, east), then diagonal moves
+/// (north-west, south-west, south-east, north-east). This order is not part of the contract;
+/// consumers such as first-move computation merge tied moves into sets and must not depend on it.
 pub struct CanonicalGridExpander<'a, P> {
     node_pool: &'a P,
     map: &'a BitGrid,
@@ -13,6 +27,17 @@ pub struct CanonicalGridExpander<'a, P> {
 
 impl<'a, P: GridNodePool> CanonicalGridExpander<'a, P> {
     pub fn new(map: &'a BitGrid, node_pool: &'a P, state: NodeMemberPointer<(i32, i32)>) -> Self {
+        // Establish invariant that coordinates in-bounds of the map are also in-bounds of the
+        // node pool.
+        assert!(
+            node_pool.width() >= map.width(),
+            "node pool must be wide enough for the map"
+        );
+        assert!(
+            node_pool.height() >= map.height(),
+            "node pool must be tall enough for the map"
+        );
+
         CanonicalGridExpander {
             node_pool,
             map,
@@ -20,6 +45,16 @@ impl<'a, P: GridNodePool> CanonicalGridExpander<'a, P> {
         }
     }
 
+    /// Generates the successors of `node` in the directions in `successors`.
+    ///
+    /// This is used by callers which already know the successor set, e.g. because they track it
+    /// in a node field. [`Expander::expand`] computes the canonical successor set itself and is
+    /// the safe way to use this expander.
+    ///
+    /// # Safety
+    /// The state of `node` must be in-bounds of the map, and every direction in `successors` must
+    /// lead to a traversable cell, e.g. by being a subset of
+    /// [`BitGrid::get_neighborhood`] at the node.
     pub unsafe fn expand_unchecked(
         &mut self,
         node: NodeRef<'a>,
@@ -95,6 +130,7 @@ impl<'a, P: GridNodePool> CanonicalGridExpander<'a, P> {
 impl<'a, P: GridNodePool> Expander<'a> for CanonicalGridExpander<'a, P> {
     type Edge = GridEdge<'a>;
 
+    /// Generates the canonical successors of `node`, given the direction it was reached from.
     fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<Self::Edge>) {
         let (x, y) = node.get(self.state);
 
@@ -105,8 +141,14 @@ impl<'a, P: GridNodePool> Expander<'a> for CanonicalGridExpander<'a, P> {
 
         let successors = canonical_successors(self.map.get_neighborhood(x, y), dir);
 
+        // SAFETY: the node was generated from the map, and canonical successors are a subset of
+        //         the traversable neighbors.
         unsafe {
             self.expand_unchecked(node, edges, successors);
         }
     }
+
+    fn max_degree(&self) -> usize {
+        8
+    }
 }
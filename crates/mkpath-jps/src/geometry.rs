@@ -0,0 +1,170 @@
+//! Helpers for reasoning about positions along a jump.
+//!
+//! Jumps move along a single axis in the direction of a sign `D`, which is either `-1` or `1`. The
+//! const-generic functions take the sign as a parameter so that jump code specialized on the
+//! direction compiles to a single comparison, while the `_dyn` variants take the sign at runtime.
+
+use mkpath_grid::Direction;
+
+/// Returns the distance from `from` to `to` in direction `D`, which is negative if `to` is behind
+/// `from`.
+#[inline(always)]
+pub fn signed_distance<const D: i32>(from: i32, to: i32) -> i32 {
+    signed_distance_dyn(D, from, to)
+}
+
+/// Returns whether `to` is strictly ahead of `from` in direction `D`.
+#[inline(always)]
+pub fn in_direction<const D: i32>(from: i32, to: i32) -> bool {
+    in_direction_dyn(D, from, to)
+}
+
+/// Returns whether a jump in direction `D` from `start` which stopped at `end` passed `target`.
+///
+/// The target is skipped past if it is strictly between `start` and `end`. A target at `start` is
+/// never reached by the jump, and a target at `end` is the cell the jump stopped at.
+#[inline(always)]
+pub fn skipped_past<const D: i32>(start: i32, end: i32, target: i32) -> bool {
+    skipped_past_dyn(D, start, end, target)
+}
+
+/// Runtime variant of [`signed_distance`].
+#[inline(always)]
+pub fn signed_distance_dyn(sign: i32, from: i32, to: i32) -> i32 {
+    debug_assert!(sign == -1 || sign == 1, "sign must be -1 or 1");
+    sign * (to - from)
+}
+
+/// Runtime variant of [`in_direction`].
+#[inline(always)]
+pub fn in_direction_dyn(sign: i32, from: i32, to: i32) -> bool {
+    signed_distance_dyn(sign, from, to) > 0
+}
+
+/// Runtime variant of [`skipped_past`].
+#[inline(always)]
+pub fn skipped_past_dyn(sign: i32, start: i32, end: i32, target: i32) -> bool {
+    in_direction_dyn(sign, start, target) && in_direction_dyn(sign, target, end)
+}
+
+/// Returns the direction of the first move of an octile path from `from` to `to` which takes all
+/// of its diagonal moves last, or `None` if the two cells are the same.
+///
+/// For the edges generated by JPS, which are a diagonal segment followed by an orthogonal one,
+/// this is the direction the last segment moves in, i.e. the direction `to` was reached in.
+/// Segments which turn the other way, moving orthogonally and then diagonally, are reached
+/// diagonally, but only the endpoints are used here, so this still returns the orthogonal
+/// direction. When the direction of the first move is known, use [`reached_direction_from`],
+/// which is correct for both kinds of segment.
+pub fn reached_direction(from: (i32, i32), to: (i32, i32)) -> Option<Direction> {
+    let dx = to.0 - from.0;
+    let dy = to.1 - from.1;
+    if dx.abs() > dy.abs() {
+        if dx < 0 {
+            Some(Direction::West)
+        } else {
+            Some(Direction::East)
+        }
+    } else if dy.abs() > dx.abs() {
+        if dy < 0 {
+            Some(Direction::North)
+        } else {
+            Some(Direction::South)
+        }
+    } else {
+        // The offsets have equal magnitude, so this is a diagonal, or `None` if both are 0.
+        Direction::from_vector((dx.signum(), dy.signum()))
+    }
+}
+
+/// Returns the direction in which `to` is reached by a segment from `from` which starts with a
+/// move in direction `first` and then turns at most once, or `None` if the two cells are the
+/// same.
+///
+/// Unlike [`reached_direction`], this gives the direction of the last move both for segments
+/// which move diagonally and then orthogonally, and for those which move orthogonally and then
+/// diagonally.
+pub fn reached_direction_from(
+    first: Direction,
+    from: (i32, i32),
+    to: (i32, i32),
+) -> Option<Direction> {
+    let dx = to.0 - from.0;
+    let dy = to.1 - from.1;
+    if dx == 0 && dy == 0 {
+        None
+    } else if first.is_diagonal() {
+        // Diagonal moves come first, so the remaining orthogonal moves (if any) come last.
+        reached_direction(from, to)
+    } else if dx == 0 || dy == 0 {
+        Some(first)
+    } else {
+        // Orthogonal moves come first, so the segment finishes diagonally.
+        Direction::from_vector((dx.signum(), dy.signum()))
+    }
+}
+
+#[test]
+fn reached_direction_of_turning_segments() {
+    // Two moves north-east, then three east.
+    assert_eq!(reached_direction((0, 0), (5, -2)), Some(Direction::East));
+    let from_ne = reached_direction_from(Direction::NorthEast, (0, 0), (5, -2));
+    assert_eq!(from_ne, Some(Direction::East));
+    // Three moves east, then two north-east. The endpoints are the same, so only the known first
+    // move tells the two segments apart.
+    let from_e = reached_direction_from(Direction::East, (0, 0), (5, -2));
+    assert_eq!(from_e, Some(Direction::NorthEast));
+
+    let straight = reached_direction_from(Direction::South, (3, 3), (3, 9));
+    assert_eq!(straight, Some(Direction::South));
+    let diagonal = reached_direction_from(Direction::SouthWest, (3, 3), (0, 6));
+    assert_eq!(diagonal, Some(Direction::SouthWest));
+    assert!(reached_direction_from(Direction::West, (3, 3), (3, 3)).is_none());
+}
+
+#[test]
+fn skip_logic() {
+    assert_eq!(signed_distance::<1>(3, 7), 4);
+    assert_eq!(signed_distance::<-1>(3, 7), -4);
+    assert_eq!(signed_distance::<-1>(7, 3), 4);
+
+    assert!(in_direction::<1>(3, 4));
+    assert!(!in_direction::<1>(3, 3));
+    assert!(!in_direction::<1>(3, 2));
+    assert!(in_direction::<-1>(3, 2));
+    assert!(!in_direction::<-1>(3, 3));
+
+    // Jumping right from 2, stopping at 6.
+    assert!(!skipped_past::<1>(2, 6, 1));
+    assert!(!skipped_past::<1>(2, 6, 2));
+    assert!(skipped_past::<1>(2, 6, 3));
+    assert!(skipped_past::<1>(2, 6, 5));
+    assert!(!skipped_past::<1>(2, 6, 6));
+    assert!(!skipped_past::<1>(2, 6, 7));
+
+    // Jumping left from 6, stopping at 2.
+    assert!(!skipped_past::<-1>(6, 2, 7));
+    assert!(!skipped_past::<-1>(6, 2, 6));
+    assert!(skipped_past::<-1>(6, 2, 5));
+    assert!(skipped_past::<-1>(6, 2, 3));
+    assert!(!skipped_past::<-1>(6, 2, 2));
+    assert!(!skipped_past::<-1>(6, 2, 1));
+
+    // A jump blocked immediately can't skip anything.
+    assert!(!skipped_past::<1>(4, 5, 4));
+    assert!(!skipped_past::<1>(4, 5, 5));
+
+    for sign in [-1, 1] {
+        for start in -3..=3 {
+            for end in -3..=3 {
+                for target in -3..=3 {
+                    let expected = match sign {
+                        -1 => skipped_past::<-1>(start, end, target),
+                        _ => skipped_past::<1>(start, end, target),
+                    };
+                    assert_eq!(skipped_past_dyn(sign, start, end, target), expected);
+                }
+            }
+        }
+    }
+}
@@ -2,12 +2,54 @@ use mkpath_core::traits::{Expander, WeightedEdge};
 use mkpath_core::{NodeMemberPointer, NodeRef};
 use mkpath_grid::{BitGrid, Direction, GridNodePool, SAFE_SQRT_2};
 
-use crate::{canonical_successors, skipped_past};
+use crate::{canonical_successors, transpose};
+use crate::geometry::{signed_distance, skipped_past};
+
+/// A grid map together with its transpose, as used by [`JpsExpander`].
+///
+/// Transposing a map takes time proportional to its size, so the transpose should be built once
+/// and shared between the expanders of every search on the map.
+pub struct JpsGrid {
+    map: BitGrid,
+    tmap: BitGrid,
+}
+
+impl JpsGrid {
+    pub fn new(map: BitGrid) -> Self {
+        let tmap = transpose(&map);
+        JpsGrid { map, tmap }
+    }
+
+    pub fn map(&self) -> &BitGrid {
+        &self.map
+    }
+
+    /// Returns the transpose of the map, in which the cell `(x, y)` is the cell `(y, x)` of the
+    /// map.
+    pub fn transposed(&self) -> &BitGrid {
+        &self.tmap
+    }
+
+    pub fn into_map(self) -> BitGrid {
+        self.map
+    }
+}
+
+impl From<BitGrid> for JpsGrid {
+    fn from(map: BitGrid) -> Self {
+        JpsGrid::new(map)
+    }
+}
 
 /// Jump Point Search expander.
 ///
 /// Harabor, D., & Grastien, A. (2014, May). Improving jump point search. In Proceedings of the
 /// International Conference on Automated Planning and Scheduling (Vol. 24, pp. 128-135).
+///
+/// JPS assumes uniform move costs (octile movement), so it can't be used with the non-square
+/// [`Metric`](mkpath_grid::Metric)s supported by
+/// [`EightConnectedExpander`](mkpath_grid::EightConnectedExpander). For maps with terrain costs,
+/// use [`WeightedJpsExpander`](crate::WeightedJpsExpander) instead.
 pub struct JpsExpander<'a, P> {
     node_pool: &'a P,
     map: &'a BitGrid,
@@ -59,6 +101,16 @@ impl<'a, P: GridNodePool> JpsExpander<'a, P> {
         }
     }
 
+    /// Creates an expander for the map of `grid`, reusing its transpose.
+    pub fn from_grid(
+        grid: &'a JpsGrid,
+        node_pool: &'a P,
+        state: NodeMemberPointer<(i32, i32)>,
+        target: (i32, i32),
+    ) -> Self {
+        Self::new(grid.map(), grid.transposed(), node_pool, state, target)
+    }
+
     /// Jumps horizontally.
     ///
     /// Preconditions:
@@ -91,7 +143,7 @@ impl<'a, P: GridNodePool> JpsExpander<'a, P> {
         if successor {
             edges.push(WeightedEdge {
                 successor: unsafe { self.node_pool.generate_unchecked((new_x, y)) },
-                cost: cost + (DX * (new_x - x)) as f64,
+                cost: cost + signed_distance::<DX>(x, new_x) as f64,
             });
         }
         all_1s
@@ -115,8 +167,8 @@ impl<'a, P: GridNodePool> JpsExpander<'a, P> {
         all_1s: i32,
     ) -> i32 {
         let (mut new_y, mut successor) = unsafe {
-            // The preconditions are upheld by the caller. Note that JpsGrid has the invariant
-            // that tmap is the transpose of map.
+            // The preconditions are upheld by the caller. Note that the constructor establishes
+            // that tmap has the dimensions of the transpose of map.
             match DY {
                 -1 => jump_left::<DX>(&self.tmap, y, x, all_1s),
                 1 => jump_right::<DX>(&self.tmap, y, x, all_1s),
@@ -135,7 +187,7 @@ impl<'a, P: GridNodePool> JpsExpander<'a, P> {
             // of the prior if statement.
             edges.push(WeightedEdge {
                 successor: unsafe { self.node_pool.generate_unchecked((x, new_y)) },
-                cost: cost + (DY * (new_y - y)) as f64,
+                cost: cost + signed_distance::<DY>(y, new_y) as f64,
             })
         }
         all_1s
@@ -349,4 +401,10 @@ impl<'a, P: GridNodePool> Expander<'a> for JpsExpander<'a, P> {
             }
         }
     }
+
+    // Diagonal jumps can produce a successor for each orthogonal jump point they pass, but most
+    // expansions have only a few successors.
+    fn max_degree(&self) -> usize {
+        8
+    }
 }
@@ -1,123 +1,45 @@
 use mkpath_core::traits::{Expander, WeightedEdge};
 use mkpath_core::{NodeMemberPointer, NodeRef};
-use mkpath_grid::{BitGrid, GridNodePool, SAFE_SQRT_2};
+use mkpath_grid::GridNodePool;
 
-use crate::{canonical_successors, Direction, JumpDatabase};
+use crate::{GenericJpsExpander, JumpDatabase, OfflineJpl};
 
 /// Jump Point Search Plus expander.
 ///
 /// Harabor, D., & Grastien, A. (2014, May). Improving jump point search. In Proceedings of the
 /// International Conference on Automated Planning and Scheduling (Vol. 24, pp. 128-135).
+///
+/// JPS assumes uniform move costs (octile movement). For maps with terrain costs, use
+/// [`WeightedJpsExpander`](crate::WeightedJpsExpander) instead.
 pub struct JpsPlusExpander<'a, P> {
-    node_pool: &'a P,
-    map: &'a BitGrid,
-    jump_db: &'a JumpDatabase,
-    state: NodeMemberPointer<(i32, i32)>,
-    target: (i32, i32),
+    inner: GenericJpsExpander<'a, OfflineJpl<'a>, P>,
 }
 
 impl<'a, P: GridNodePool> JpsPlusExpander<'a, P> {
+    /// Creates an expander which jumps using `jump_db` on the map it was built for.
     pub fn new(
-        map: &'a BitGrid,
         jump_db: &'a JumpDatabase,
         node_pool: &'a P,
         state: NodeMemberPointer<(i32, i32)>,
         target: (i32, i32),
     ) -> Self {
-        // Establish invariant that coordinates in-bounds of the map are also in-bounds of the
-        // node pool.
-        assert!(
-            node_pool.width() >= map.width(),
-            "node pool must be wide enough for the map"
-        );
-        assert!(
-            node_pool.height() >= map.height(),
-            "node pool must be tall enough for the map"
-        );
-
-        // Establish invariant that coordinates in-bounds of the map are in-bounds of the jump
-        // database, and vice-versa.
-        // We don't check that the content of the jump database is actually correct for the map
-        // since that's a) slow b) merely a logic error; not required for safety.
-        assert_eq!(
-            map.width(),
-            jump_db.width(),
-            "jump database has incorrect width"
-        );
-        assert_eq!(
-            map.height(),
-            jump_db.height(),
-            "jump database has incorrect height"
-        );
-
         JpsPlusExpander {
-            node_pool,
-            map,
-            jump_db,
-            state,
-            target,
-        }
-    }
-
-    #[inline(always)]
-    unsafe fn jump_ortho(
-        &self,
-        x: i32,
-        y: i32,
-        dir: Direction,
-        cost: f64,
-        edges: &mut Vec<WeightedEdge<'a>>,
-    ) {
-        let (dx, dy) = match dir {
-            Direction::North => (0, -1),
-            Direction::West => (-1, 0),
-            Direction::South => (0, 1),
-            Direction::East => (1, 0),
-            _ => unreachable!(),
-        };
-
-        if let Some(dist) = self.jump_db.ortho_jump_unchecked(x, y, dir, self.target) {
-            edges.push(WeightedEdge {
-                successor: self
-                    .node_pool
-                    .generate_unchecked((x + dx * dist, y + dy * dist)),
-                cost: cost + dist as f64,
-            })
+            inner: GenericJpsExpander::from_locator(
+                OfflineJpl::new(jump_db),
+                node_pool,
+                state,
+                target,
+            ),
         }
     }
 
-    #[inline(always)]
-    unsafe fn jump_diagonal(
-        &self,
-        mut x: i32,
-        mut y: i32,
-        dir: Direction,
-        edges: &mut Vec<WeightedEdge<'a>>,
-    ) {
-        let (dx, dy, dir_x, dir_y) = match dir {
-            Direction::NorthWest => (-1, -1, Direction::West, Direction::North),
-            Direction::SouthWest => (-1, 1, Direction::West, Direction::South),
-            Direction::SouthEast => (1, 1, Direction::East, Direction::South),
-            Direction::NorthEast => (1, -1, Direction::East, Direction::North),
-            _ => unreachable!(),
-        };
-
-        let mut cost = 0.0;
-        while let Some((dist, _)) = self.jump_db.diagonal_jump_unchecked(x, y, dir, self.target) {
-            x += dx * dist;
-            y += dy * dist;
-            cost += dist as f64 * SAFE_SQRT_2;
-
-            if (x, y) == self.target {
-                edges.push(WeightedEdge {
-                    successor: self.node_pool.generate_unchecked((x, y)),
-                    cost,
-                });
-                break;
-            }
-
-            self.jump_ortho(x, y, dir_x, cost, edges);
-            self.jump_ortho(x, y, dir_y, cost, edges);
+    /// Makes the expander generate the cells at which diagonal jumps turn, so that consecutive
+    /// nodes of a path are always connected by a straight line.
+    ///
+    /// See [`GenericJpsExpander::with_turn_points`].
+    pub fn with_turn_points(self) -> Self {
+        JpsPlusExpander {
+            inner: self.inner.with_turn_points(),
         }
     }
 }
@@ -125,44 +47,67 @@ impl<'a, P: GridNodePool> JpsPlusExpander<'a, P> {
 impl<'a, P: GridNodePool> Expander<'a> for JpsPlusExpander<'a, P> {
     type Edge = WeightedEdge<'a>;
 
+    #[inline(always)]
     fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<Self::Edge>) {
-        let (x, y) = node.get(self.state);
+        self.inner.expand(node, edges)
+    }
 
-        let dir = node.get_parent().and_then(|parent| {
-            let (px, py) = parent.get(self.state);
-            crate::reached_direction((px, py), (x, y))
-        });
+    fn max_degree(&self) -> usize {
+        self.inner.max_degree()
+    }
+}
 
-        let successors = canonical_successors(self.map.get_neighborhood(x, y), dir);
+#[test]
+fn turn_points_give_straight_segments() {
+    use mkpath_core::NodeBuilder;
+    use mkpath_grid::{exact_distance, BitGrid, Direction, GridPool, SAFE_SQRT_2};
 
-        unsafe {
-            // All jumps have the traversability of the relevant tile checked via successor set.
-            // Remaining preconditions hold trivially.
+    use crate::TestSearcher;
 
-            if successors.contains(Direction::North) {
-                self.jump_ortho(x, y, Direction::North, 0.0, edges);
-            }
-            if successors.contains(Direction::West) {
-                self.jump_ortho(x, y, Direction::West, 0.0, edges);
-            }
-            if successors.contains(Direction::South) {
-                self.jump_ortho(x, y, Direction::South, 0.0, edges);
-            }
-            if successors.contains(Direction::East) {
-                self.jump_ortho(x, y, Direction::East, 0.0, edges);
-            }
-            if successors.contains(Direction::NorthWest) {
-                self.jump_diagonal(x, y, Direction::NorthWest, edges);
-            }
-            if successors.contains(Direction::SouthWest) {
-                self.jump_diagonal(x, y, Direction::SouthWest, edges);
-            }
-            if successors.contains(Direction::SouthEast) {
-                self.jump_diagonal(x, y, Direction::SouthEast, edges);
-            }
-            if successors.contains(Direction::NorthEast) {
-                self.jump_diagonal(x, y, Direction::NorthEast, edges);
+    let mut map = BitGrid::new(20, 14);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x == 7 && y > 2 && y < 11) || (y == 10 && x > 10 && x < 17);
+            map.set(x, y, !wall);
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+
+    let mut builder = NodeBuilder::new();
+    let mut searcher = TestSearcher::new(&mut builder);
+    let state = searcher.state;
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+    for (start, target) in [((0, 0), (19, 13)), ((2, 12), (18, 3)), ((9, 12), (14, 0))] {
+        let optimal = exact_distance(&map, start, target);
+
+        pool.reset();
+        let expander = JpsPlusExpander::new(&jump_db, &pool, state, target);
+        let expander = expander.with_turn_points();
+        let (path, cost) = searcher.search(&pool, expander, start, target).unwrap();
+        assert!((cost - optimal).abs() < 1e-9);
+
+        // Walk the path one cell at a time, which is possible since every segment is straight.
+        let mut dense_cost = 0.0;
+        let mut pos = start;
+        for &next in &path[1..] {
+            let (dx, dy) = (next.0 - pos.0, next.1 - pos.1);
+            assert!(dx == 0 || dy == 0 || dx.abs() == dy.abs());
+            let step = (dx.signum(), dy.signum());
+            while pos != next {
+                let dir = Direction::from_vector(step).unwrap();
+                assert!(map.get_neighborhood(pos.0, pos.1).contains(dir));
+                if dir.orthogonal() {
+                    dense_cost += 1.0;
+                } else {
+                    // Corners may not be cut.
+                    assert!(map.get(pos.0 + step.0, pos.1) && map.get(pos.0, pos.1 + step.1));
+                    dense_cost += SAFE_SQRT_2;
+                }
+                pos = (pos.0 + step.0, pos.1 + step.1);
             }
         }
+        assert_eq!(pos, target);
+        assert!((dense_cost - optimal).abs() < 1e-9);
     }
 }
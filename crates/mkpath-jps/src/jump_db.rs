@@ -1,12 +1,68 @@
-use mkpath_grid::{BitGrid, Direction, Grid};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
 
+use enumset::EnumSet;
+use mkpath_grid::{BitGrid, Direction, Grid, MapStats, SAFE_SQRT_2};
+
+use crate::canonical_successors;
+use crate::geometry::signed_distance;
+
+/// Precomputed jump distances for every cell and direction of a grid map, as used by JPS+.
+///
+/// The database keeps its own copy of the map it was built for, so the two can't get out of sync.
+///
+/// Databases built by [`JumpDatabase::new_orthogonal_only`] lack diagonal jump data, and can only
+/// be used for orthogonal jumps.
 pub struct JumpDatabase {
-    db: Grid<[u16; 8]>,
+    map: BitGrid,
+    ortho: Grid<[u16; 4]>,
+    /// Empty if the database has no diagonal jump data.
+    diagonal: Grid<[u16; 4]>,
+}
+
+/// The end of a jump with target check, as returned by [`JumpDatabase::jump_to`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JumpResult {
+    /// The successor the jump ends at; either a jump point or the target.
+    pub end: (i32, i32),
+    /// Octile cost of the jump, including the part after the turn.
+    pub cost: f64,
+    /// If a diagonal jump turns to reach the target, the orthogonal direction it turns in and the
+    /// distance travelled after the turn.
+    pub turn: Option<(Direction, i32)>,
+}
+
+impl JumpResult {
+    /// Returns the coordinate at which the jump turns, if it does.
+    pub fn turn_point(&self) -> Option<(i32, i32)> {
+        let (dir, dist) = self.turn?;
+        let (dx, dy) = dir.vector();
+        Some((self.end.0 - dx * dist, self.end.1 - dy * dist))
+    }
 }
 
 impl JumpDatabase {
-    #[inline(never)]
+    /// Builds the jump database for a copy of `map`.
     pub fn new(map: &BitGrid) -> Self {
+        Self::from_map(map.clone())
+    }
+
+    /// Builds the jump database for `map`, taking ownership of it.
+    pub fn from_map(map: BitGrid) -> Self {
+        Self::build(map, true)
+    }
+
+    /// Builds a jump database for a copy of `map` with only orthogonal jump data.
+    ///
+    /// This takes half the time and memory of a full database, for uses which never jump
+    /// diagonally, such as 4-connected movement. Diagonal queries on the database panic, and
+    /// expanders which jump diagonally refuse to use it.
+    pub fn new_orthogonal_only(map: &BitGrid) -> Self {
+        Self::build(map.clone(), false)
+    }
+
+    #[inline(never)]
+    fn build(map: BitGrid, diagonals: bool) -> Self {
         use Direction::*;
 
         assert!(
@@ -18,11 +74,12 @@ impl JumpDatabase {
             "map cannot be taller than 32768 tiles"
         );
 
-        let mut db = Grid::new(map.width(), map.height(), |_, _| [0; 8]);
+        let mut ortho: Grid<[u16; 4]> = Grid::new_default(map.width(), map.height());
 
         for y in 0..map.height() {
             for x in 0..map.width() {
-                let nb = map.get_neighborhood(x, y);
+                // SAFETY: x and y are in-bounds by the loop ranges.
+                let nb = unsafe { map.get_neighborhood_unchecked(x, y) };
 
                 // West
                 if nb & (West | NorthWest | North) == West | NorthWest
@@ -30,11 +87,11 @@ impl JumpDatabase {
                 {
                     // The location to the west is a jump point; distance 1, successor.
                     // DB values are encoded distance << 1 | successor
-                    db[(x, y)][West as usize] = 3;
+                    ortho[(x, y)][West as usize] = 3;
                 } else if nb.contains(West) {
                     // The location to the west is not a jump point, but we can jump through it.
                     // Increase the distance by 1 and keep the successor flag.
-                    db[(x, y)][West as usize] = db[(x - 1, y)][West as usize] + 2;
+                    ortho[(x, y)][West as usize] = ortho[(x - 1, y)][West as usize] + 2;
                 } else {
                     // If we can't go west, then the jump distance is 0 and there is no successor.
                     // This is represented by db value 0, which is the default, so we don't need
@@ -46,55 +103,68 @@ impl JumpDatabase {
                 if nb & (North | NorthWest | West) == North | NorthWest
                     || nb & (North | NorthEast | East) == North | NorthEast
                 {
-                    db[(x, y)][North as usize] = 3;
+                    ortho[(x, y)][North as usize] = 3;
                 } else if nb.contains(North) {
-                    db[(x, y)][North as usize] = db[(x, y - 1)][North as usize] + 2;
+                    ortho[(x, y)][North as usize] = ortho[(x, y - 1)][North as usize] + 2;
                 }
             }
         }
 
         for y in (0..map.height()).rev() {
             for x in (0..map.width()).rev() {
-                let nb = map.get_neighborhood(x, y);
+                // SAFETY: x and y are in-bounds by the loop ranges.
+                let nb = unsafe { map.get_neighborhood_unchecked(x, y) };
 
                 // East
                 if nb & (East | NorthEast | North) == East | NorthEast
                     || nb & (East | SouthEast | South) == East | SouthEast
                 {
-                    db[(x, y)][East as usize] = 3;
+                    ortho[(x, y)][East as usize] = 3;
                 } else if nb.contains(East) {
-                    db[(x, y)][East as usize] = db[(x + 1, y)][East as usize] + 2;
+                    ortho[(x, y)][East as usize] = ortho[(x + 1, y)][East as usize] + 2;
                 }
 
                 // South
                 if nb & (South | SouthWest | West) == South | SouthWest
                     || nb & (South | SouthEast | East) == South | SouthEast
                 {
-                    db[(x, y)][South as usize] = 3;
+                    ortho[(x, y)][South as usize] = 3;
                 } else if nb.contains(South) {
-                    db[(x, y)][South as usize] = db[(x, y + 1)][South as usize] + 2;
+                    ortho[(x, y)][South as usize] = ortho[(x, y + 1)][South as usize] + 2;
                 }
             }
         }
 
+        if !diagonals {
+            return JumpDatabase {
+                map,
+                ortho,
+                diagonal: Grid::new_default(0, 0),
+            };
+        }
+
+        let mut diagonal: Grid<[u16; 4]> = Grid::new_default(map.width(), map.height());
+
         for y in 0..map.height() {
             for x in 0..map.width() {
-                let nb = map.get_neighborhood(x, y);
+                // SAFETY: x and y are in-bounds by the loop ranges.
+                let nb = unsafe { map.get_neighborhood_unchecked(x, y) };
 
                 // NorthWest
                 if nb.is_superset(North | West | NorthWest) {
                     // We can go northwest. The northwest tile is a jump point if at least one of
                     // the north or west jumps have successors.
-                    if db[(x - 1, y - 1)][West as usize] & 1 != 0
-                        || db[(x - 1, y - 1)][North as usize] & 1 != 0
+                    if ortho[(x - 1, y - 1)][West as usize] & 1 != 0
+                        || ortho[(x - 1, y - 1)][North as usize] & 1 != 0
                     {
                         // At least one of the orthogonal jumps for the next tile has a successor;
                         // distance 1, successor.
-                        db[(x, y)][NorthWest as usize] = 3;
+                        diagonal[(x, y)][NorthWest as usize - 4] = 3;
                     } else {
                         // The location to the west is not a jump point, but we can jump through it.
                         // Increase the distance by 1 and keep the successor flag.
-                        db[(x, y)][NorthWest as usize] = db[(x - 1, y - 1)][NorthWest as usize] + 2;
+                        diagonal[(x, y)][NorthWest as usize - 4] =
+                            diagonal[(x - 1, y - 1)][NorthWest as usize - 4] + 2;
                     }
                 } else {
                     // If we can't go northwest, then the jump distance is 0 and there is no
@@ -105,12 +175,13 @@ impl JumpDatabase {
                 // NorthEast
                 // This works basically the same as the above logic.
                 if nb.is_superset(North | East | NorthEast) {
-                    if db[(x + 1, y - 1)][East as usize] & 1 != 0
-                        || db[(x + 1, y - 1)][North as usize] & 1 != 0
+                    if ortho[(x + 1, y - 1)][East as usize] & 1 != 0
+                        || ortho[(x + 1, y - 1)][North as usize] & 1 != 0
                     {
-                        db[(x, y)][NorthEast as usize] = 3;
+                        diagonal[(x, y)][NorthEast as usize - 4] = 3;
                     } else {
-                        db[(x, y)][NorthEast as usize] = db[(x + 1, y - 1)][NorthEast as usize] + 2;
+                        diagonal[(x, y)][NorthEast as usize - 4] =
+                            diagonal[(x + 1, y - 1)][NorthEast as usize - 4] + 2;
                     }
                 }
             }
@@ -118,53 +189,325 @@ impl JumpDatabase {
 
         for y in (0..map.height()).rev() {
             for x in (0..map.width()).rev() {
-                let nb = map.get_neighborhood(x, y);
+                // SAFETY: x and y are in-bounds by the loop ranges.
+                let nb = unsafe { map.get_neighborhood_unchecked(x, y) };
 
                 // SouthWest
                 if nb.is_superset(South | West | SouthWest) {
-                    if db[(x - 1, y + 1)][West as usize] & 1 != 0
-                        || db[(x - 1, y + 1)][South as usize] & 1 != 0
+                    if ortho[(x - 1, y + 1)][West as usize] & 1 != 0
+                        || ortho[(x - 1, y + 1)][South as usize] & 1 != 0
                     {
-                        db[(x, y)][SouthWest as usize] = 3;
+                        diagonal[(x, y)][SouthWest as usize - 4] = 3;
                     } else {
-                        db[(x, y)][SouthWest as usize] = db[(x - 1, y + 1)][SouthWest as usize] + 2;
+                        diagonal[(x, y)][SouthWest as usize - 4] =
+                            diagonal[(x - 1, y + 1)][SouthWest as usize - 4] + 2;
                     }
                 }
 
                 // SouthEast
                 if nb.is_superset(South | East | SouthEast) {
-                    if db[(x + 1, y + 1)][East as usize] & 1 != 0
-                        || db[(x + 1, y + 1)][South as usize] & 1 != 0
+                    if ortho[(x + 1, y + 1)][East as usize] & 1 != 0
+                        || ortho[(x + 1, y + 1)][South as usize] & 1 != 0
                     {
-                        db[(x, y)][SouthEast as usize] = 3;
+                        diagonal[(x, y)][SouthEast as usize - 4] = 3;
                     } else {
-                        db[(x, y)][SouthEast as usize] = db[(x + 1, y + 1)][SouthEast as usize] + 2;
+                        diagonal[(x, y)][SouthEast as usize - 4] =
+                            diagonal[(x + 1, y + 1)][SouthEast as usize - 4] + 2;
                     }
                 }
             }
         }
 
-        JumpDatabase { db }
+        JumpDatabase {
+            map,
+            ortho,
+            diagonal,
+        }
+    }
+
+    /// Returns the map the jump database was built for.
+    pub fn map(&self) -> &BitGrid {
+        &self.map
+    }
+
+    /// Returns the independent jump points of the map along with the directions in which they are
+    /// jump points, ordered by row and then column.
+    ///
+    /// A cell is an orthogonal jump point in a direction if an orthogonal move into it in that
+    /// direction has forced successors. A cell is a diagonal jump point in a direction if a
+    /// diagonal jump in that direction from an orthogonal jump point stops at it. These are the
+    /// nodes of the jump point graph that TOPS and JPS+BB+ compute oracles for.
+    pub fn jump_points(&self) -> Vec<((i32, i32), EnumSet<Direction>)> {
+        let map = &self.map;
+        let mut jump_points: Grid<EnumSet<Direction>> =
+            Grid::new_default(map.width(), map.height());
+
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                if !map.get(x, y) {
+                    continue;
+                }
+
+                // SAFETY: x and y are in-bounds by the loop ranges.
+                let nb = unsafe { map.get_neighborhood_unchecked(x, y) };
+                let mut jp_successors = EnumSet::empty();
+                let mut jps = EnumSet::empty();
+
+                for dir in Direction::ORTHOGONAL {
+                    if !nb.contains(dir.backwards()) {
+                        continue;
+                    }
+                    let dirs = canonical_successors(nb, Some(dir));
+                    if dirs & dir != dirs {
+                        jps |= dir;
+                        jp_successors |= dirs;
+                    }
+                }
+
+                if jps.is_empty() {
+                    continue;
+                }
+                jump_points[(x, y)] |= jps;
+
+                for dir in jp_successors & Direction::DIAG {
+                    for (pos, _) in self.diagonal_chain(x, y, dir) {
+                        jump_points[pos] |= dir;
+                    }
+                }
+            }
+        }
+
+        let mut result = vec![];
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                if !jump_points[(x, y)].is_empty() {
+                    result.push(((x, y), jump_points[(x, y)]));
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns every diagonal jump point reached by repeatedly jumping from `(x, y)` in diagonal
+    /// direction `dir`, along with the octile cost of reaching it from `(x, y)`, until a jump has
+    /// no successor.
+    ///
+    /// Unlike [`JumpDatabase::jump_to`], this ignores any target, so it yields the jump points
+    /// along the diagonal regardless of whether they are relevant to a particular search.
+    ///
+    /// # Panics
+    /// Panics if the coordinates are out of bounds, if `dir` is orthogonal, or if the database has
+    /// no diagonal jump data.
+    pub fn diagonal_chain(
+        &self,
+        x: i32,
+        y: i32,
+        dir: Direction,
+    ) -> impl Iterator<Item = ((i32, i32), f64)> + '_ {
+        let _ = self.ortho[(x, y)];
+        assert!(dir.is_diagonal(), "diagonal chain in orthogonal direction");
+        assert!(self.has_diagonals(), "jump database has no diagonal data");
+
+        let (dx, dy) = dir.vector();
+        let (mut x, mut y, mut total) = (x, y, 0);
+        std::iter::from_fn(move || {
+            // SAFETY: (x, y) is in-bounds, since it is either checked above or a jump point.
+            let (dist, true) = (unsafe { self.get_unchecked(x, y, dir) }) else {
+                return None;
+            };
+            x += dx * dist;
+            y += dy * dist;
+            total += dist;
+            Some(((x, y), total as f64 * SAFE_SQRT_2))
+        })
+    }
+
+    /// Returns the jump points which can be reached from `center` by jumping with a total octile
+    /// cost of at most `radius`, ordered by row and then column.
+    ///
+    /// These are the nodes a JPS+ search from `center` would generate before its `g` values exceed
+    /// `radius`, found by a Dijkstra search over the jump point graph which stops at `radius`.
+    /// Jumps are made without a target, so only cells at which jumps stop by themselves are
+    /// included, and `center` itself is not. The search only visits the area around `center`, so
+    /// this is suitable for re-planning locally without touching the rest of the map. Returns
+    /// nothing if `center` is an obstacle.
+    ///
+    /// # Panics
+    /// Panics if `center` is out of bounds, or if the database has no diagonal jump data.
+    pub fn local_jump_points(&self, center: (i32, i32), radius: f64) -> Vec<(i32, i32)> {
+        assert!(self.has_diagonals(), "jump database has no diagonal data");
+        if !self.map.get(center.0, center.1) {
+            return vec![];
+        }
+
+        // For each generated cell, the best cost found so far, the directions it was reached in
+        // at that cost, and whether it has been expanded.
+        let mut cells: HashMap<(i32, i32), (f64, EnumSet<Direction>, bool)> = HashMap::new();
+        let mut open = BinaryHeap::new();
+        cells.insert(center, (0.0, EnumSet::empty(), false));
+        open.push(Reverse(LocalEntry(0.0, center)));
+
+        while let Some(Reverse(LocalEntry(g, (x, y)))) = open.pop() {
+            let (best, arrivals, expanded) = cells.get_mut(&(x, y)).unwrap();
+            if *expanded || g > *best {
+                continue;
+            }
+            *expanded = true;
+
+            let nb = self.map.get_neighborhood(x, y);
+            let successors = match (x, y) == center {
+                true => canonical_successors(nb, None),
+                false => arrivals
+                    .iter()
+                    .map(|dir| canonical_successors(nb, Some(dir)))
+                    .fold(EnumSet::empty(), |a, b| a | b),
+            };
+            for dir in successors {
+                // SAFETY: (x, y) is in-bounds, since it is either checked above or a jump point.
+                let (dist, true) = (unsafe { self.get_unchecked(x, y, dir) }) else {
+                    continue;
+                };
+                let (dx, dy) = dir.vector();
+                let next = (x + dx * dist, y + dy * dist);
+                let next_g = match dir.is_diagonal() {
+                    true => g + dist as f64 * SAFE_SQRT_2,
+                    false => g + dist as f64,
+                };
+                if next_g > radius {
+                    continue;
+                }
+                let entry = cells
+                    .entry(next)
+                    .or_insert((f64::INFINITY, EnumSet::empty(), false));
+                if next_g < entry.0 {
+                    *entry = (next_g, EnumSet::only(dir), false);
+                    open.push(Reverse(LocalEntry(next_g, next)));
+                } else if next_g == entry.0 {
+                    entry.1 |= dir;
+                }
+            }
+        }
+
+        let mut result: Vec<_> = cells.into_keys().filter(|&pos| pos != center).collect();
+        result.sort_unstable_by_key(|&(x, y)| (y, x));
+        result
+    }
+
+    /// Computes a summary of the map as with [`BitGrid::statistics`], including the number of
+    /// jump points (see [`JumpDatabase::jump_points`]).
+    pub fn statistics(&self) -> MapStats {
+        MapStats {
+            jump_points: Some(self.jump_points().len()),
+            ..self.map.statistics()
+        }
     }
 
     pub fn width(&self) -> i32 {
-        self.db.width()
+        self.ortho.width()
     }
 
     pub fn height(&self) -> i32 {
-        self.db.height()
+        self.ortho.height()
+    }
+
+    /// Returns whether the database has diagonal jump data, i.e. whether it was not built by
+    /// [`JumpDatabase::new_orthogonal_only`].
+    pub fn has_diagonals(&self) -> bool {
+        self.diagonal.width() == self.ortho.width() && self.diagonal.height() == self.ortho.height()
+    }
+
+    /// Returns the number of cells a jump from `(x, y)` in direction `dir` travels before stopping,
+    /// ignoring any target.
+    ///
+    /// If [`JumpDatabase::has_successor`] is true, the jump stops at a jump point; otherwise it
+    /// stops at the last traversable cell before an obstacle or the edge of the map, which is not
+    /// a successor. Distances are 0 where `dir` is blocked.
+    ///
+    /// # Panics
+    /// Panics under the same conditions as [`JumpDatabase::get`].
+    pub fn jump_distance(&self, x: i32, y: i32, dir: Direction) -> i32 {
+        self.get(x, y, dir).0
+    }
+
+    /// Returns whether a jump from `(x, y)` in direction `dir` ends at a jump point, ignoring any
+    /// target. See [`JumpDatabase::jump_distance`].
+    ///
+    /// # Panics
+    /// Panics under the same conditions as [`JumpDatabase::get`].
+    pub fn has_successor(&self, x: i32, y: i32, dir: Direction) -> bool {
+        self.get(x, y, dir).1
     }
 
+    /// Returns the jump distance and whether the jump has a successor; see
+    /// [`JumpDatabase::jump_distance`] and [`JumpDatabase::has_successor`].
+    ///
+    /// # Panics
+    /// Panics if the coordinates are out of bounds, or if `dir` is diagonal and the database has
+    /// no diagonal jump data.
     pub fn get(&self, x: i32, y: i32, dir: Direction) -> (i32, bool) {
-        let _ = self.db[(x, y)];
+        let _ = self.ortho[(x, y)];
+        if dir.is_diagonal() {
+            assert!(self.has_diagonals(), "jump database has no diagonal data");
+        }
         unsafe { self.get_unchecked(x, y, dir) }
     }
 
+    /// # Safety
+    /// The coordinates must be in-bounds of the map. If `dir` is diagonal, the database must have
+    /// diagonal jump data.
     pub unsafe fn get_unchecked(&self, x: i32, y: i32, dir: Direction) -> (i32, bool) {
-        let raw = self.db.get_unchecked(x, y)[dir as usize];
+        let raw = if dir.orthogonal() {
+            self.ortho.get_unchecked(x, y)[dir as usize]
+        } else {
+            debug_assert!(self.has_diagonals());
+            self.diagonal.get_unchecked(x, y)[dir as usize - 4]
+        };
         ((raw >> 1) as i32, raw & 1 != 0)
     }
 
+    /// Finds the end of a jump from `from` in direction `dir` with target check.
+    ///
+    /// This is a convenience wrapper around [`JumpDatabase::ortho_jump_unchecked`] and
+    /// [`JumpDatabase::diagonal_jump_unchecked`] which decodes their results into coordinates.
+    /// Returns `None` if the jump has no successor.
+    ///
+    /// # Panics
+    /// Panics if `from` is out of bounds, or if `dir` is diagonal and the database has no diagonal
+    /// jump data.
+    pub fn jump_to(
+        &self,
+        from: (i32, i32),
+        dir: Direction,
+        target: (i32, i32),
+    ) -> Option<JumpResult> {
+        let _ = self.ortho[from];
+        if dir.is_diagonal() {
+            assert!(self.has_diagonals(), "jump database has no diagonal data");
+        }
+        let (dx, dy) = dir.vector();
+        // SAFETY: from is in-bounds, checked above.
+        unsafe {
+            if dir.orthogonal() {
+                let dist = self.ortho_jump_unchecked(from.0, from.1, dir, target)?;
+                Some(JumpResult {
+                    end: (from.0 + dx * dist, from.1 + dy * dist),
+                    cost: dist as f64,
+                    turn: None,
+                })
+            } else {
+                let (dist, turn) = self.diagonal_jump_unchecked(from.0, from.1, dir, target)?;
+                let mut end = (from.0 + dx * dist, from.1 + dy * dist);
+                let mut cost = dist as f64 * SAFE_SQRT_2;
+                if let Some((turn_dir, dist2)) = turn {
+                    let (tx, ty) = turn_dir.vector();
+                    end = (end.0 + tx * dist2, end.1 + ty * dist2);
+                    cost += dist2 as f64;
+                }
+                Some(JumpResult { end, cost, turn })
+            }
+        }
+    }
+
     /// Finds the end of an orthogonal jump with target check.
     ///
     /// If this function returns `Some(d)`, then `d` is the distance to the next successor. The
@@ -208,7 +551,8 @@ impl JumpDatabase {
     /// turn in orthogonal direction `o` to reach the target, and it will have to go `d2` to get .
     ///
     /// # Safety
-    /// The x and y coordinates must be in-bounds of the map.
+    /// The x and y coordinates must be in-bounds of the map, and the database must have diagonal
+    /// jump data.
     #[inline(always)]
     pub unsafe fn diagonal_jump_unchecked(
         &self,
@@ -233,18 +577,14 @@ impl JumpDatabase {
         y: i32,
         target: (i32, i32),
     ) -> Option<(i32, Option<(Direction, i32)>)> {
-        let (dir, dir_x, dir_y) = match (DX, DY) {
-            (-1, -1) => (Direction::NorthWest, Direction::West, Direction::North),
-            (1, -1) => (Direction::NorthEast, Direction::East, Direction::North),
-            (1, 1) => (Direction::SouthEast, Direction::East, Direction::South),
-            (-1, 1) => (Direction::SouthWest, Direction::West, Direction::South),
-            _ => unreachable!(),
-        };
+        let dir = Direction::from_vector((DX, DY)).unwrap();
+        let dir_x = Direction::from_vector((DX, 0)).unwrap();
+        let dir_y = Direction::from_vector((0, DY)).unwrap();
 
         let (dist, successor) = self.get_unchecked(x, y, dir);
 
-        let x_target_dist = DX * (target.0 - x);
-        let y_target_dist = DY * (target.1 - y);
+        let x_target_dist = signed_distance::<DX>(x, target.0);
+        let y_target_dist = signed_distance::<DY>(y, target.1);
 
         if x_target_dist > 0 && x_target_dist < dist + !successor as i32 {
             // passed target on the x axis, so we need to check y axis followup jump
@@ -256,7 +596,7 @@ impl JumpDatabase {
             let turn_x = target.0;
             let turn_y = y + DY * x_target_dist;
             // Calculate the length of the post-turn jump to the target
-            let remaining_dist = DY * (target.1 - turn_y);
+            let remaining_dist = signed_distance::<DY>(turn_y, target.1);
 
             // If the remaining orthogonal jump is valid, then return the way we hit the target.
             // Note that the .1 component is always false. If it were true, then `successor` and
@@ -271,7 +611,7 @@ impl JumpDatabase {
             let turn_x = x + DX * y_target_dist;
             let turn_y = target.1;
             // Calculate the length of the post-turn jump to the target
-            let remaining_dist = DX * (target.0 - turn_x);
+            let remaining_dist = signed_distance::<DX>(turn_x, target.0);
 
             // If the remaining orthogonal jump is valid, then return the way we hit the target.
             // Note that the .1 component is always false. If it were true, then `successor` and
@@ -285,3 +625,208 @@ impl JumpDatabase {
         successor.then_some((dist, None))
     }
 }
+
+/// Open list entry of [`JumpDatabase::local_jump_points`], ordered by cost.
+struct LocalEntry(f64, (i32, i32));
+
+impl PartialEq for LocalEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for LocalEntry {}
+
+impl PartialOrd for LocalEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LocalEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[test]
+fn jump_points_around_obstacle() {
+    use Direction::*;
+
+    let mut map = BitGrid::new(7, 7);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x, y) != (3, 3));
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let jump_points = jump_db.jump_points();
+
+    let find = |pos| {
+        jump_points
+            .iter()
+            .find(|&&(p, _)| p == pos)
+            .map(|&(_, dirs)| dirs)
+    };
+    assert_eq!(find((2, 2)), Some(North | West));
+    assert_eq!(find((4, 2)), Some(North | East));
+    assert_eq!(find((2, 4)), Some(South | West));
+    assert_eq!(find((4, 4)), Some(South | East));
+
+    assert!(jump_points
+        .windows(2)
+        .all(|w| (w[0].0 .1, w[0].0 .0) < (w[1].0 .1, w[1].0 .0)));
+    for &((x, y), dirs) in &jump_points {
+        let nb = map.get_neighborhood(x, y);
+        for dir in dirs & Direction::ORTHO {
+            assert_ne!(canonical_successors(nb, Some(dir)), dir);
+        }
+    }
+}
+
+#[test]
+fn orthogonal_only_matches_full_database() {
+    let mut map = BitGrid::new(15, 11);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x * 3 + y * 5) % 7 != 0);
+        }
+    }
+    let full = JumpDatabase::new(&map);
+    let ortho = JumpDatabase::new_orthogonal_only(&map);
+    assert!(full.has_diagonals());
+    assert!(!ortho.has_diagonals());
+
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            for dir in Direction::ORTHOGONAL {
+                assert_eq!(ortho.get(x, y, dir), full.get(x, y, dir));
+                assert_eq!(
+                    ortho.jump_to((x, y), dir, (7, 5)),
+                    full.jump_to((x, y), dir, (7, 5))
+                );
+            }
+        }
+    }
+
+    let diagonal = std::panic::catch_unwind(|| ortho.get(1, 1, Direction::NorthWest));
+    assert!(diagonal.is_err());
+}
+
+#[test]
+fn jump_distances_in_corridor() {
+    use Direction::*;
+
+    // A corridor along the top, with a side passage opening off it at x = 5.
+    let mut map = BitGrid::new(9, 3);
+    for x in 0..map.width() {
+        map.set(x, 0, true);
+    }
+    map.set(5, 1, true);
+    map.set(5, 2, true);
+    let jump_db = JumpDatabase::new(&map);
+
+    // Moving east from the west end, (5, 0) is a jump point, since the side passage can only be
+    // entered from it.
+    assert_eq!(jump_db.jump_distance(0, 0, East), 5);
+    assert!(jump_db.has_successor(0, 0, East));
+    // Past the side passage, the jump runs into the east end without finding a jump point.
+    assert_eq!(jump_db.jump_distance(6, 0, East), 2);
+    assert!(!jump_db.has_successor(6, 0, East));
+    // Blocked directions have distance 0.
+    assert_eq!(jump_db.jump_distance(0, 0, North), 0);
+    assert_eq!(jump_db.jump_distance(5, 2, SouthEast), 0);
+    assert_eq!(jump_db.get(0, 0, East), (5, true));
+}
+
+#[test]
+fn diagonal_chain_follows_jumps() {
+    use Direction::*;
+
+    // Isolated obstacles next to the main diagonal create a jump point beside each of them.
+    let mut map = BitGrid::new(12, 12);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, !matches!((x, y), (4, 2) | (8, 6)));
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+
+    let chain: Vec<_> = jump_db.diagonal_chain(0, 0, SouthEast).collect();
+    assert!(chain.len() >= 2);
+    let (mut x, mut y, mut cost) = (0, 0, 0.0);
+    for &((cx, cy), chain_cost) in &chain {
+        let (dist, successor) = jump_db.get(x, y, SouthEast);
+        assert!(successor);
+        (x, y) = (x + dist, y + dist);
+        cost += dist as f64 * SAFE_SQRT_2;
+        assert_eq!((cx, cy), (x, y));
+        assert!((chain_cost - cost).abs() < 1e-9);
+    }
+    assert!(!jump_db.has_successor(x, y, SouthEast));
+
+    assert_eq!(jump_db.diagonal_chain(0, 0, NorthWest).count(), 0);
+    assert!(std::panic::catch_unwind(|| jump_db.diagonal_chain(0, 0, East).count()).is_err());
+}
+
+#[test]
+fn statistics_count_jump_points() {
+    let mut map = BitGrid::new(8, 8);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x, y) != (3, 4));
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+    let stats = jump_db.statistics();
+    assert_eq!(stats.jump_points, Some(jump_db.jump_points().len()));
+    assert!(stats.jump_points.unwrap() > 0);
+    assert_eq!((stats.traversable, stats.components), (63, 1));
+    assert!(stats
+        .to_string()
+        .ends_with(&format!("jump points: {}", stats.jump_points.unwrap())));
+}
+
+#[test]
+fn local_jump_points_within_radius() {
+    use mkpath_grid::exact_distance;
+
+    let mut map = BitGrid::new(7, 7);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x, y) != (3, 3));
+        }
+    }
+    let jump_db = JumpDatabase::new(&map);
+    assert_eq!(jump_db.local_jump_points((0, 0), 0.0), []);
+    assert_eq!(jump_db.local_jump_points((0, 0), 3.0), [(2, 2)]);
+    // (4, 4) is behind the obstacle, so it is only reached by going around it.
+    assert_eq!(
+        jump_db.local_jump_points((0, 0), 6.0),
+        [(2, 2), (4, 2), (2, 4)]
+    );
+    assert_eq!(
+        jump_db.local_jump_points((0, 0), 7.0),
+        [(2, 2), (4, 2), (2, 4), (4, 4)]
+    );
+    assert_eq!(jump_db.local_jump_points((1, 3), 2.0), [(2, 2), (2, 4)]);
+    assert_eq!(jump_db.local_jump_points((3, 3), 100.0), []);
+
+    let map = BitGrid::random_obstacles(40, 30, 0.2, 9);
+    let jump_db = JumpDatabase::new(&map);
+    for center in [(0, 0), (13, 7), (25, 20), (39, 29)] {
+        if !map.get(center.0, center.1) {
+            continue;
+        }
+        let mut previous = vec![];
+        for radius in [0.0, 2.5, 6.0, 10.0, 20.0] {
+            let local = jump_db.local_jump_points(center, radius);
+            for &pos in &local {
+                assert!(map.get(pos.0, pos.1));
+                assert!(exact_distance(&map, center, pos) <= radius + 1e-9);
+            }
+            assert!(previous.iter().all(|pos| local.contains(pos)));
+            previous = local;
+        }
+    }
+}
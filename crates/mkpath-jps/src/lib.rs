@@ -2,55 +2,24 @@ use enumset::EnumSet;
 use mkpath_grid::{BitGrid, Direction};
 
 mod canonical;
+pub mod geometry;
 mod jps;
 mod jps_plus;
 mod jump_db;
+mod locator;
+mod weighted;
 
 pub use self::canonical::*;
+pub use self::geometry::{reached_direction, reached_direction_from};
 pub use self::jps::*;
 pub use self::jps_plus::*;
 pub use self::jump_db::*;
+pub use self::locator::*;
+pub use self::weighted::*;
 
+/// Returns the transpose of `map`; see [`BitGrid::transpose`].
 pub fn transpose(map: &BitGrid) -> BitGrid {
-    let mut tmap = BitGrid::new(map.height(), map.width());
-    for x in 0..tmap.width() {
-        for y in 0..tmap.height() {
-            tmap.set(x, y, map.get(y, x));
-        }
-    }
-    tmap
-}
-
-pub fn reached_direction(from: (i32, i32), to: (i32, i32)) -> Option<Direction> {
-    let dx = to.0 - from.0;
-    let dy = to.1 - from.1;
-    if dx.abs() > dy.abs() {
-        if dx < 0 {
-            Some(Direction::West)
-        } else {
-            Some(Direction::East)
-        }
-    } else if dy.abs() > dx.abs() {
-        if dy < 0 {
-            Some(Direction::North)
-        } else {
-            Some(Direction::South)
-        }
-    } else if dx < 0 {
-        if dy < 0 {
-            Some(Direction::NorthWest)
-        } else {
-            Some(Direction::SouthWest)
-        }
-    } else if dx > 0 {
-        if dy < 0 {
-            Some(Direction::NorthEast)
-        } else {
-            Some(Direction::SouthEast)
-        }
-    } else {
-        None
-    }
+    map.transpose()
 }
 
 pub fn canonical_successors(
@@ -154,14 +123,102 @@ pub fn canonical_successors(
     EnumSet::from_u8(SUCCESSORS[going.map_or(8, |d| d as usize)][nb.as_usize()])
 }
 
-fn skipped_past<const D: i32>(start: i32, end: i32, target: i32) -> bool {
-    in_direction::<D>(start, target) && in_direction::<D>(target, end)
+/// Dijkstra's algorithm over any grid expander, for checking expanders in tests.
+#[cfg(test)]
+struct TestSearcher {
+    state: mkpath_core::NodeMemberPointer<(i32, i32)>,
+    g: mkpath_core::NodeMemberPointer<f64>,
+    pqueue_factory: mkpath_core::PriorityQueueFactory,
 }
 
-fn in_direction<const D: i32>(from: i32, to: i32) -> bool {
-    match D {
-        -1 => to < from,
-        1 => from < to,
-        _ => unreachable!(),
+#[cfg(test)]
+impl TestSearcher {
+    fn new(builder: &mut mkpath_core::NodeBuilder) -> Self {
+        TestSearcher {
+            state: builder.add_field((-1, -1)),
+            g: builder.add_field(f64::INFINITY),
+            pqueue_factory: mkpath_core::PriorityQueueFactory::new(builder),
+        }
+    }
+
+    /// Returns the cells of the path from `start` to `target` and its cost, or `None` if the
+    /// target is unreachable.
+    fn search<'a, E: mkpath_core::traits::Expander<'a>>(
+        &mut self,
+        pool: &'a mkpath_grid::GridPool,
+        mut expander: E,
+        start: (i32, i32),
+        target: (i32, i32),
+    ) -> Option<(Vec<(i32, i32)>, f64)>
+    where
+        E::Edge: mkpath_core::traits::Successor<'a> + mkpath_core::traits::Cost,
+    {
+        use mkpath_core::traits::{Cost, OpenList, Successor};
+
+        let TestSearcher { state, g, .. } = *self;
+        let mut open = self.pqueue_factory.new_queue(g);
+        let mut edges = vec![];
+        let start_node = pool.generate(start);
+        start_node.set(g, 0.0);
+        open.relaxed(start_node);
+        while let Some(node) = open.next() {
+            if node.get(state) == target {
+                let mut path = vec![node];
+                while let Some(parent) = path[path.len() - 1].get_parent() {
+                    path.push(parent);
+                }
+                let path = path.iter().rev().map(|node| node.get(state)).collect();
+                return Some((path, node.get(g)));
+            }
+            edges.clear();
+            expander.expand(node, &mut edges);
+            for edge in &edges {
+                let new_g = node.get(g) + edge.cost();
+                if new_g < edge.successor().get(g) {
+                    edge.successor().set(g, new_g);
+                    edge.successor().set_parent(Some(node));
+                    open.relaxed(edge.successor());
+                }
+            }
+        }
+        None
+    }
+
+    /// Asserts that the cost of the path found with `expander` is `expected`, which is infinite
+    /// if the target is unreachable.
+    #[track_caller]
+    fn assert_cost<'a, E: mkpath_core::traits::Expander<'a>>(
+        &mut self,
+        pool: &'a mkpath_grid::GridPool,
+        expander: E,
+        start: (i32, i32),
+        target: (i32, i32),
+        expected: f64,
+    ) where
+        E::Edge: mkpath_core::traits::Successor<'a> + mkpath_core::traits::Cost,
+    {
+        match self.search(pool, expander, start, target) {
+            Some((_, found)) => assert!(
+                (found - expected).abs() < 1e-9,
+                "{start:?} to {target:?}: found {found}, expected {expected}"
+            ),
+            None => assert!(
+                expected.is_infinite(),
+                "{start:?} to {target:?}: found no path, expected {expected}"
+            ),
+        }
+    }
+}
+
+/// A map with a vertical wall with two gaps, and to its right a horizontal wall with one gap.
+#[cfg(test)]
+fn walled_map() -> BitGrid {
+    let mut map = BitGrid::new(16, 12);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x == 5 && y != 3 && y != 9) || (y == 6 && x > 8 && x != 12);
+            map.set(x, y, !wall);
+        }
     }
+    map
 }
@@ -0,0 +1,393 @@
+use enumset::EnumSet;
+use mkpath_core::traits::{Expander, WeightedEdge};
+use mkpath_core::{NodeMemberPointer, NodeRef};
+use mkpath_grid::{Direction, GridMap, GridNodePool, SAFE_SQRT_2};
+
+use crate::{canonical_successors, JumpDatabase};
+
+/// Strategy for finding jump points, used by [`GenericJpsExpander`].
+///
+/// The expander takes care of canonical successor pruning and of spawning orthogonal jumps along
+/// diagonal jumps; the locator only needs to find where each individual jump stops.
+///
+/// # Safety
+/// [`GenericJpsExpander`] generates nodes for the cells returned by the locator without bounds
+/// checking, so implementations must uphold the postconditions documented on each method.
+pub unsafe trait JumpPointLocator {
+    /// Width of the map. All cells the locator reports must have `x` in `0..width()`.
+    fn width(&self) -> i32;
+
+    /// Height of the map. All cells the locator reports must have `y` in `0..height()`.
+    fn height(&self) -> i32;
+
+    /// Returns the set of traversable neighbors of a cell, as
+    /// [`BitGrid::get_neighborhood`](mkpath_grid::BitGrid::get_neighborhood).
+    ///
+    /// The expander only starts jumps from a node in directions from this set. Blockers which are
+    /// not part of the underlying map must be taken into account both here and by the jumps
+    /// themselves, since jumps continuing along a diagonal jump are not checked against it.
+    ///
+    /// Preconditions:
+    /// - `x`, `y` are in-bounds of the map.
+    fn neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction>;
+
+    /// Jumps orthogonally from `x`, `y` in direction `dir`.
+    ///
+    /// Returns the distance to the first jump point or to `target`, whichever comes first, or
+    /// `None` if the jump reaches neither before being blocked, including when the cell adjacent
+    /// to `x`, `y` in direction `dir` is not traversable.
+    ///
+    /// # Safety
+    /// Callers must ensure that:
+    /// - `x`, `y` are in-bounds of the map.
+    /// - `dir` is orthogonal.
+    ///
+    /// Implementations must ensure that if `Some(d)` is returned, `d > 0` and the cell `d` steps
+    /// away in direction `dir` is in-bounds of the map.
+    unsafe fn jump_ortho(&self, x: i32, y: i32, dir: Direction, target: (i32, i32)) -> Option<i32>;
+
+    /// Jumps diagonally from `x`, `y` in direction `dir`.
+    ///
+    /// Returns the distance to the first cell from which an orthogonal jump in one of the two
+    /// components of `dir` finds a jump point or `target`, or to `target` itself, or `None` if the
+    /// jump reaches no such cell before being blocked, including when diagonal movement from
+    /// `x`, `y` in direction `dir` is not possible.
+    ///
+    /// # Safety
+    /// Callers must ensure that:
+    /// - `x`, `y` are in-bounds of the map.
+    /// - `dir` is diagonal.
+    ///
+    /// Implementations must ensure that if `Some(d)` is returned, `d > 0` and the cell `d` steps
+    /// away in direction `dir` is in-bounds of the map.
+    unsafe fn jump_diagonal(
+        &self,
+        x: i32,
+        y: i32,
+        dir: Direction,
+        target: (i32, i32),
+    ) -> Option<i32>;
+}
+
+/// Jump point locator using a precomputed [`JumpDatabase`], as in JPS+.
+pub struct OfflineJpl<'a> {
+    jump_db: &'a JumpDatabase,
+}
+
+impl<'a> OfflineJpl<'a> {
+    pub fn new(jump_db: &'a JumpDatabase) -> Self {
+        assert!(
+            jump_db.has_diagonals(),
+            "jump database must have diagonal jump data"
+        );
+        OfflineJpl { jump_db }
+    }
+}
+
+// SAFETY: Jump database entries never extend past the edge of the map, and the target is only
+//         returned if it lies within such a jump.
+unsafe impl JumpPointLocator for OfflineJpl<'_> {
+    fn width(&self) -> i32 {
+        self.jump_db.width()
+    }
+
+    fn height(&self) -> i32 {
+        self.jump_db.height()
+    }
+
+    #[inline(always)]
+    fn neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction> {
+        self.jump_db.map().get_neighborhood(x, y)
+    }
+
+    #[inline(always)]
+    unsafe fn jump_ortho(&self, x: i32, y: i32, dir: Direction, target: (i32, i32)) -> Option<i32> {
+        self.jump_db.ortho_jump_unchecked(x, y, dir, target)
+    }
+
+    #[inline(always)]
+    unsafe fn jump_diagonal(
+        &self,
+        x: i32,
+        y: i32,
+        dir: Direction,
+        target: (i32, i32),
+    ) -> Option<i32> {
+        self.jump_db
+            .diagonal_jump_unchecked(x, y, dir, target)
+            .map(|(dist, _)| dist)
+    }
+}
+
+/// Jump point locator which scans the map cell by cell, as in the original JPS.
+///
+/// This works on any [`GridMap`], such as an [`OverlayGrid`](mkpath_grid::OverlayGrid) with
+/// temporary obstacles, at the cost of being much slower than [`JpsExpander`](crate::JpsExpander)
+/// or [`OfflineJpl`] on plain maps.
+pub struct OnlineJpl<'a, M> {
+    map: &'a M,
+}
+
+impl<'a, M: GridMap> OnlineJpl<'a, M> {
+    pub fn new(map: &'a M) -> Self {
+        OnlineJpl { map }
+    }
+}
+
+// SAFETY: Jumps only ever step onto cells which the map reports as traversable, and GridMap
+//         guarantees that traversable cells are in-bounds.
+unsafe impl<M: GridMap> JumpPointLocator for OnlineJpl<'_, M> {
+    fn width(&self) -> i32 {
+        self.map.width()
+    }
+
+    fn height(&self) -> i32 {
+        self.map.height()
+    }
+
+    #[inline(always)]
+    fn neighborhood(&self, x: i32, y: i32) -> EnumSet<Direction> {
+        self.map.get_neighborhood(x, y)
+    }
+
+    unsafe fn jump_ortho(&self, x: i32, y: i32, dir: Direction, target: (i32, i32)) -> Option<i32> {
+        let (dx, dy) = dir.vector();
+        let (mut x, mut y) = (x, y);
+        let mut dist = 0;
+        let mut nb = self.map.get_neighborhood(x, y);
+        loop {
+            if !nb.contains(dir) {
+                return None;
+            }
+            x += dx;
+            y += dy;
+            dist += 1;
+
+            nb = self.map.get_neighborhood(x, y);
+            if (x, y) == target || !(canonical_successors(nb, Some(dir)) - dir).is_empty() {
+                return Some(dist);
+            }
+        }
+    }
+
+    unsafe fn jump_diagonal(
+        &self,
+        x: i32,
+        y: i32,
+        dir: Direction,
+        target: (i32, i32),
+    ) -> Option<i32> {
+        let (dx, dy) = dir.vector();
+        let (dir_x, dir_y) = match dir {
+            Direction::NorthWest => (Direction::West, Direction::North),
+            Direction::SouthWest => (Direction::West, Direction::South),
+            Direction::SouthEast => (Direction::East, Direction::South),
+            Direction::NorthEast => (Direction::East, Direction::North),
+            _ => unreachable!(),
+        };
+
+        let (mut x, mut y) = (x, y);
+        let mut dist = 0;
+        let mut nb = self.map.get_neighborhood(x, y);
+        loop {
+            // Diagonal moves may not cut corners.
+            if !nb.is_superset(dir | dir_x | dir_y) {
+                return None;
+            }
+            x += dx;
+            y += dy;
+            dist += 1;
+
+            if (x, y) == target {
+                return Some(dist);
+            }
+            nb = self.map.get_neighborhood(x, y);
+            unsafe {
+                // The cell is traversable, so it is in-bounds.
+                if self.jump_ortho(x, y, dir_x, target).is_some()
+                    || self.jump_ortho(x, y, dir_y, target).is_some()
+                {
+                    return Some(dist);
+                }
+            }
+        }
+    }
+}
+
+/// Jump point search expander using a custom [`JumpPointLocator`].
+pub struct GenericJpsExpander<'a, L, P> {
+    node_pool: &'a P,
+    jpl: L,
+    state: NodeMemberPointer<(i32, i32)>,
+    target: (i32, i32),
+    turn_points: bool,
+}
+
+impl<'a, L: JumpPointLocator, P: GridNodePool> GenericJpsExpander<'a, L, P> {
+    pub fn from_locator(
+        jpl: L,
+        node_pool: &'a P,
+        state: NodeMemberPointer<(i32, i32)>,
+        target: (i32, i32),
+    ) -> Self {
+        // Establish invariant that coordinates in-bounds of the map are also in-bounds of the
+        // node pool.
+        assert!(
+            node_pool.width() >= jpl.width(),
+            "node pool must be wide enough for the map"
+        );
+        assert!(
+            node_pool.height() >= jpl.height(),
+            "node pool must be tall enough for the map"
+        );
+
+        GenericJpsExpander {
+            node_pool,
+            jpl,
+            state,
+            target,
+            turn_points: false,
+        }
+    }
+
+    /// Makes diagonal jumps stop at the cells where they turn orthogonally, generating those cells
+    /// as successors, as in the original JPS.
+    ///
+    /// By default, the successors of orthogonal jumps made partway along a diagonal jump are
+    /// generated directly, so an edge may be a diagonal segment followed by an orthogonal one.
+    /// With turn points, every edge is a straight line, so a path (including the turn made to reach
+    /// the target) can be followed exactly by moving in a single direction between consecutive
+    /// nodes. This generates more nodes, so searches are somewhat slower.
+    pub fn with_turn_points(mut self) -> Self {
+        self.turn_points = true;
+        self
+    }
+
+    pub fn locator(&self) -> &L {
+        &self.jpl
+    }
+
+    #[inline(always)]
+    unsafe fn jump_ortho(
+        &self,
+        x: i32,
+        y: i32,
+        dir: Direction,
+        cost: f64,
+        edges: &mut Vec<WeightedEdge<'a>>,
+    ) {
+        debug_assert!(dir.orthogonal());
+        let (dx, dy) = dir.vector();
+
+        if let Some(dist) = self.jpl.jump_ortho(x, y, dir, self.target) {
+            edges.push(WeightedEdge {
+                successor: self
+                    .node_pool
+                    .generate_unchecked((x + dx * dist, y + dy * dist)),
+                cost: cost + dist as f64,
+            })
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn jump_diagonal(
+        &self,
+        mut x: i32,
+        mut y: i32,
+        dir: Direction,
+        edges: &mut Vec<WeightedEdge<'a>>,
+    ) {
+        let (dx, dy) = dir.vector();
+        let (dir_x, dir_y) = match dir {
+            Direction::NorthWest => (Direction::West, Direction::North),
+            Direction::SouthWest => (Direction::West, Direction::South),
+            Direction::SouthEast => (Direction::East, Direction::South),
+            Direction::NorthEast => (Direction::East, Direction::North),
+            _ => unreachable!(),
+        };
+
+        let mut cost = 0.0;
+        while let Some(dist) = self.jpl.jump_diagonal(x, y, dir, self.target) {
+            x += dx * dist;
+            y += dy * dist;
+            cost += dist as f64 * SAFE_SQRT_2;
+
+            if (x, y) == self.target || self.turn_points {
+                edges.push(WeightedEdge {
+                    successor: self.node_pool.generate_unchecked((x, y)),
+                    cost,
+                });
+                break;
+            }
+
+            // Jumps which are blocked right away return `None`, so there is no need to check
+            // the neighborhood here, which keeps this loop as tight as in JPS+.
+            self.jump_ortho(x, y, dir_x, cost, edges);
+            self.jump_ortho(x, y, dir_y, cost, edges);
+        }
+    }
+}
+
+impl<'a, L: JumpPointLocator, P: GridNodePool> Expander<'a> for GenericJpsExpander<'a, L, P> {
+    type Edge = WeightedEdge<'a>;
+
+    fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<Self::Edge>) {
+        let (x, y) = node.get(self.state);
+
+        let dir = node.get_parent().and_then(|parent| {
+            let (px, py) = parent.get(self.state);
+            crate::reached_direction((px, py), (x, y))
+        });
+
+        let successors = canonical_successors(self.jpl.neighborhood(x, y), dir);
+
+        unsafe {
+            // The node is in-bounds and each jump is made in the right kind of direction.
+            for dir in successors {
+                if dir.orthogonal() {
+                    self.jump_ortho(x, y, dir, 0.0, edges);
+                } else {
+                    self.jump_diagonal(x, y, dir, edges);
+                }
+            }
+        }
+    }
+
+    fn max_degree(&self) -> usize {
+        8
+    }
+}
+
+#[test]
+fn online_jps_avoids_overlay_obstacles() {
+    use mkpath_core::NodeBuilder;
+    use mkpath_grid::{exact_distance, GridPool, OverlayGrid};
+
+    use crate::{walled_map, TestSearcher};
+
+    let map = walled_map();
+    let mut overlay = OverlayGrid::new(&map);
+    for (x, y) in [(5, 9), (12, 6), (2, 2), (3, 2), (8, 8), (9, 7)] {
+        overlay.block(x, y);
+    }
+
+    let mut builder = NodeBuilder::new();
+    let mut searcher = TestSearcher::new(&mut builder);
+    let state = searcher.state;
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+    for start in [(0, 0), (7, 3), (15, 11)] {
+        for ty in 0..map.height() {
+            for tx in 0..map.width() {
+                if !overlay.get(tx, ty) {
+                    continue;
+                }
+                pool.reset();
+                let jpl = OnlineJpl::new(&overlay);
+                let expander = GenericJpsExpander::from_locator(jpl, &pool, state, (tx, ty));
+                let expected = exact_distance(&overlay, start, (tx, ty));
+                searcher.assert_cost(&pool, expander, start, (tx, ty), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use enumset::EnumSet;
+use mkpath_core::traits::{Expander, WeightedEdge};
+use mkpath_core::{NodeMemberPointer, NodeRef};
+use mkpath_grid::{BitGrid, Direction, Grid, GridNodePool, SAFE_SQRT_2};
+
+use crate::canonical_successors;
+
+/// Jump Point Search expander for maps with non-uniform cell costs.
+///
+/// JPS relies on every orthogonal move costing the same and every diagonal move costing the same,
+/// which is not the case on maps with terrain costs; using [`JpsExpander`](crate::JpsExpander) or
+/// [`JpsPlusExpander`](crate::JpsPlusExpander) on such maps silently produces suboptimal paths.
+///
+/// This expander only prunes and jumps through cells whose 3x3 neighborhood has uniform cost. At
+/// cells next to a cost boundary it stops and falls back to full 8-connected expansion. This
+/// preserves optimality on any map, but it is only faster than plain 8-connected search when the
+/// map consists of large regions of uniform cost.
+///
+/// Moving into a cell costs the length of the move (1 or [`SAFE_SQRT_2`]) multiplied by the
+/// weight of the cell being entered. Weights of traversable cells must be positive and finite.
+pub struct WeightedJpsExpander<'a, P> {
+    node_pool: &'a P,
+    map: &'a BitGrid,
+    weights: &'a Grid<f64>,
+    state: NodeMemberPointer<(i32, i32)>,
+    target: (i32, i32),
+}
+
+impl<'a, P: GridNodePool> WeightedJpsExpander<'a, P> {
+    pub fn new(
+        map: &'a BitGrid,
+        weights: &'a Grid<f64>,
+        node_pool: &'a P,
+        state: NodeMemberPointer<(i32, i32)>,
+        target: (i32, i32),
+    ) -> Self {
+        // Establish invariant that coordinates in-bounds of the map are also in-bounds of the
+        // node pool.
+        assert!(
+            node_pool.width() >= map.width(),
+            "node pool must be wide enough for the map"
+        );
+        assert!(
+            node_pool.height() >= map.height(),
+            "node pool must be tall enough for the map"
+        );
+
+        assert_eq!(
+            map.width(),
+            weights.width(),
+            "weight grid has incorrect width"
+        );
+        assert_eq!(
+            map.height(),
+            weights.height(),
+            "weight grid has incorrect height"
+        );
+
+        WeightedJpsExpander {
+            node_pool,
+            map,
+            weights,
+            state,
+            target,
+        }
+    }
+
+    /// Returns whether all traversable cells in the 3x3 neighborhood of `x`, `y` have the same
+    /// weight.
+    ///
+    /// Precondition: `x`, `y` is traversable.
+    fn uniform(&self, x: i32, y: i32) -> bool {
+        let weight = self.weights[(x, y)];
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                // x, y is in-bounds, so the neighbors are padded in-bounds of the map. Traversable
+                // cells are in-bounds of the weight grid.
+                if self.map.get(x + dx, y + dy) && self.weights[(x + dx, y + dy)] != weight {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Precondition: `x`, `y` is traversable.
+    fn push_edge(&self, edges: &mut Vec<WeightedEdge<'a>>, x: i32, y: i32, cost: f64) {
+        edges.push(WeightedEdge {
+            // SAFETY: x, y is traversable, so it is in-bounds of the map, and therefore also
+            //         in-bounds of the node pool.
+            successor: unsafe { self.node_pool.generate_unchecked((x, y)) },
+            cost,
+        });
+    }
+
+    fn jump_ortho(
+        &self,
+        mut x: i32,
+        mut y: i32,
+        dir: Direction,
+        mut cost: f64,
+        edges: &mut Vec<WeightedEdge<'a>>,
+    ) {
+        let (dx, dy) = dir.vector();
+        loop {
+            x += dx;
+            y += dy;
+            if !self.map.get(x, y) {
+                return;
+            }
+            cost += self.weights[(x, y)];
+
+            let forced = canonical_successors(self.map.get_neighborhood(x, y), Some(dir)) - dir;
+            if (x, y) == self.target || !forced.is_empty() || !self.uniform(x, y) {
+                self.push_edge(edges, x, y, cost);
+                return;
+            }
+        }
+    }
+
+    /// Precondition: the diagonal move from `x`, `y` in direction `dir` is legal.
+    fn jump_diagonal(
+        &self,
+        mut x: i32,
+        mut y: i32,
+        dir: Direction,
+        edges: &mut Vec<WeightedEdge<'a>>,
+    ) {
+        let (dx, dy) = dir.vector();
+        let dir_x = if dx < 0 {
+            Direction::West
+        } else {
+            Direction::East
+        };
+        let dir_y = if dy < 0 {
+            Direction::North
+        } else {
+            Direction::South
+        };
+
+        let mut cost = 0.0;
+        loop {
+            x += dx;
+            y += dy;
+            cost += SAFE_SQRT_2 * self.weights[(x, y)];
+
+            if (x, y) == self.target || !self.uniform(x, y) {
+                self.push_edge(edges, x, y, cost);
+                return;
+            }
+
+            let nb = self.map.get_neighborhood(x, y);
+            if nb.contains(dir_x) {
+                self.jump_ortho(x, y, dir_x, cost, edges);
+            }
+            if nb.contains(dir_y) {
+                self.jump_ortho(x, y, dir_y, cost, edges);
+            }
+            if !nb.is_superset(dir | dir_x | dir_y) {
+                return;
+            }
+        }
+    }
+}
+
+impl<'a, P: GridNodePool> Expander<'a> for WeightedJpsExpander<'a, P> {
+    type Edge = WeightedEdge<'a>;
+
+    fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<Self::Edge>) {
+        let (x, y) = node.get(self.state);
+        let nb = self.map.get_neighborhood(x, y);
+
+        if !self.uniform(x, y) {
+            // Pruning is only valid when all nearby moves of the same kind cost the same, so at
+            // cost boundaries we generate every neighbor.
+            for dir in canonical_successors(nb, None) {
+                let (dx, dy) = dir.vector();
+                let length = if dir.orthogonal() { 1.0 } else { SAFE_SQRT_2 };
+                let cost = length * self.weights[(x + dx, y + dy)];
+                self.push_edge(edges, x + dx, y + dy, cost);
+            }
+            return;
+        }
+
+        let dir = node.get_parent().and_then(|parent| {
+            let (px, py) = parent.get(self.state);
+            crate::reached_direction((px, py), (x, y))
+        });
+
+        let successors: EnumSet<Direction> = canonical_successors(nb, dir);
+        for dir in successors {
+            if dir.orthogonal() {
+                self.jump_ortho(x, y, dir, 0.0, edges);
+            } else {
+                self.jump_diagonal(x, y, dir, edges);
+            }
+        }
+    }
+
+    fn max_degree(&self) -> usize {
+        8
+    }
+}
+
+#[test]
+fn weighted_jps_is_optimal() {
+    use mkpath_core::NodeBuilder;
+    use mkpath_grid::GridPool;
+
+    use crate::{walled_map, TestSearcher};
+
+    let map = walled_map();
+    let weights = Grid::new(map.width(), map.height(), |x, y| match (x, y) {
+        (2..=4, _) => 3.0,
+        (_, 0..=2) if x > 7 => 2.0,
+        (13, 9) => 5.0,
+        _ => 1.0,
+    });
+
+    let reference = |start: (i32, i32)| {
+        let mut dist = Grid::filled(map.width(), map.height(), f64::INFINITY);
+        dist[start] = 0.0;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for y in 0..map.height() {
+                for x in 0..map.width() {
+                    if !map.get(x, y) {
+                        continue;
+                    }
+                    for dir in canonical_successors(map.get_neighborhood(x, y), None) {
+                        let (dx, dy) = dir.vector();
+                        let length = if dir.orthogonal() { 1.0 } else { SAFE_SQRT_2 };
+                        let new_dist = dist[(x, y)] + length * weights[(x + dx, y + dy)];
+                        if new_dist < dist[(x + dx, y + dy)] - 1e-9 {
+                            dist[(x + dx, y + dy)] = new_dist;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        dist
+    };
+
+    let mut builder = NodeBuilder::new();
+    let mut searcher = TestSearcher::new(&mut builder);
+    let state = searcher.state;
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+    for start in [(0, 0), (7, 3), (15, 11), (13, 9)] {
+        let dist = reference(start);
+        for ty in 0..map.height() {
+            for tx in 0..map.width() {
+                if !map.get(tx, ty) {
+                    continue;
+                }
+                pool.reset();
+                let expander = WeightedJpsExpander::new(&map, &weights, &pool, state, (tx, ty));
+                searcher.assert_cost(&pool, expander, start, (tx, ty), dist[(tx, ty)]);
+            }
+        }
+    }
+}
@@ -1,6 +1,5 @@
 use std::io::{Read, Write};
 
-use mkpath_core::traits::{Cost, Expander, Successor};
 use mkpath_ess::{ExplicitStateSpace, Mapper};
 use rand::Rng;
 use rand_pcg::Pcg64;
@@ -12,10 +11,7 @@ pub struct DifferentialHeuristic<SS: ExplicitStateSpace, const N: usize> {
 }
 
 impl<SS: ExplicitStateSpace, const N: usize> DifferentialHeuristic<SS, N> {
-    pub fn calculate(domain: &SS, mapper: &Mapper<SS>) -> Self
-    where
-        for<'a> <SS::Expander<'a> as Expander<'a>>::Edge: Successor<'a> + Cost,
-    {
+    pub fn calculate(domain: &SS, mapper: &Mapper<SS>) -> Self {
         let mut this = Self {
             data: domain.new_auxiliary(|_| [f64::INFINITY; N]),
         };
@@ -23,7 +19,7 @@ impl<SS: ExplicitStateSpace, const N: usize> DifferentialHeuristic<SS, N> {
         let mut rng = Pcg64::new(0xcafef00dd15ea5e5, 0xa02bdbf7bb3c0a7ac28fa16a64abf96);
 
         let nodes_required = (0..mapper.components())
-            .map(|comp| mapper.component_id_range(comp).len())
+            .map(|comp| mapper.component_size(comp))
             .max()
             .unwrap_or(0);
 
@@ -42,8 +38,7 @@ impl<SS: ExplicitStateSpace, const N: usize> DifferentialHeuristic<SS, N> {
                         }
                     });
                 } else {
-                    for id in id_range.clone() {
-                        let state = mapper.to_state(id);
+                    for state in mapper.component_states(component) {
                         let d = this.data[state]
                             .iter()
                             .fold(f64::INFINITY, |prev, &new| prev.min(new));
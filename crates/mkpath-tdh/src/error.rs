@@ -0,0 +1,104 @@
+use mkpath_ess::ExplicitStateSpace;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+use crate::Searcher;
+
+/// Accuracy of a heuristic compared to exact distances, as computed by [`heuristic_error`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ErrorStats {
+    /// Number of (start, goal) pairs the heuristic was evaluated on.
+    pub pairs: usize,
+    /// Mean of `(d - h) / d` over all pairs, where `d` is the exact distance and `h` the heuristic
+    /// value. 0 means the heuristic is perfect.
+    pub mean_relative_error: f64,
+    /// Largest `(d - h) / d` over all pairs.
+    pub max_relative_error: f64,
+    /// Number of pairs for which the heuristic overestimates the exact distance.
+    pub inadmissible: usize,
+}
+
+/// Measures how closely `heuristic` estimates exact distances in `domain`.
+///
+/// Picks `samples` start states using a deterministic generator seeded with `seed`, and computes
+/// the exact distance from each of them to every reachable state using Dijkstra's algorithm. The
+/// heuristic is called as `heuristic(start, goal)` for every such pair of distinct states.
+///
+/// This is useful for deciding how much preprocessing a heuristic is worth, e.g. how many pivots
+/// a [`DifferentialHeuristic`](crate::DifferentialHeuristic) should use.
+pub fn heuristic_error<SS: ExplicitStateSpace>(
+    domain: &SS,
+    mut heuristic: impl FnMut(SS::State, SS::State) -> f64,
+    samples: usize,
+    seed: u64,
+) -> ErrorStats {
+    let states = domain.list_valid_states();
+    let mut stats = ErrorStats {
+        pairs: 0,
+        mean_relative_error: 0.0,
+        max_relative_error: 0.0,
+        inadmissible: 0,
+    };
+    if states.is_empty() {
+        return stats;
+    }
+
+    let mut rng = Pcg64::seed_from_u64(seed);
+    let mut searcher = Searcher::new(domain, states.len());
+    let mut total = 0.0;
+
+    for _ in 0..samples {
+        let start = states[rng.gen_range(0..states.len())];
+        searcher.search(domain, start, |goal, dist| {
+            if dist <= 0.0 {
+                return;
+            }
+            let h = heuristic(start, goal);
+            let error = (dist - h) / dist;
+            stats.pairs += 1;
+            total += error;
+            stats.max_relative_error = stats.max_relative_error.max(error);
+            if h > dist + 1e-9 {
+                stats.inadmissible += 1;
+            }
+        });
+    }
+
+    if stats.pairs > 0 {
+        stats.mean_relative_error = total / stats.pairs as f64;
+    }
+    stats
+}
+
+#[test]
+fn octile_error_on_small_grid() {
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedGrid};
+
+    let mut map = BitGrid::new(12, 10);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, true);
+        }
+    }
+
+    // Octile distance is exact on an empty map.
+    let open = EightConnectedGrid(&map);
+    let stats = heuristic_error(&open, octile_distance, 5, 1);
+    assert_eq!(stats.pairs, 5 * (12 * 10 - 1));
+    assert!(stats.max_relative_error < 1e-9);
+    assert_eq!(stats.inadmissible, 0);
+
+    for y in 0..9 {
+        map.set(6, y, false);
+    }
+    let walled = EightConnectedGrid(&map);
+    let stats = heuristic_error(&walled, octile_distance, 5, 1);
+    assert!(stats.mean_relative_error > 0.0);
+    assert!(stats.max_relative_error >= stats.mean_relative_error);
+    assert!(stats.max_relative_error < 1.0);
+    assert_eq!(stats.inadmissible, 0);
+
+    // Overestimating is detected.
+    let stats = heuristic_error(&walled, |s, g| 2.0 * octile_distance(s, g), 5, 1);
+    assert!(stats.inadmissible > 0);
+}
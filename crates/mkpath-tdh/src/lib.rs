@@ -1,6 +1,8 @@
 mod differential;
+mod error;
 
 pub use differential::DifferentialHeuristic;
+pub use error::{heuristic_error, ErrorStats};
 use mkpath_core::traits::{Cost, Expander, NodePool, OpenList, Successor};
 use mkpath_core::{NodeBuilder, NodeMemberPointer, PriorityQueueFactory};
 use mkpath_ess::ExplicitStateSpace;
@@ -12,10 +14,7 @@ struct Searcher<SS: ExplicitStateSpace> {
     g: NodeMemberPointer<f64>,
 }
 
-impl<SS: ExplicitStateSpace> Searcher<SS>
-where
-    for<'a> <SS::Expander<'a> as Expander<'a>>::Edge: Successor<'a> + Cost,
-{
+impl<SS: ExplicitStateSpace> Searcher<SS> {
     fn new(domain: &SS, nodes_required: usize) -> Self {
         let mut builder = NodeBuilder::new();
         let state = domain.add_state_field(&mut builder);
@@ -1,15 +1,18 @@
 use std::path::PathBuf;
 
 use clap::Parser;
-use mkpath::grid::{octile_distance, EightConnectedExpander, GridPool};
+use mkpath::grid::{octile_distance, BitGrid, EightConnectedExpander, GridPool};
+use mkpath::traits::CostValue;
 use mkpath::{AStarSearcher, NodeBuilder, PriorityQueueFactory};
-use mkpath::traits::NodePool;
 
 mod movingai;
 
 #[derive(Parser)]
 struct Options {
     scen: PathBuf,
+    /// Store costs as f32 rather than f64, halving the size of the cost fields.
+    #[arg(long)]
+    f32: bool,
 }
 
 fn main() {
@@ -20,19 +23,29 @@ fn main() {
     let scen = movingai::read_scenario(&opt.scen).unwrap();
     let map = movingai::read_bitgrid(&scen.map).unwrap();
 
+    let t2 = std::time::Instant::now();
+
+    match opt.f32 {
+        true => run::<f32>(&map, &scen),
+        false => run::<f64>(&map, &scen),
+    }
+
+    let t3 = std::time::Instant::now();
+    eprintln!("Load: {:<10.2?} Search: {:.2?}", t2 - t1, t3 - t2);
+}
+
+fn run<C: CostValue>(map: &BitGrid, scen: &movingai::Scenario) {
     let mut builder = NodeBuilder::new();
     let state = builder.add_field((-1, -1));
-    let mut astar = AStarSearcher::new(&mut builder);
+    let mut astar = AStarSearcher::<C>::with_cost_type(&mut builder);
     let mut open_list_factory = PriorityQueueFactory::new(&mut builder);
     let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
 
-    let t2 = std::time::Instant::now();
-
     for problem in &scen.instances {
         pool.reset();
 
         let open_list = open_list_factory.new_queue(astar.ordering());
-        let expander = EightConnectedExpander::new(&map, &pool, state);
+        let expander = EightConnectedExpander::new(map, &pool, state);
 
         let result = astar.search(
             expander,
@@ -43,14 +56,11 @@ fn main() {
         );
 
         if let Some(path) = result {
-            let cost = path.last().unwrap().get(astar.g());
+            let cost = path.last().unwrap().get(astar.g()).to_f64();
             let path: Vec<_> = path.into_iter().map(|node| node.get(state)).collect();
             println!("{cost:.2} {path:?}");
         } else {
             println!("failed to find path");
         }
     }
-
-    let t3 = std::time::Instant::now();
-    eprintln!("Load: {:<10.2?} Search: {:.2?}", t2 - t1, t3 - t2);
 }
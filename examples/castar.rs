@@ -3,7 +3,6 @@ use std::path::PathBuf;
 use clap::Parser;
 use mkpath::grid::{octile_distance, GridPool};
 use mkpath::jps::CanonicalGridExpander;
-use mkpath::traits::NodePool;
 use mkpath::{AStarSearcher, NodeBuilder, PriorityQueueFactory};
 
 mod movingai;
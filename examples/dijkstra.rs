@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use mkpath::grid::{EightConnectedExpander, GridPool};
-use mkpath::traits::{Expander, NodePool, OpenList};
+use mkpath::traits::{Expander, OpenList};
 use mkpath::NodeBuilder;
 use mkpath_cpd::BucketQueueFactory;
 use mkpath_grid::GridEdge;
@@ -4,9 +4,8 @@ use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use clap::Parser;
-use mkpath::cpd::{CpdRow, FirstMoveSearcher, StateIdMapper};
+use mkpath::cpd::{CpdRow, FirstMoveSearcher, FormatVersion, StateIdMapper};
 use mkpath::grid::{EightConnectedExpander, Grid, GridPool};
-use mkpath::traits::NodePool;
 use mkpath::NodeBuilder;
 use mkpath_cpd::BucketQueueFactory;
 use mkpath_grid::{Direction, SAFE_SQRT_2};
@@ -14,6 +13,11 @@ use rayon::prelude::*;
 
 mod movingai;
 
+const FORMAT: FormatVersion = FormatVersion {
+    magic: 0xA53BE83F,
+    version: 1,
+};
+
 #[derive(Parser)]
 struct Options {
     path: PathBuf,
@@ -49,17 +53,9 @@ fn main() {
                     break;
                 }
                 let dir = rows[mapper.state_to_id(state)].lookup(target_id);
-                match Direction::try_from(dir).unwrap() {
-                    Direction::North => path.push((state.0, state.1 - 1)),
-                    Direction::West => path.push((state.0 - 1, state.1)),
-                    Direction::South => path.push((state.0, state.1 + 1)),
-                    Direction::East => path.push((state.0 + 1, state.1)),
-                    Direction::NorthWest => path.push((state.0 - 1, state.1 - 1)),
-                    Direction::SouthWest => path.push((state.0 - 1, state.1 + 1)),
-                    Direction::SouthEast => path.push((state.0 + 1, state.1 + 1)),
-                    Direction::NorthEast => path.push((state.0 + 1, state.1 - 1)),
-                }
-                if dir > 3 {
+                let dir = Direction::from_edge_id(dir).unwrap();
+                path.push(dir.step(state));
+                if dir.is_diagonal() {
                     cost += SAFE_SQRT_2;
                 } else {
                     cost += 1.0;
@@ -98,7 +94,7 @@ impl StateIdMapper for GridMapper {
 fn build_cpd(map: &Path, output: &Path) -> std::io::Result<()> {
     let map = movingai::read_bitgrid(map)?;
 
-    let mut grid = Grid::new(map.width(), map.height(), |_, _| usize::MAX);
+    let mut grid = Grid::filled(map.width(), map.height(), usize::MAX);
     let mut array = vec![];
 
     let mut builder = NodeBuilder::new();
@@ -181,12 +177,12 @@ fn build_cpd(map: &Path, output: &Path) -> std::io::Result<()> {
 
     let mut output = BufWriter::new(File::create(output)?);
 
+    FORMAT.save(&mut output)?;
     output.write_all(&(mapper.array.len() as u32).to_le_bytes())?;
     for (x, y) in mapper.array {
         output.write_all(&x.to_le_bytes())?;
         output.write_all(&y.to_le_bytes())?;
     }
-    output.write_all(&0xA53BE83Fu32.to_le_bytes())?;
     for row in rows {
         row.save(&mut output)?;
     }
@@ -200,12 +196,13 @@ fn load_cpd(
     height: i32,
 ) -> std::io::Result<(GridMapper, Vec<Box<CpdRow>>)> {
     let mut cpd_file = BufReader::new(File::open(cpd_file)?);
+    FORMAT.check(&mut cpd_file)?;
 
     let mut bytes = [0; 4];
     cpd_file.read_exact(&mut bytes)?;
     let len = u32::from_le_bytes(bytes) as usize;
 
-    let mut grid = Grid::new(width, height, |_, _| usize::MAX);
+    let mut grid = Grid::filled(width, height, usize::MAX);
     let mut array = vec![(0, 0); len];
     for id in 0..len {
         cpd_file.read_exact(&mut bytes)?;
@@ -216,9 +213,6 @@ fn load_cpd(
         array[id] = (x, y);
     }
 
-    cpd_file.read_exact(&mut bytes)?;
-    assert_eq!(u32::from_le_bytes(bytes), 0xA53BE83F);
-
     let rows = (0..len)
         .map(|_| CpdRow::load(&mut cpd_file))
         .collect::<std::io::Result<_>>()?;
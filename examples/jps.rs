@@ -2,10 +2,9 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use mkpath::grid::octile_distance;
-use mkpath::jps::JpsExpander;
+use mkpath::jps::{JpsExpander, JpsGrid};
 use mkpath::traits::NodePool;
 use mkpath::{AStarSearcher, HashPool, NodeBuilder, PriorityQueueFactory};
-use mkpath_jps::transpose;
 
 mod movingai;
 
@@ -20,7 +19,7 @@ fn main() {
     let t1 = std::time::Instant::now();
 
     let scen = movingai::read_scenario(&opt.scen).unwrap();
-    let map = movingai::read_bitgrid(&scen.map).unwrap();
+    let grid = JpsGrid::new(movingai::read_bitgrid(&scen.map).unwrap());
 
     let mut builder = NodeBuilder::new();
     let state = builder.add_field((-1, -1));
@@ -28,15 +27,13 @@ fn main() {
     let mut open_list_factory = PriorityQueueFactory::new(&mut builder);
     let mut pool = HashPool::new(builder.build(), state);
 
-    let tmap = transpose(&map);
-
     let t2 = std::time::Instant::now();
 
     for problem in &scen.instances {
         pool.reset();
 
         let open_list = open_list_factory.new_queue(astar.ordering());
-        let expander = JpsExpander::new(&map, &tmap, &pool, state, problem.target);
+        let expander = JpsExpander::from_grid(&grid, &pool, state, problem.target);
 
         let result = astar.search(
             expander,
@@ -0,0 +1,86 @@
+use clap::Parser;
+use mkpath::{AStarSearcher, NodeBuilder, PriorityQueueFactory};
+use mkpath_grid::{octile_distance, BidirectionalDijkstra, BitGrid, GridPool};
+use mkpath_grid_gb::{JpsBbExpander, PartialCellBb};
+use mkpath_jps::JumpDatabase;
+
+/// Searches a random map with JPS+BB and A*, checking each path cost against Dijkstra.
+///
+/// Unlike the `jps_plus_bb` example, this needs no map files or preprocessing step, so it shows
+/// everything needed to use JPS+BB from start to finish.
+#[derive(Parser)]
+struct Options {
+    #[arg(long, default_value_t = 64)]
+    width: i32,
+    #[arg(long, default_value_t = 64)]
+    height: i32,
+    #[arg(long, default_value_t = 0.25)]
+    density: f64,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    #[arg(long, default_value_t = 20)]
+    queries: usize,
+}
+
+fn main() {
+    let opt = Options::parse();
+
+    let map = BitGrid::random_obstacles(opt.width, opt.height, opt.density, opt.seed);
+    let jump_db = JumpDatabase::new(&map);
+
+    let t1 = std::time::Instant::now();
+    let oracle = PartialCellBb::compute(&map, &jump_db, |_, _, _| {});
+    let t2 = std::time::Instant::now();
+
+    let mut builder = NodeBuilder::new();
+    let state = builder.add_field((-1, -1));
+    let mut astar = AStarSearcher::new(&mut builder);
+    let mut open_list_factory = PriorityQueueFactory::new(&mut builder);
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+    let mut dijkstra = BidirectionalDijkstra::new(map.width(), map.height());
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+    if cells.is_empty() {
+        eprintln!("map has no traversable cells");
+        return;
+    }
+
+    for i in 0..opt.queries {
+        let start = cells[i * 7919 % cells.len()];
+        let target = cells[(i * 104729 + cells.len() / 2) % cells.len()];
+
+        pool.reset();
+        let open_list = open_list_factory.new_queue(astar.ordering());
+        let expander = JpsBbExpander::new(&jump_db, &oracle, &pool, state, target);
+
+        let result = astar.search(
+            expander,
+            open_list,
+            |node| octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            pool.generate(start),
+        );
+
+        let expected = dijkstra.distance(&map, start, target);
+        match result {
+            Some(path) => {
+                let cost = path.last().unwrap().get(astar.g());
+                assert!((cost - expected).abs() < 1e-6, "expected {expected}");
+                println!(
+                    "{start:?} -> {target:?}: {cost:.2} in {} jumps",
+                    path.len() - 1
+                );
+            }
+            None => {
+                assert!(expected.is_infinite(), "expected {expected}");
+                println!("{start:?} -> {target:?}: no path");
+            }
+        }
+    }
+
+    let t3 = std::time::Instant::now();
+    eprintln!("Preprocess: {:<10.2?} Search: {:<10.2?}", t2 - t1, t3 - t2);
+}
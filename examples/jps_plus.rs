@@ -35,7 +35,7 @@ fn main() {
         pool.reset();
 
         let open_list = open_list_factory.new_queue(astar.ordering());
-        let expander = JpsPlusExpander::new(&map, &jump_db, &pool, state, problem.target);
+        let expander = JpsPlusExpander::new(&jump_db, &pool, state, problem.target);
 
         let result = astar.search(
             expander,
@@ -6,7 +6,7 @@ use clap::Parser;
 use mkpath::traits::NodePool;
 use mkpath::{AStarSearcher, HashPool, NodeBuilder, PriorityQueueFactory};
 use mkpath_grid::octile_distance;
-use mkpath_grid_gb::{JpsBbExpander, PartialCellBb};
+use mkpath_grid_gb::{BbBuildStrategy, JpsBbExpander, PartialCellBb, PreprocessCache};
 use mkpath_jps::JumpDatabase;
 
 mod movingai;
@@ -16,38 +16,51 @@ struct Options {
     path: PathBuf,
     #[arg(long)]
     generate: bool,
+    /// Build tighter bounding boxes at the cost of longer preprocessing.
+    #[arg(long)]
+    min_area_retry: bool,
 }
 
 fn main() {
     let opt = Options::parse();
 
     if opt.generate {
-        let mut cpd_file = opt.path.clone();
-        cpd_file.as_mut_os_string().push(".bb+");
+        let cpd_file =
+            PreprocessCache::new(&opt.path).artifact_path(PreprocessCache::PARTIAL_CELL_BB);
 
         let map = movingai::read_bitgrid(&opt.path).unwrap();
         let jump_db = JumpDatabase::new(&map);
 
-        let oracle = PartialCellBb::compute(&map, &jump_db, |progress, total, time| {
-            let done = progress == total;
-            let progress = progress as f64 / total as f64;
-            let ttg = if done {
-                time.as_secs_f64() as u64
-            } else {
-                (time.as_secs_f64() / progress - time.as_secs_f64()) as u64
-            };
-            let mut stdout = std::io::stdout().lock();
-            let _ = write!(
-                stdout,
-                "\r{:4.1}% {} {} hr {:2} min {:2} sec",
-                (progress * 1000.0).round() / 10.0,
-                if done { "Done" } else { "ETA" },
-                ttg / 60 / 60,
-                ttg / 60 % 60,
-                ttg % 60,
-            );
-            stdout.flush().unwrap();
-        });
+        let strategy = match opt.min_area_retry {
+            true => BbBuildStrategy::MinAreaRetry,
+            false => BbBuildStrategy::Greedy,
+        };
+
+        let oracle = PartialCellBb::compute_with_strategy(
+            &map,
+            &jump_db,
+            strategy,
+            |progress, total, time| {
+                let done = progress == total;
+                let progress = progress as f64 / total as f64;
+                let ttg = if done {
+                    time.as_secs_f64() as u64
+                } else {
+                    (time.as_secs_f64() / progress - time.as_secs_f64()) as u64
+                };
+                let mut stdout = std::io::stdout().lock();
+                let _ = write!(
+                    stdout,
+                    "\r{:4.1}% {} {} hr {:2} min {:2} sec",
+                    (progress * 1000.0).round() / 10.0,
+                    if done { "Done" } else { "ETA" },
+                    ttg / 60 / 60,
+                    ttg / 60 % 60,
+                    ttg % 60,
+                );
+                stdout.flush().unwrap();
+            },
+        );
         println!();
 
         oracle
@@ -60,8 +73,8 @@ fn main() {
         let map = movingai::read_bitgrid(&scen.map).unwrap();
         let jump_db = JumpDatabase::new(&map);
 
-        let mut cpd_file = scen.map.clone();
-        cpd_file.as_mut_os_string().push(".bb+");
+        let cpd_file =
+            PreprocessCache::new(&scen.map).artifact_path(PreprocessCache::PARTIAL_CELL_BB);
         let oracle =
             PartialCellBb::load(&map, &mut BufReader::new(File::open(cpd_file).unwrap())).unwrap();
 
@@ -77,8 +90,7 @@ fn main() {
             pool.reset();
 
             let open_list = open_list_factory.new_queue(astar.ordering());
-            let expander =
-                JpsBbExpander::new(&map, &jump_db, &oracle, &pool, state, problem.target);
+            let expander = JpsBbExpander::new(&jump_db, &oracle, &pool, state, problem.target);
 
             let result = astar.search(
                 expander,
@@ -1,9 +1,9 @@
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 
 use clap::Parser;
-use mkpath_grid_gb::{PartialCellCpd, ToppingPlus};
+use mkpath_grid_gb::{PartialCellCpd, PreprocessCache, ToppingPlus};
 use mkpath_jps::JumpDatabase;
 
 mod movingai;
@@ -19,8 +19,8 @@ fn main() {
     let opt = Options::parse();
 
     if opt.generate {
-        let mut cpd_file = opt.path.clone();
-        cpd_file.as_mut_os_string().push(".top+");
+        let cpd_file =
+            PreprocessCache::new(&opt.path).artifact_path(PreprocessCache::PARTIAL_CELL_CPD);
 
         let map = movingai::read_bitgrid(&opt.path).unwrap();
         let jump_db = JumpDatabase::new(&map);
@@ -56,11 +56,10 @@ fn main() {
         let map = movingai::read_bitgrid(&scen.map).unwrap();
         let jump_db = JumpDatabase::new(&map);
 
-        let mut cpd_file = scen.map.clone();
-        cpd_file.as_mut_os_string().push(".top+");
-        let oracle =
-            PartialCellCpd::load(&map, &mut BufReader::new(File::open(cpd_file).unwrap())).unwrap();
-        let mut topping_plus = ToppingPlus::new(&map, &jump_db, &oracle);
+        let oracle = PreprocessCache::new(&scen.map)
+            .partial_cell_cpd(&map, &jump_db, |_, _, _| {})
+            .unwrap();
+        let mut topping_plus = ToppingPlus::new(&jump_db, &oracle);
 
         let t2 = std::time::Instant::now();
 
@@ -1,12 +1,12 @@
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 
 use clap::Parser;
 use mkpath::traits::NodePool;
 use mkpath::{AStarSearcher, HashPool, NodeBuilder, PriorityQueueFactory};
 use mkpath_grid::octile_distance;
-use mkpath_grid_gb::{PartialCellCpd, TopsExpander};
+use mkpath_grid_gb::{PartialCellCpd, PreprocessCache, TopsExpander};
 use mkpath_jps::JumpDatabase;
 
 mod movingai;
@@ -22,8 +22,8 @@ fn main() {
     let opt = Options::parse();
 
     if opt.generate {
-        let mut cpd_file = opt.path.clone();
-        cpd_file.as_mut_os_string().push(".top+");
+        let cpd_file =
+            PreprocessCache::new(&opt.path).artifact_path(PreprocessCache::PARTIAL_CELL_CPD);
 
         let map = movingai::read_bitgrid(&opt.path).unwrap();
         let jump_db = JumpDatabase::new(&map);
@@ -59,10 +59,9 @@ fn main() {
         let map = movingai::read_bitgrid(&scen.map).unwrap();
         let jump_db = JumpDatabase::new(&map);
 
-        let mut cpd_file = scen.map.clone();
-        cpd_file.as_mut_os_string().push(".top+");
-        let oracle =
-            PartialCellCpd::load(&map, &mut BufReader::new(File::open(cpd_file).unwrap())).unwrap();
+        let oracle = PreprocessCache::new(&scen.map)
+            .partial_cell_cpd(&map, &jump_db, |_, _, _| {})
+            .unwrap();
 
         let mut builder = NodeBuilder::new();
         let state = builder.add_field((-1, -1));
@@ -76,7 +75,7 @@ fn main() {
             pool.reset();
 
             let open_list = open_list_factory.new_queue(astar.ordering());
-            let expander = TopsExpander::new(&map, &jump_db, &oracle, &pool, state, problem.target);
+            let expander = TopsExpander::new(&jump_db, &oracle, &pool, state, problem.target);
 
             let result = astar.search(
                 expander,
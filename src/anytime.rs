@@ -0,0 +1,252 @@
+use mkpath_core::traits::{Cost, Expander, OpenList, Successor};
+use mkpath_core::{FieldComparator, NodeBuilder, NodeMemberPointer, NodeRef};
+
+/// Anytime Repairing A* (Likhachev, Gordon & Thrun, 2003).
+///
+/// Runs weighted A* with a decreasing sequence of heuristic weights. The first path is found
+/// quickly using a large weight, then each following iteration improves it using a smaller one,
+/// reusing the search effort of the previous iterations instead of starting over.
+///
+/// The default weight schedule is `[5.0, 3.0, 2.0, 1.5, 1.0]`; it can be changed with
+/// [`AnytimeAStar::set_weights`].
+pub struct AnytimeAStar {
+    g: NodeMemberPointer<f64>,
+    h: NodeMemberPointer<f64>,
+    f: NodeMemberPointer<f64>,
+    closed: NodeMemberPointer<u32>,
+    inconsistent: NodeMemberPointer<u32>,
+    weights: Vec<f64>,
+}
+
+impl AnytimeAStar {
+    pub fn new(builder: &mut NodeBuilder) -> Self {
+        AnytimeAStar {
+            g: builder.add_field(f64::INFINITY),
+            h: builder.add_field(f64::NAN),
+            f: builder.add_field(f64::INFINITY),
+            closed: builder.add_field(0),
+            inconsistent: builder.add_field(0),
+            weights: vec![5.0, 3.0, 2.0, 1.5, 1.0],
+        }
+    }
+
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// Sets the heuristic weights used by successive iterations of the search.
+    ///
+    /// The weights must be non-empty, non-increasing, and at least 1. Ending the schedule with a
+    /// weight of 1 allows the search to eventually find an optimal path.
+    #[track_caller]
+    pub fn set_weights(&mut self, weights: impl IntoIterator<Item = f64>) {
+        let weights: Vec<_> = weights.into_iter().collect();
+        assert!(!weights.is_empty(), "weight schedule is empty");
+        assert!(
+            weights.iter().all(|&w| w >= 1.0),
+            "weights must be at least 1"
+        );
+        assert!(
+            weights.windows(2).all(|w| w[1] <= w[0]),
+            "weights must be non-increasing"
+        );
+        self.weights = weights;
+    }
+
+    pub fn g(&self) -> NodeMemberPointer<f64> {
+        self.g
+    }
+
+    pub fn ordering(&self) -> impl FieldComparator {
+        (self.f, self.h)
+    }
+
+    /// Searches for a path from `start` to a node satisfying `goal_test`, improving it for each
+    /// weight in the schedule.
+    ///
+    /// After each iteration, `on_solution` is called with the best path found so far, its cost,
+    /// and a bound on its suboptimality: the cost is at most `bound` times the optimal cost,
+    /// provided the heuristic is admissible. The search stops once `on_solution` returns `false`,
+    /// the weights run out, or the path is known to be optimal, and returns the last path found.
+    ///
+    /// As with [`AStarSearcher::search`](crate::AStarSearcher::search), nodes are assumed to be
+    /// freshly generated.
+    pub fn search<'a, Exp, Open, Edge>(
+        &mut self,
+        mut expander: Exp,
+        mut open_list: Open,
+        mut heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        mut goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        mut on_solution: impl FnMut(&[NodeRef<'a>], f64, f64) -> bool,
+        start: NodeRef<'a>,
+    ) -> Option<Vec<NodeRef<'a>>>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        let AnytimeAStar {
+            g,
+            h,
+            f,
+            closed,
+            inconsistent,
+            ref weights,
+        } = *self;
+
+        let mut weights = weights.iter().copied();
+        let mut weight = weights.next().unwrap();
+
+        let mut edges = vec![];
+        // Nodes whose g value improved after they were expanded in the current iteration. These
+        // are re-opened at the start of the next iteration.
+        let mut incons = vec![];
+        let mut pending = vec![];
+        let mut iteration = 1;
+        let mut goal = goal_test(start).then_some(start);
+
+        start.set(g, 0.0);
+        start.set(h, heuristic(start));
+        start.set(f, weight * start.get(h));
+        open_list.relaxed(start);
+
+        loop {
+            while let Some(node) = open_list.next() {
+                if goal.is_some_and(|goal| goal.get(g) <= node.get(f)) {
+                    open_list.relaxed(node);
+                    break;
+                }
+                node.set(closed, iteration);
+
+                edges.clear();
+                expander.expand(node, &mut edges);
+
+                let node_g = node.get(g);
+
+                for edge in &edges {
+                    let successor = edge.successor();
+                    let new_g = node_g + edge.cost();
+                    if new_g >= successor.get(g) {
+                        continue;
+                    }
+                    if successor.get(h).is_nan() {
+                        successor.set(h, heuristic(successor));
+                    }
+                    successor.set(g, new_g);
+                    successor.set_parent(Some(node));
+
+                    if goal.is_none_or(|goal| new_g < goal.get(g)) && goal_test(successor) {
+                        goal = Some(successor);
+                    }
+
+                    if successor.get(closed) != iteration {
+                        successor.set(f, new_g + weight * successor.get(h));
+                        open_list.relaxed(successor);
+                    } else if successor.get(inconsistent) != iteration {
+                        successor.set(inconsistent, iteration);
+                        incons.push(successor);
+                    }
+                }
+            }
+
+            let goal = goal?;
+
+            while let Some(node) = open_list.next() {
+                pending.push(node);
+            }
+            pending.append(&mut incons);
+
+            let cost = goal.get(g);
+            let lower_bound = pending
+                .iter()
+                .map(|node| node.get(g) + node.get(h))
+                .fold(f64::INFINITY, f64::min);
+            let bound = if lower_bound >= cost {
+                1.0
+            } else {
+                weight.min(cost / lower_bound)
+            };
+
+            let mut path = vec![goal];
+            while let Some(parent) = path[path.len() - 1].get_parent() {
+                path.push(parent);
+            }
+            path.reverse();
+            if !on_solution(&path, cost, bound) || bound <= 1.0 {
+                return Some(path);
+            }
+            let Some(next_weight) = weights.next() else {
+                return Some(path);
+            };
+            weight = next_weight;
+
+            iteration += 1;
+            for node in pending.drain(..) {
+                node.set(f, node.get(g) + weight * node.get(h));
+                open_list.relaxed(node);
+            }
+        }
+    }
+}
+
+#[test]
+fn anytime_paths_improve_to_optimal() {
+    use mkpath_core::PriorityQueueFactory;
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedExpander, GridPool};
+
+    use crate::AStarSearcher;
+
+    // A maze of staggered walls, so that greedy searches find poor paths.
+    let mut map = BitGrid::new(30, 20);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = x % 6 == 3 && if x % 12 == 3 { y > 2 } else { y < 17 };
+            map.set(x, y, !wall);
+        }
+    }
+    let (start, target) = ((0, 10), (29, 10));
+
+    let mut builder = NodeBuilder::new();
+    let state = builder.add_field((-1, -1));
+    let mut astar = AStarSearcher::new(&mut builder);
+    let mut anytime = AnytimeAStar::new(&mut builder);
+    let mut open_list_factory = PriorityQueueFactory::new(&mut builder);
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+    let optimal = astar
+        .search_reset(
+            &mut pool,
+            |pool| EightConnectedExpander::new(&map, pool, state),
+            open_list_factory.new_queue(astar.ordering()),
+            |node| octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            start,
+        )
+        .map(|path| path.last().unwrap().get(astar.g()))
+        .unwrap();
+
+    pool.reset();
+    let mut solutions = vec![];
+    let path = anytime.search(
+        EightConnectedExpander::new(&map, &pool, state),
+        open_list_factory.new_queue(anytime.ordering()),
+        |node| octile_distance(node.get(state), target),
+        |node| node.get(state) == target,
+        |path, cost, bound| {
+            assert_eq!(path.first().unwrap().get(state), start);
+            assert_eq!(path.last().unwrap().get(state), target);
+            assert!(cost <= optimal * bound + 1e-9);
+            solutions.push((cost, bound));
+            true
+        },
+        pool.generate(start),
+    );
+
+    assert_eq!(path.unwrap().last().unwrap().get(state), target);
+    assert!(solutions
+        .windows(2)
+        .all(|w| w[1].0 <= w[0].0 && w[1].1 <= w[0].1));
+    let &(cost, bound) = solutions.last().unwrap();
+    assert_eq!(bound, 1.0);
+    assert!((cost - optimal).abs() < 1e-9);
+}
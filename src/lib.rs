@@ -1,85 +1,1725 @@
-use mkpath_core::traits::{Cost, Expander, OpenList, Successor};
+use std::task::Poll;
+
+use mkpath_core::traits::{Cost, CostValue, Expander, NodePool, OpenList, Successor};
 pub use mkpath_core::*;
 pub use mkpath_cpd as cpd;
 pub use mkpath_grid as grid;
 pub use mkpath_jps as jps;
 pub use mkpath_grid_gb as grid_gb;
 pub use mkpath_ess as ess;
+pub use mkpath_hpa as hpa;
+
+mod anytime;
+mod stats;
+mod steppable;
+
+pub use self::anytime::*;
+pub use self::stats::*;
+pub use self::steppable::*;
 
-pub struct AStarSearcher {
-    g: NodeMemberPointer<f64>,
-    h: NodeMemberPointer<f64>,
-    f: NodeMemberPointer<f64>,
+use self::stats::StatsRecorder;
+
+/// A* search, storing `g`, `h` and `f` in node fields of type `C`.
+///
+/// Costs are stored as `f64` by default. Searchers created by [`AStarSearcher::with_cost_type`]
+/// can store them as `f32` instead; see [`CostValue`] for the tradeoffs.
+pub struct AStarSearcher<C = f64> {
+    g: NodeMemberPointer<C>,
+    h: NodeMemberPointer<C>,
+    f: NodeMemberPointer<C>,
+    queue: Option<PriorityQueueFactory>,
 }
 
 impl AStarSearcher {
     pub fn new(builder: &mut NodeBuilder) -> Self {
-        let g = builder.add_field(f64::INFINITY);
-        let h = builder.add_field(f64::NAN);
-        let f = builder.add_field(f64::INFINITY);
-        AStarSearcher { g, h, f }
+        AStarSearcher::with_cost_type(builder)
+    }
+
+    /// Creates a searcher which also owns its open list, so that queries can be made with
+    /// [`AStarSearcher::search_with_queue`] without passing one in.
+    pub fn with_queue(builder: &mut NodeBuilder) -> Self {
+        let queue = PriorityQueueFactory::new(builder);
+        AStarSearcher {
+            queue: Some(queue),
+            ..AStarSearcher::new(builder)
+        }
+    }
+}
+
+impl<C: CostValue> AStarSearcher<C> {
+    /// Creates a searcher which stores costs as `C`, e.g. `AStarSearcher::<f32>::with_cost_type`
+    /// for smaller nodes.
+    ///
+    /// Edge costs and heuristic values are still computed as `f64`, and are rounded when stored.
+    /// With `f32`, paths are only optimal up to the rounding error accumulated along them, so this
+    /// is best suited to maps where path costs stay well below 2^24.
+    pub fn with_cost_type(builder: &mut NodeBuilder) -> Self {
+        let g = builder.add_field(C::INFINITY);
+        let h = builder.add_field(C::NAN);
+        let f = builder.add_field(C::INFINITY);
+        AStarSearcher {
+            g,
+            h,
+            f,
+            queue: None,
+        }
     }
 
-    pub fn g(&self) -> NodeMemberPointer<f64> {
+    pub fn g(&self) -> NodeMemberPointer<C> {
         self.g
     }
 
+    pub fn h(&self) -> NodeMemberPointer<C> {
+        self.h
+    }
+
+    pub fn f(&self) -> NodeMemberPointer<C> {
+        self.f
+    }
+
+    /// Returns the default open list ordering: by `f`, breaking ties by lowest `h`.
+    ///
+    /// Other orderings can be built from [`AStarSearcher::f`], [`AStarSearcher::g`], and
+    /// [`AStarSearcher::h`], since [`FieldComparator`] is implemented for tuples (compared
+    /// lexicographically) and [`Reverse`](std::cmp::Reverse) (ordering by highest). For example,
+    /// `(astar.f(), Reverse(astar.g()))` breaks ties in favor of deeper nodes. Pass the ordering to
+    /// [`PriorityQueueFactory::new_queue`] or [`AStarSearcher::search_with_ordering`].
+    ///
+    /// Orderings must still order primarily by `f` for the search to return optimal paths.
     pub fn ordering(&self) -> impl FieldComparator {
         (self.f, self.h)
     }
 
+    /// Searches for a path from `start` to a node satisfying `goal_test`.
+    ///
+    /// The path is returned in order from `start` to the goal. [`AStarSearcher::search_into`]
+    /// instead produces it in reverse order, without allocating a new vector.
+    ///
+    /// Nodes are assumed to be freshly generated, so the node pool must be reset between searches;
+    /// otherwise `g` and `h` values from the previous search are reused and the result is wrong.
+    /// [`AStarSearcher::search_reset`] takes care of this.
     pub fn search<'a, Exp, Open, Edge>(
+        &mut self,
+        expander: Exp,
+        open_list: Open,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: NodeRef<'a>,
+    ) -> Option<Vec<NodeRef<'a>>>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        let goal = self.search_impl(expander, open_list, heuristic, goal_test, start, &mut ())?;
+        Some(path_to(goal))
+    }
+
+    /// Like [`AStarSearcher::search`], but appends the path to `out` in order from the goal to
+    /// `start`, i.e. following parent pointers, and returns whether a path was found.
+    ///
+    /// This skips reversing the path, and lets the caller reuse a buffer across queries. Nothing
+    /// is appended if no path is found.
+    pub fn search_into<'a, Exp, Open, Edge>(
+        &mut self,
+        out: &mut Vec<NodeRef<'a>>,
+        expander: Exp,
+        open_list: Open,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: NodeRef<'a>,
+    ) -> bool
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        match self.search_impl(expander, open_list, heuristic, goal_test, start, &mut ()) {
+            Some(goal) => {
+                push_parents(goal, out);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Like [`AStarSearcher::search`], but when no node satisfying `goal_test` can be reached,
+    /// returns the path to the expanded node closest to the goal instead of `None`.
+    ///
+    /// The closest node is the expanded node with the smallest heuristic value, with ties broken
+    /// in favor of the node expanded first. Since the open list is exhausted before giving up, the
+    /// heuristic is only consulted for nodes reachable from `start`, so e.g. an agent ordered to a
+    /// blocked cell moves to the reachable cell nearest it. Whether the goal was reached can be
+    /// checked by testing the last node of the path.
+    ///
+    /// Unreachable goals cause every node reachable from `start` to be expanded, as with
+    /// [`AStarSearcher::search`].
+    pub fn search_closest<'a, Exp, Open, Edge>(
+        &mut self,
+        expander: Exp,
+        open_list: Open,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        mut goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: NodeRef<'a>,
+    ) -> Vec<NodeRef<'a>>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        let h = self.h;
+        let mut closest = start;
+        // Every expanded node is goal tested first, which lets us track the closest one.
+        let track_closest = |node: NodeRef<'a>| {
+            if node.get(h) < closest.get(h) {
+                closest = node;
+            }
+            goal_test(node)
+        };
+        let goal = self.search_impl(
+            expander,
+            open_list,
+            heuristic,
+            track_closest,
+            start,
+            &mut (),
+        );
+        path_to(goal.unwrap_or(closest))
+    }
+
+    /// Like [`AStarSearcher::search`], but gives up after expanding `max_expansions` nodes.
+    ///
+    /// If the budget runs out first, the result is [`SearchOutcome::BudgetExceeded`] with the path
+    /// to the expanded node with the smallest heuristic value, as in
+    /// [`AStarSearcher::search_closest`], so that e.g. an agent can start moving towards the goal
+    /// while the search is continued or retried later. Taking the goal from the open list counts
+    /// towards the budget, so a budget of 0 always gives [`SearchOutcome::BudgetExceeded`] with
+    /// the path consisting of just `start`.
+    ///
+    /// For spreading a search over several calls without losing progress, see
+    /// [`AStarSearcher::search_steppable`].
+    pub fn search_budgeted<'a, Exp, Open, Edge>(
+        &mut self,
+        expander: Exp,
+        open_list: Open,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        mut goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: NodeRef<'a>,
+        max_expansions: usize,
+    ) -> SearchOutcome<'a>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        let h = self.h;
+        let mut closest = start;
+        let track_closest = |node: NodeRef<'a>| {
+            if node.get(h) < closest.get(h) {
+                closest = node;
+            }
+            goal_test(node)
+        };
+        let result = self
+            .search_steppable(expander, open_list, heuristic, track_closest, start)
+            .step_impl(max_expansions, &mut ());
+        match result {
+            Poll::Ready(Some(goal)) => SearchOutcome::Optimal(path_to(goal)),
+            Poll::Ready(None) => SearchOutcome::NoPath,
+            Poll::Pending => SearchOutcome::BudgetExceeded(path_to(closest)),
+        }
+    }
+
+    /// Searches for a path from `start` to whichever of `goals` is closest, i.e. a node whose
+    /// `state` is one of `goals`.
+    ///
+    /// `heuristic(node, goal)` estimates the cost from `node` to a single goal, and the search
+    /// uses the minimum over all goals, which is admissible if the heuristic is. This finds an
+    /// optimal path to the nearest goal in a single search, instead of one search per goal.
+    /// Evaluating the heuristic and the goal test takes time linear in the number of goals.
+    /// Returns `None` if `goals` is empty or none of them can be reached.
+    pub fn search_multigoal<'a, S, Exp, Open, Edge>(
+        &mut self,
+        expander: Exp,
+        open_list: Open,
+        state: NodeMemberPointer<S>,
+        mut heuristic: impl FnMut(NodeRef<'a>, S) -> f64,
+        goals: &[S],
+        start: NodeRef<'a>,
+    ) -> Option<Vec<NodeRef<'a>>>
+    where
+        S: Copy + PartialEq + 'static,
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        if goals.is_empty() {
+            return None;
+        }
+        let nearest = |node: NodeRef<'a>| {
+            goals
+                .iter()
+                .map(|&goal| heuristic(node, goal))
+                .fold(f64::INFINITY, f64::min)
+        };
+        let goal_test = |node: NodeRef<'a>| goals.contains(&node.get(state));
+        self.search(expander, open_list, nearest, goal_test, start)
+    }
+
+    /// Searches for a shortest path from `start` to any cell 8-adjacent to `blocked_goal`, for
+    /// approaching a target which can't be entered itself, such as a wall or an occupied cell.
+    ///
+    /// Nodes are grid cells given by `state`, and `heuristic(node, cell)` estimates the cost from
+    /// `node` to `cell`. Whether `blocked_goal` is traversable doesn't matter; the search never
+    /// ends on it. This is [`AStarSearcher::search_multigoal`] with the neighbors of
+    /// `blocked_goal` as the goals. Returns the approach cell and the path to it, or `None` if no
+    /// neighbor of `blocked_goal` can be reached.
+    pub fn search_adjacent<'a, Exp, Open, Edge>(
+        &mut self,
+        expander: Exp,
+        open_list: Open,
+        state: NodeMemberPointer<(i32, i32)>,
+        heuristic: impl FnMut(NodeRef<'a>, (i32, i32)) -> f64,
+        blocked_goal: (i32, i32),
+        start: NodeRef<'a>,
+    ) -> Option<((i32, i32), Vec<NodeRef<'a>>)>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        let (x, y) = blocked_goal;
+        let neighbors: Vec<_> = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            .filter(|&cell| cell != blocked_goal)
+            .collect();
+        let path =
+            self.search_multigoal(expander, open_list, state, heuristic, &neighbors, start)?;
+        Some((path.last().unwrap().get(state), path))
+    }
+
+    /// Like [`AStarSearcher::search`], but uses a priority queue from `open_list_factory` which
+    /// orders nodes by `cmp` rather than taking an open list.
+    ///
+    /// See [`AStarSearcher::ordering`] for how to build orderings.
+    pub fn search_with_ordering<'a, Exp, Edge>(
+        &mut self,
+        open_list_factory: &mut PriorityQueueFactory,
+        cmp: impl FieldComparator,
+        expander: Exp,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: NodeRef<'a>,
+    ) -> Option<Vec<NodeRef<'a>>>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+    {
+        let open_list = open_list_factory.new_queue(cmp);
+        self.search(expander, open_list, heuristic, goal_test, start)
+    }
+
+    /// Like [`AStarSearcher::search`], but also records statistics about the search in `stats`.
+    ///
+    /// Counts are added to those already in `stats`, so the totals over several searches can be
+    /// collected in one [`SearchStats`]. [`AStarSearcher::search`] does not do this bookkeeping.
+    pub fn search_with_stats<'a, Exp, Open, Edge>(
+        &mut self,
+        expander: Exp,
+        open_list: Open,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: NodeRef<'a>,
+        stats: &mut SearchStats,
+    ) -> Option<Vec<NodeRef<'a>>>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        stats.open = 0;
+        let goal = self.search_impl(expander, open_list, heuristic, goal_test, start, stats)?;
+        Some(path_to(goal))
+    }
+
+    /// Starts a search from `start` to a node satisfying `goal_test` which is run incrementally
+    /// using [`SteppableAStar::step_n`], rather than to completion.
+    ///
+    /// As with [`AStarSearcher::search`], nodes are assumed to be freshly generated.
+    pub fn search_steppable<'a, Exp, Open, Edge, H, G>(
+        &self,
+        expander: Exp,
+        open_list: Open,
+        heuristic: H,
+        goal_test: G,
+        start: NodeRef<'a>,
+    ) -> SteppableAStar<'a, Exp, Open, H, G, C>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+        H: FnMut(NodeRef<'a>) -> f64,
+        G: FnMut(NodeRef<'a>) -> bool,
+    {
+        SteppableAStar::new(self, expander, open_list, heuristic, goal_test, start)
+    }
+
+    fn search_impl<'a, Exp, Open, Edge>(
+        &mut self,
+        expander: Exp,
+        open_list: Open,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: NodeRef<'a>,
+        stats: &mut impl StatsRecorder,
+    ) -> Option<NodeRef<'a>>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        let mut search = self.search_steppable(expander, open_list, heuristic, goal_test, start);
+        stats.opened();
+        loop {
+            if let Poll::Ready(goal) = search.step_impl(usize::MAX, stats) {
+                return goal;
+            }
+        }
+    }
+
+    /// Searches for the canonical optimal path from `start` to a node satisfying `goal_test`.
+    ///
+    /// Among optimal paths, this returns the one where, walking backwards from the goal, every
+    /// node's parent is the optimal predecessor with the smallest `parent_key`. Unlike
+    /// [`AStarSearcher::search`], the result therefore does not depend on the order in which the
+    /// expander produces edges or the open list breaks ties, which is useful when paths must be
+    /// reproduced exactly. For grid maps, the state of a node is a suitable key.
+    ///
+    /// To consider every optimal predecessor, the search continues until all nodes with `f` up to
+    /// the cost of the path are expanded, so it is somewhat slower than a regular search. Costs
+    /// within `1e-9` of each other are considered equal, and edge costs must be larger than that.
+    pub fn search_canonical<'a, Exp, Open, Edge, K>(
         &mut self,
         mut expander: Exp,
         mut open_list: Open,
         mut heuristic: impl FnMut(NodeRef<'a>) -> f64,
         mut goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        mut parent_key: impl FnMut(NodeRef<'a>) -> K,
         start: NodeRef<'a>,
     ) -> Option<Vec<NodeRef<'a>>>
     where
         Exp: Expander<'a, Edge = Edge>,
         Edge: Successor<'a> + Cost,
         Open: OpenList<'a>,
+        K: Ord,
     {
-        let AStarSearcher { g, h, f } = *self;
+        const TIE_EPSILON: f64 = 1e-9;
+
+        let AStarSearcher { g, h, f, .. } = *self;
 
-        let mut edges = vec![];
+        let mut edges = Vec::with_capacity(expander.max_degree());
+        let mut goal: Option<NodeRef<'a>> = None;
 
-        start.set(g, 0.0);
-        start.set(h, heuristic(start));
+        start.set(g, C::ZERO);
+        start.set(h, C::from_f64(heuristic(start)));
         start.set(f, start.get(h));
         open_list.relaxed(start);
 
         while let Some(node) = open_list.next() {
-            if goal_test(node) {
-                let mut path = vec![node];
-                while let Some(parent) = path[path.len() - 1].get_parent() {
-                    path.push(parent);
+            match goal {
+                Some(goal) if node.get(f).to_f64() > goal.get(g).to_f64() + TIE_EPSILON => break,
+                None if goal_test(node) => {
+                    goal = Some(node);
+                    continue;
                 }
-                path.reverse();
-                return Some(path);
+                _ => {}
             }
 
             edges.clear();
             expander.expand(node, &mut edges);
 
-            let node_g = node.get(g);
+            let node_g = node.get(g).to_f64();
 
             for edge in &edges {
                 let successor = edge.successor();
                 let new_g = node_g + edge.cost();
-                if new_g < successor.get(g) {
-                    if successor.get(h).is_nan() {
-                        successor.set(h, heuristic(successor));
+                let old_g = successor.get(g).to_f64();
+                if new_g < old_g - TIE_EPSILON {
+                    if successor.get(h).to_f64().is_nan() {
+                        successor.set(h, C::from_f64(heuristic(successor)));
                     }
-                    successor.set(g, new_g);
-                    successor.set(f, new_g + successor.get(h));
+                    successor.set(g, C::from_f64(new_g));
+                    successor.set(f, C::from_f64(new_g + successor.get(h).to_f64()));
                     successor.set_parent(Some(node));
                     open_list.relaxed(successor);
+                } else if new_g <= old_g + TIE_EPSILON {
+                    // Equally good predecessor. The g value does not change, so there is no need
+                    // to requeue the successor even if it was already expanded.
+                    let better = successor
+                        .get_parent()
+                        .is_some_and(|parent| parent_key(node) < parent_key(parent));
+                    if better {
+                        successor.set_parent(Some(node));
+                    }
                 }
             }
         }
 
-        None
+        Some(path_to(goal?))
+    }
+
+    /// Resets `pool`, then searches for a path from the `start` state to a node satisfying
+    /// `goal_test`.
+    ///
+    /// The expander is constructed by `expander` after the pool is reset, since expanders
+    /// typically borrow the pool. Resetting the pool gives every generated node fresh field
+    /// values, so the same pool and searcher can safely be reused for any number of queries.
+    pub fn search_reset<'a, P, Exp, Open, Edge>(
+        &mut self,
+        pool: &'a mut P,
+        expander: impl FnOnce(&'a P) -> Exp,
+        open_list: Open,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: P::State,
+    ) -> Option<Vec<NodeRef<'a>>>
+    where
+        P: NodePool,
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+        Open: OpenList<'a>,
+    {
+        pool.reset();
+        let pool = &*pool;
+        let start = pool.generate(start);
+        self.search(expander(pool), open_list, heuristic, goal_test, start)
+    }
+
+    /// Like [`AStarSearcher::search_reset`], but uses the searcher's own open list, ordered by
+    /// [`AStarSearcher::ordering`], instead of taking one.
+    ///
+    /// Both the pool and the open list start out empty for each query, so this is all the wiring
+    /// needed to reuse one searcher and pool for any number of queries.
+    ///
+    /// # Panics
+    /// Panics if the searcher was not created with [`AStarSearcher::with_queue`].
+    #[track_caller]
+    pub fn search_with_queue<'a, P, Exp, Edge>(
+        &mut self,
+        pool: &'a mut P,
+        expander: impl FnOnce(&'a P) -> Exp,
+        heuristic: impl FnMut(NodeRef<'a>) -> f64,
+        goal_test: impl FnMut(NodeRef<'a>) -> bool,
+        start: P::State,
+    ) -> Option<Vec<NodeRef<'a>>>
+    where
+        P: NodePool,
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+    {
+        let ordering = self.ordering();
+        let open_list = self
+            .queue
+            .as_mut()
+            .expect("searcher must be created with AStarSearcher::with_queue")
+            .new_queue(ordering);
+        self.search_reset(pool, expander, open_list, heuristic, goal_test, start)
+    }
+}
+
+/// The result of [`AStarSearcher::search_budgeted`].
+///
+/// Paths are in order from the start node.
+#[derive(Clone)]
+pub enum SearchOutcome<'a> {
+    /// The goal was reached within the budget, by an optimal path.
+    Optimal(Vec<NodeRef<'a>>),
+    /// The budget ran out first. The path leads to the expanded node closest to the goal according
+    /// to the heuristic.
+    BudgetExceeded(Vec<NodeRef<'a>>),
+    /// The search space was exhausted within the budget without reaching a goal.
+    NoPath,
+}
+
+impl<'a> SearchOutcome<'a> {
+    /// Returns the path, or `None` for [`SearchOutcome::NoPath`].
+    pub fn path(&self) -> Option<&[NodeRef<'a>]> {
+        match self {
+            SearchOutcome::Optimal(path) | SearchOutcome::BudgetExceeded(path) => Some(path),
+            SearchOutcome::NoPath => None,
+        }
+    }
+}
+
+/// Returns the path from the root of the search tree to `node`.
+fn path_to(node: NodeRef) -> Vec<NodeRef> {
+    let mut path = vec![];
+    push_parents(node, &mut path);
+    path.reverse();
+    path
+}
+
+/// Appends `node` and its ancestors in the search tree to `out`.
+fn push_parents<'a>(node: NodeRef<'a>, out: &mut Vec<NodeRef<'a>>) {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        out.push(node);
+        current = node.get_parent();
+    }
+}
+
+#[cfg(test)]
+struct TestSearcher {
+    state: NodeMemberPointer<(i32, i32)>,
+    astar: AStarSearcher,
+    open_list_factory: PriorityQueueFactory,
+}
+
+#[cfg(test)]
+impl TestSearcher {
+    /// Creates a searcher and a pool of grid cells for a `width` by `height` map.
+    fn new(width: i32, height: i32) -> (Self, mkpath_grid::GridPool) {
+        TestSearcher::with_builder(NodeBuilder::new(), width, height)
+    }
+
+    /// Like [`TestSearcher::new`], but adds the fields to `builder`, which may already have
+    /// fields for other searchers sharing the pool.
+    fn with_builder(
+        mut builder: NodeBuilder,
+        width: i32,
+        height: i32,
+    ) -> (Self, mkpath_grid::GridPool) {
+        let state = builder.add_field((-1, -1));
+        let searcher = TestSearcher {
+            state,
+            astar: AStarSearcher::new(&mut builder),
+            open_list_factory: PriorityQueueFactory::new(&mut builder),
+        };
+        let pool = mkpath_grid::GridPool::new(builder.build(), state, width, height);
+        (searcher, pool)
+    }
+
+    /// Returns the cells of the path from `start` to `target` and its cost, or `None` if the
+    /// target is unreachable. The search uses the octile distance heuristic.
+    fn search<'a, Exp, Edge>(
+        &mut self,
+        pool: &'a mut mkpath_grid::GridPool,
+        expander: impl FnOnce(&'a mkpath_grid::GridPool) -> Exp,
+        start: (i32, i32),
+        target: (i32, i32),
+    ) -> Option<(Vec<(i32, i32)>, f64)>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+    {
+        self.search_with_ordering(self.astar.ordering(), pool, expander, start, target)
+    }
+
+    /// Like [`TestSearcher::search`], but orders the open list by `cmp`.
+    fn search_with_ordering<'a, Exp, Edge>(
+        &mut self,
+        cmp: impl FieldComparator,
+        pool: &'a mut mkpath_grid::GridPool,
+        expander: impl FnOnce(&'a mkpath_grid::GridPool) -> Exp,
+        start: (i32, i32),
+        target: (i32, i32),
+    ) -> Option<(Vec<(i32, i32)>, f64)>
+    where
+        Exp: Expander<'a, Edge = Edge>,
+        Edge: Successor<'a> + Cost,
+    {
+        let state = self.state;
+        pool.reset();
+        let pool = &*pool;
+        let path = self.astar.search_with_ordering(
+            &mut self.open_list_factory,
+            cmp,
+            expander(pool),
+            |node| mkpath_grid::octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            pool.generate(start),
+        )?;
+        let cost = path.last().unwrap().get(self.astar.g());
+        Some((path.iter().map(|node| node.get(state)).collect(), cost))
+    }
+}
+
+#[test]
+fn pool_reuse_across_searches() {
+    use mkpath_grid::{BitGrid, EightConnectedExpander};
+
+    let mut map = BitGrid::new(8, 8);
+    for y in 0..8 {
+        for x in 0..8 {
+            map.set(x, y, x != 4 || y == 7);
+        }
+    }
+
+    let (mut searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let state = searcher.state;
+
+    let mut query = |start, target| {
+        let expander = |pool| EightConnectedExpander::new(&map, pool, state);
+        let (_, cost) = searcher.search(&mut pool, expander, start, target)?;
+        Some(cost)
+    };
+
+    let first = query((0, 0), (7, 0)).unwrap();
+    assert_eq!(query((7, 0), (0, 0)), Some(first));
+    assert_eq!(query((0, 0), (7, 0)), Some(first));
+    assert_eq!(query((3, 3), (3, 3)), Some(0.0));
+}
+
+#[test]
+fn f32_costs_match_f64() {
+    use mkpath_cpd::FirstMoveSearcher;
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedExpander};
+
+    let mut map = BitGrid::new(40, 30);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let wall = (x % 9 == 4 && y % 7 != 3) || (x * 5 + y * 3) % 13 == 1;
+            map.set(x, y, !wall);
+        }
     }
+
+    let mut builder = NodeBuilder::new();
+    let mut astar_f32 = AStarSearcher::<f32>::with_cost_type(&mut builder);
+    let mut first_moves = FirstMoveSearcher::new(&mut builder);
+    let mut first_moves_f32 = FirstMoveSearcher::<f32>::with_cost_type(&mut builder);
+    let (searcher, mut pool) = TestSearcher::with_builder(builder, map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    for (start, target) in [((0, 0), (39, 29)), ((1, 28), (38, 3)), ((20, 15), (3, 1))] {
+        let mut costs = vec![];
+        for f32_costs in [false, true] {
+            pool.reset();
+            let expander = EightConnectedExpander::new(&map, &pool, state);
+            let heuristic = |node: NodeRef| octile_distance(node.get(state), target);
+            let goal_test = |node: NodeRef| node.get(state) == target;
+            let start = pool.generate(start);
+            let cost = match f32_costs {
+                false => {
+                    let open_list = open_list_factory.new_queue(astar.ordering());
+                    let path = astar.search(expander, open_list, heuristic, goal_test, start);
+                    path.unwrap().last().unwrap().get(astar.g())
+                }
+                true => {
+                    let open_list = open_list_factory.new_queue(astar_f32.ordering());
+                    let path = astar_f32.search(expander, open_list, heuristic, goal_test, start);
+                    path.unwrap().last().unwrap().get(astar_f32.g()) as f64
+                }
+            };
+            costs.push(cost);
+        }
+        assert!((costs[0] - costs[1]).abs() < 1e-4);
+
+        // Tied paths can accumulate different rounding errors in f32, so some ties are missed.
+        // Here, rounding never makes a suboptimal path look optimal, so the first moves found
+        // with f32 costs are always a subset of those found with f64 costs.
+        let mut found = vec![];
+        for f32_costs in [false, true] {
+            pool.reset();
+            let expander = EightConnectedExpander::new(&map, &pool, state);
+            let start = pool.generate(start);
+            let mut moves = vec![];
+            let record = |node: NodeRef, fm| moves.push((node.get(state), fm));
+            match f32_costs {
+                false => {
+                    let open_list = open_list_factory.new_queue(first_moves.g());
+                    first_moves.search(start, expander, open_list, record);
+                }
+                true => {
+                    let open_list = open_list_factory.new_queue(first_moves_f32.g());
+                    first_moves_f32.search(start, expander, open_list, record);
+                }
+            }
+            moves.sort_unstable();
+            found.push(moves);
+        }
+        assert_eq!(found[0].len(), found[1].len());
+        for (&(cell, moves), &(cell_f32, moves_f32)) in found[0].iter().zip(&found[1]) {
+            assert_eq!(cell, cell_f32);
+            assert!(moves_f32 != 0 && moves_f32 & !moves == 0);
+        }
+    }
+}
+
+#[test]
+fn scaled_integer_costs_tie_exactly() {
+    use mkpath_cpd::FirstMoveSearcher;
+    use mkpath_grid::{octile_distance_scaled, BitGrid, Direction, EightConnectedExpander, Metric};
+
+    let mut map = BitGrid::new(9, 7);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, true);
+        }
+    }
+
+    let mut builder = NodeBuilder::new();
+    let mut astar = AStarSearcher::<i64>::with_cost_type(&mut builder);
+    let mut first_moves = FirstMoveSearcher::<i64>::with_cost_type(&mut builder);
+    let (searcher, mut pool) = TestSearcher::with_builder(builder, map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut open_list_factory,
+        ..
+    } = searcher;
+
+    let (start, metric) = ((2, 3), Metric::SCALED_OCTILE);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let target = (x, y);
+            let path = astar.search_reset(
+                &mut pool,
+                |pool| EightConnectedExpander::with_metric(&map, pool, state, metric),
+                open_list_factory.new_queue(astar.ordering()),
+                |node| octile_distance_scaled(node.get(state), target) as f64,
+                |node| node.get(state) == target,
+                start,
+            );
+            let cost = path.unwrap().last().unwrap().get(astar.g());
+            assert_eq!(cost, octile_distance_scaled(start, target));
+        }
+    }
+
+    pool.reset();
+    let expander = EightConnectedExpander::with_metric(&map, &pool, state, metric);
+    let mut found = vec![];
+    first_moves.search(
+        pool.generate(start),
+        expander,
+        open_list_factory.new_queue(first_moves.g()),
+        |node, moves| found.push((node.get(state), moves)),
+    );
+    assert_eq!(found.len(), 9 * 7 - 1);
+    for (target, moves) in found {
+        // On an empty map, a move is optimal exactly when it keeps the remaining distance
+        // consistent, so every tied first move must be found.
+        let distance = octile_distance_scaled(start, target);
+        let mut expected = 0;
+        for dir in Direction::ALL {
+            let (dx, dy) = dir.vector();
+            let next = (start.0 + dx, start.1 + dy);
+            let step = octile_distance_scaled(start, next);
+            if step + octile_distance_scaled(next, target) == distance {
+                expected |= 1 << dir as usize;
+            }
+        }
+        assert_eq!(moves, expected, "{target:?}");
+    }
+}
+
+#[test]
+fn search_into_appends_reversed_path() {
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedExpander};
+
+    let map = BitGrid::from_str_rows(&["......", ".@@@@.", "......"], &['.']);
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    let target = (5, 2);
+    let expected: Vec<_> = astar
+        .search_reset(
+            &mut pool,
+            |pool| EightConnectedExpander::new(&map, pool, state),
+            open_list_factory.new_queue(astar.ordering()),
+            |node| octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            (0, 0),
+        )
+        .unwrap()
+        .iter()
+        .map(|node| node.get(state))
+        .collect();
+
+    pool.reset();
+    let mut out = vec![pool.generate((3, 0))];
+    assert!(astar.search_into(
+        &mut out,
+        EightConnectedExpander::new(&map, &pool, state),
+        open_list_factory.new_queue(astar.ordering()),
+        |node| octile_distance(node.get(state), target),
+        |node| node.get(state) == target,
+        pool.generate((0, 0)),
+    ));
+    let mut found: Vec<_> = out[1..].iter().map(|node| node.get(state)).collect();
+    found.reverse();
+    assert_eq!(found, expected);
+
+    // Nothing is appended when there is no path.
+    pool.reset();
+    let mut out = vec![];
+    assert!(!astar.search_into(
+        &mut out,
+        EightConnectedExpander::new(&map, &pool, state),
+        open_list_factory.new_queue(astar.ordering()),
+        |_| 0.0,
+        |node| node.get(state) == (2, 1),
+        pool.generate((0, 0)),
+    ));
+    assert!(out.is_empty());
+}
+
+#[test]
+fn closest_reachable_cell_when_goal_is_enclosed() {
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedExpander};
+
+    let map = BitGrid::from_str_rows(
+        &["........", "....@@@@", "....@...", "....@...", "....@@@@"],
+        &['.'],
+    );
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    let mut query = |start, target| {
+        pool.reset();
+        let path = astar.search_closest(
+            EightConnectedExpander::new(&map, &pool, state),
+            open_list_factory.new_queue(astar.ordering()),
+            |node| octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            pool.generate(start),
+        );
+        assert_eq!(path[0].get(state), start);
+        path.last().unwrap().get(state)
+    };
+
+    // The room on the right is enclosed, so the nearest reachable cell is outside its walls.
+    assert_eq!(query((0, 4), (6, 3)), (3, 3));
+    assert_eq!(query((0, 0), (6, 2)), (6, 0));
+    // Reachable goals are found as usual.
+    assert_eq!(query((0, 4), (7, 0)), (7, 0));
+    // The same applies when starting inside the enclosed room.
+    assert_eq!(query((6, 3), (0, 0)), (5, 2));
+}
+
+#[test]
+fn multigoal_reaches_nearest_goal() {
+    use mkpath_grid::{exact_distance, octile_distance, BitGrid, EightConnectedExpander};
+
+    let map = BitGrid::random_obstacles(24, 16, 0.3, 11);
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    for (i, &start) in cells.iter().enumerate().step_by(29) {
+        let goals: Vec<_> = cells.iter().skip(i % 7).step_by(61).copied().collect();
+        let nearest = goals
+            .iter()
+            .map(|&goal| exact_distance(&map, start, goal))
+            .fold(f64::INFINITY, f64::min);
+
+        pool.reset();
+        let path = astar.search_multigoal(
+            EightConnectedExpander::new(&map, &pool, state),
+            open_list_factory.new_queue(astar.ordering()),
+            state,
+            |node, goal| octile_distance(node.get(state), goal),
+            &goals,
+            pool.generate(start),
+        );
+        match path {
+            Some(path) => {
+                let goal = path.last().unwrap();
+                assert!(goals.contains(&goal.get(state)));
+                assert!((goal.get(astar.g()) - nearest).abs() < 1e-9);
+            }
+            None => assert!(nearest.is_infinite()),
+        }
+    }
+
+    pool.reset();
+    let path = astar.search_multigoal(
+        EightConnectedExpander::new(&map, &pool, state),
+        open_list_factory.new_queue(astar.ordering()),
+        state,
+        |node, goal| octile_distance(node.get(state), goal),
+        &[],
+        pool.generate(cells[0]),
+    );
+    assert!(path.is_none());
+}
+
+#[test]
+fn adjacent_search_stops_next_to_blocked_goal() {
+    use mkpath_grid::{exact_distance, octile_distance, BitGrid, EightConnectedExpander};
+
+    let map = BitGrid::from_str_rows(
+        &[
+            "......", //
+            ".####.", //
+            ".#..#.", //
+            ".####.", //
+            "......", //
+        ],
+        &['.'],
+    );
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    let mut search = |start, blocked_goal| {
+        pool.reset();
+        astar
+            .search_adjacent(
+                EightConnectedExpander::new(&map, &pool, state),
+                open_list_factory.new_queue(astar.ordering()),
+                state,
+                |node, cell| octile_distance(node.get(state), cell),
+                blocked_goal,
+                pool.generate(start),
+            )
+            .map(|(cell, path)| (cell, path.last().unwrap().get(astar.g())))
+    };
+
+    // The wall cell (4, 2) is best approached from (5, 1), since diagonals can't cut corners.
+    let (cell, cost) = search((0, 0), (4, 2)).unwrap();
+    assert_eq!(cell, (5, 1));
+    assert!((cost - exact_distance(&map, (0, 0), (5, 1))).abs() < 1e-9);
+
+    // Already standing next to the goal.
+    assert_eq!(search((5, 0), (4, 1)), Some(((5, 0), 0.0)));
+
+    // Of the traversable neighbors of (2, 1), (1, 0) is the closest which is not enclosed.
+    assert_eq!(search((0, 4), (2, 1)), Some(((1, 0), 5.0)));
+
+    // No neighbor of (0, 0) can be reached from inside the enclosure.
+    assert!(search((2, 2), (0, 0)).is_none());
+}
+
+#[test]
+fn budgeted_search_returns_best_effort_path() {
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedExpander};
+
+    let map = BitGrid::from_str_rows(
+        &["..........", "..........", "@@@@@@@@.@", ".........."],
+        &['.'],
+    );
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    let mut query = |start, target, max_expansions| {
+        pool.reset();
+        let outcome = astar.search_budgeted(
+            EightConnectedExpander::new(&map, &pool, state),
+            open_list_factory.new_queue(astar.ordering()),
+            |node| octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            pool.generate(start),
+            max_expansions,
+        );
+        let cells = |path: &[NodeRef]| -> Vec<_> { path.iter().map(|n| n.get(state)).collect() };
+        match outcome {
+            SearchOutcome::Optimal(path) => Ok(cells(&path)),
+            SearchOutcome::BudgetExceeded(path) => {
+                assert_eq!(path[0].get(state), start);
+                Err(Some(*cells(&path).last().unwrap()))
+            }
+            SearchOutcome::NoPath => Err(None),
+        }
+    };
+
+    // A generous budget finds the optimal path.
+    let path = query((0, 0), (0, 3), 1000).unwrap();
+    assert_eq!(path.first(), Some(&(0, 0)));
+    assert_eq!(path.last(), Some(&(0, 3)));
+    assert!(path.contains(&(8, 2)));
+
+    // With a small budget, the search stops short, at the expanded cell with the smallest
+    // heuristic value, which is against the wall right above the target.
+    assert_eq!(query((0, 0), (0, 3), 3), Err(Some((0, 1))));
+    assert_eq!(query((0, 0), (0, 3), 0), Err(Some((0, 0))));
+
+    // Exhausting the search space within the budget means there is no path.
+    assert_eq!(query((0, 0), (5, 2), 1000), Err(None));
+    // Stopping early instead gives a partial path which makes progress towards the wall.
+    let partial = query((0, 0), (5, 2), 5);
+    assert!(matches!(partial, Err(Some((x, 1))) if x > 0), "{partial:?}");
+}
+
+#[test]
+fn owned_queue_across_searches() {
+    use mkpath_grid::{exact_distance, octile_distance, BitGrid, EightConnectedExpander, GridPool};
+
+    let map = BitGrid::from_str_rows(&["....@...", "....@...", "........", "..@@@@.."], &['.']);
+
+    let mut builder = NodeBuilder::new();
+    let state = builder.add_field((-1, -1));
+    let mut astar = AStarSearcher::with_queue(&mut builder);
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+    let queries = [
+        ((0, 0), (7, 0)),
+        ((7, 3), (0, 3)),
+        ((0, 0), (7, 0)),
+        ((5, 2), (5, 2)),
+    ];
+    for (start, target) in queries {
+        let path = astar
+            .search_with_queue(
+                &mut pool,
+                |pool| EightConnectedExpander::new(&map, pool, state),
+                |node| octile_distance(node.get(state), target),
+                |node| node.get(state) == target,
+                start,
+            )
+            .unwrap();
+        let cost = path.last().unwrap().get(astar.g());
+        assert!((cost - exact_distance(&map, start, target)).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn chebyshev_matches_max_delta() {
+    use mkpath_grid::{chebyshev_distance, BitGrid, EightConnectedExpander};
+
+    let mut map = BitGrid::new(12, 9);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, true);
+        }
+    }
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    for (start, target) in [((0, 0), (11, 8)), ((3, 7), (10, 1)), ((5, 5), (5, 0))] {
+        let path = astar
+            .search_reset(
+                &mut pool,
+                |pool| EightConnectedExpander::with_costs(&map, pool, state, 1.0, 1.0),
+                open_list_factory.new_queue(astar.ordering()),
+                |node| chebyshev_distance(node.get(state), target),
+                |node| node.get(state) == target,
+                start,
+            )
+            .unwrap();
+        let dx = (start.0 - target.0).abs();
+        let dy = (start.1 - target.1).abs();
+        assert_eq!(path.last().unwrap().get(astar.g()), dx.max(dy) as f64);
+    }
+}
+
+#[test]
+fn metric_distance_is_exact_on_empty_maps() {
+    use mkpath_grid::{metric_distance, BitGrid, EightConnectedExpander, Metric};
+
+    let mut map = BitGrid::new(12, 9);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, true);
+        }
+    }
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    let metrics = [
+        Metric::OCTILE,
+        Metric {
+            ortho_x: 1.0,
+            ortho_y: 0.5,
+            diagonal: 1.25,
+        },
+        Metric {
+            ortho_x: 1.0,
+            ortho_y: 2.0,
+            diagonal: 3.5,
+        },
+        // Diagonals cheaper than some orthogonal moves, so paths zig-zag.
+        Metric {
+            ortho_x: 1.0,
+            ortho_y: 5.0,
+            diagonal: 2.0,
+        },
+        Metric {
+            ortho_x: 3.0,
+            ortho_y: 3.0,
+            diagonal: 1.0,
+        },
+    ];
+    let pairs = [
+        ((0, 0), (11, 8)),
+        ((3, 7), (10, 1)),
+        ((5, 5), (5, 0)),
+        ((0, 0), (0, 2)),
+        ((2, 4), (9, 4)),
+        ((1, 1), (2, 7)),
+    ];
+    for metric in metrics {
+        for (start, target) in pairs {
+            // Dijkstra's algorithm, so that the result doesn't rely on the heuristic.
+            let path = astar
+                .search_reset(
+                    &mut pool,
+                    |pool| EightConnectedExpander::with_metric(&map, pool, state, metric),
+                    open_list_factory.new_queue(astar.ordering()),
+                    |_| 0.0,
+                    |node| node.get(state) == target,
+                    start,
+                )
+                .unwrap();
+            let cost = path.last().unwrap().get(astar.g());
+            let h = metric_distance(metric, start, target);
+            assert!(
+                (cost - h).abs() < 1e-9,
+                "{metric:?} {start:?} {target:?}: {cost} vs {h}"
+            );
+        }
+    }
+}
+
+#[test]
+fn one_way_corridor_is_asymmetric() {
+    use mkpath_grid::{BitGrid, Direction, EightConnectedExpander, Grid, Metric, SAFE_SQRT_2};
+
+    // The middle row can only be travelled eastwards; the top row is unrestricted.
+    let mut map = BitGrid::new(10, 3);
+    for x in 0..map.width() {
+        map.set(x, 0, true);
+        map.set(x, 1, true);
+    }
+    let restrictions = Grid::new(map.width(), map.height(), |_, y| {
+        if y == 1 {
+            Direction::West | Direction::NorthWest | Direction::SouthWest
+        } else {
+            Default::default()
+        }
+    });
+
+    let (mut searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let state = searcher.state;
+
+    let mut cost = |metric, start, target| {
+        let expander = |pool| {
+            EightConnectedExpander::with_metric(&map, pool, state, metric)
+                .with_restrictions(&restrictions)
+        };
+        searcher
+            .search(&mut pool, expander, start, target)
+            .unwrap()
+            .1
+    };
+
+    assert_eq!(cost(Metric::OCTILE, (0, 1), (9, 1)), 9.0);
+    // Backwards, the search has to leave the corridor and travel along the top row.
+    assert_eq!(cost(Metric::OCTILE, (9, 1), (0, 1)), 9.0 + SAFE_SQRT_2);
+
+    // Restrictions apply with other metrics too.
+    let metric = Metric {
+        ortho_x: 2.0,
+        ortho_y: 2.0,
+        diagonal: 3.0,
+    };
+    assert_eq!(cost(metric, (0, 1), (9, 1)), 18.0);
+    assert_eq!(cost(metric, (9, 1), (0, 1)), 21.0);
+}
+
+#[test]
+fn movement_masks_restrict_directions() {
+    use std::collections::VecDeque;
+
+    use mkpath_grid::{BitGrid, Direction, EightConnectedExpander, Grid, SAFE_SQRT_2};
+
+    let map = BitGrid::random_obstacles(32, 24, 0.25, 3);
+
+    let (mut searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let state = searcher.state;
+
+    let mut cost = |mask, start, target| {
+        let expander =
+            |pool| EightConnectedExpander::new(&map, pool, state).with_movement_mask(mask);
+        let path = searcher.search(&mut pool, expander, start, target);
+        path.map_or(f64::INFINITY, |(_, cost)| cost)
+    };
+
+    let cells: Vec<_> = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| map.get(x, y))
+        .collect();
+    for &start in cells.iter().step_by(53) {
+        // Breadth-first search gives the 4-connected distances.
+        let mut distance = Grid::new(map.width(), map.height(), |_, _| f64::INFINITY);
+        distance[start] = 0.0;
+        let mut queue = VecDeque::from([start]);
+        while let Some((x, y)) = queue.pop_front() {
+            for dir in Direction::ORTHOGONAL {
+                let (dx, dy) = dir.vector();
+                let next = (x + dx, y + dy);
+                if map.get(next.0, next.1) && distance[next].is_infinite() {
+                    distance[next] = distance[(x, y)] + 1.0;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        for &target in cells.iter().step_by(31) {
+            assert_eq!(cost(Direction::ORTHO, start, target), distance[target]);
+        }
+    }
+
+    // Diagonal moves keep the parity of x + y, so only half the cells can be reached.
+    let start = cells[0];
+    let mut reached = 0;
+    for &target in &cells {
+        let cost = cost(Direction::DIAG, start, target);
+        if (start.0 + start.1 + target.0 + target.1) % 2 != 0 {
+            assert!(cost.is_infinite());
+        } else if cost.is_finite() {
+            let moves = (cost / SAFE_SQRT_2).round();
+            assert!((moves * SAFE_SQRT_2 - cost).abs() < 1e-9);
+            reached += 1;
+        }
+    }
+    assert!(reached > 1);
+}
+
+#[test]
+fn canonical_search_ignores_edge_order() {
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedExpander, GridEdge};
+
+    /// Optionally reverses the order of the edges produced by an expander.
+    struct Reversed<E>(E, bool);
+
+    impl<'a, E: Expander<'a, Edge = GridEdge<'a>>> Expander<'a> for Reversed<E> {
+        type Edge = GridEdge<'a>;
+
+        fn expand(&mut self, node: NodeRef<'a>, edges: &mut Vec<GridEdge<'a>>) {
+            let first = edges.len();
+            self.0.expand(node, edges);
+            if self.1 {
+                edges[first..].reverse();
+            }
+        }
+    }
+
+    let mut map = BitGrid::new(12, 10);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, !(x == 6 && (2..8).contains(&y)));
+        }
+    }
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    for (start, target) in [((0, 0), (11, 9)), ((2, 5), (10, 5)), ((11, 0), (0, 9))] {
+        let mut paths = vec![];
+        for reversed in [false, true, false] {
+            pool.reset();
+            let path = astar.search_canonical(
+                Reversed(EightConnectedExpander::new(&map, &pool, state), reversed),
+                open_list_factory.new_queue(astar.ordering()),
+                |node| octile_distance(node.get(state), target),
+                |node| node.get(state) == target,
+                |node| node.get(state),
+                pool.generate(start),
+            );
+            let path: Vec<_> = path.unwrap().iter().map(|node| node.get(state)).collect();
+            paths.push(path);
+        }
+        assert_eq!(paths[0], paths[1]);
+        assert_eq!(paths[0], paths[2]);
+        assert_eq!(paths[0].first(), Some(&start));
+        assert_eq!(paths[0].last(), Some(&target));
+    }
+}
+
+#[test]
+fn degenerate_maps() {
+    use mkpath_grid::{distance_from, octile_distance, BitGrid, EightConnectedExpander};
+    use mkpath_grid_gb::{PartialCellBb, PartialCellCpd, ToppingPlus, TopsExpander};
+    use mkpath_hpa::{HpaStar, SectorGraph};
+    use mkpath_jps::{JpsExpander, JpsGrid, JpsPlusExpander, JumpDatabase};
+
+    // Empty maps, maps with a single cell, and maps a single cell wide.
+    let maps = [
+        (0, 0, false),
+        (0, 3, false),
+        (4, 0, false),
+        (1, 1, false),
+        (1, 1, true),
+        (1, 5, true),
+        (6, 1, true),
+    ];
+    for (width, height, open) in maps {
+        let mut map = BitGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                map.set(x, y, open);
+            }
+        }
+        assert_eq!(map.statistics().components, open as usize);
+
+        // Preprocessing and round trips of the preprocessed data all succeed.
+        let jps_grid = JpsGrid::new(map.clone());
+        let jump_db = JumpDatabase::new(&map);
+        let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+        let bb = PartialCellBb::compute(&map, &jump_db, |_, _, _| {});
+        let mut bytes = vec![];
+        cpd.save(&mut bytes).unwrap();
+        PartialCellCpd::load(&map, &mut &bytes[..]).unwrap();
+        bytes.clear();
+        bb.save(&mut bytes).unwrap();
+        PartialCellBb::load(&map, &mut &bytes[..]).unwrap();
+        let graph = SectorGraph::build(&map, 4);
+        let mut hpa = HpaStar::new(&map, &graph);
+        let mut topping_plus = ToppingPlus::new(&jump_db, &cpd);
+
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let start = (0, 0);
+        let target = (width - 1, height - 1);
+
+        if !open {
+            // Searches from or to an obstacle find nothing, even if the start is the target.
+            assert!(distance_from(&map, start)[target].is_infinite());
+            assert_eq!(hpa.get_path(start, target), None);
+            assert!(topping_plus.get_path(start, target).1.is_infinite());
+            continue;
+        }
+
+        let cost = octile_distance(start, target);
+        assert_eq!(distance_from(&map, start)[target], cost);
+        assert_eq!(hpa.get_path(start, target).unwrap().1, cost);
+        assert_eq!(topping_plus.get_path(start, target).1, cost);
+
+        let (mut searcher, mut pool) = TestSearcher::new(width, height);
+        let state = searcher.state;
+        let check = |name: &str, found: Option<(Vec<_>, f64)>| {
+            assert_eq!(found.unwrap().1, cost, "{name} on {width}x{height} map");
+        };
+
+        let expander = |pool| EightConnectedExpander::new(&map, pool, state);
+        check("A*", searcher.search(&mut pool, expander, start, target));
+        let expander = |pool| JpsExpander::from_grid(&jps_grid, pool, state, target);
+        check("JPS", searcher.search(&mut pool, expander, start, target));
+        let expander = |pool| JpsPlusExpander::new(&jump_db, pool, state, target);
+        check("JPS+", searcher.search(&mut pool, expander, start, target));
+        let expander = |pool| TopsExpander::new(&jump_db, &cpd, pool, state, target);
+        check("TOPS", searcher.search(&mut pool, expander, start, target));
+    }
+}
+
+#[test]
+fn algorithms_match_reference_cost() {
+    use mkpath_grid::{reference_cost, BitGrid};
+    use mkpath_grid_gb::{
+        JpsBbExpander, PartialCellBb, PartialCellCpd, ToppingPlusOracle, TopsExpander,
+    };
+    use mkpath_jps::{JpsExpander, JpsGrid, JpsPlusExpander, JumpDatabase};
+
+    let rooms = BitGrid::from_str_rows(
+        &[
+            "..........#.........", //
+            "..........#.........", //
+            "....#.....#....##...", //
+            "....#..........##...", //
+            "....#.....#.........", //
+            "######.####.#####.##", //
+            "..........#.........", //
+            "...##.....#...#.#...", //
+            "...##.........#.#...", //
+            "..........#.........", //
+        ],
+        &['.'],
+    );
+    let fixtures = [
+        rooms,
+        BitGrid::random_obstacles(32, 24, 0.2, 1),
+        BitGrid::random_obstacles(32, 24, 0.35, 2),
+        BitGrid::random_maze(21, 15, 5),
+    ];
+
+    for map in &fixtures {
+        let jps_grid = JpsGrid::new(map.clone());
+        let jump_db = JumpDatabase::new(map);
+        let cpd = PartialCellCpd::compute(map, &jump_db, |_, _, _| {});
+        let bb = PartialCellBb::compute(map, &jump_db, |_, _, _| {});
+        let oracle = ToppingPlusOracle::new(&jump_db, &cpd);
+
+        let cells: Vec<_> = (0..map.height())
+            .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.get(x, y))
+            .collect();
+        let queries: Vec<_> = cells
+            .iter()
+            .step_by(11)
+            .flat_map(|&start| cells.iter().step_by(13).map(move |&target| (start, target)))
+            .collect();
+        let topping_plus = oracle.query_batch(&queries);
+
+        let (mut searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+        let state = searcher.state;
+
+        for (&(start, target), (_, topping_cost)) in queries.iter().zip(&topping_plus) {
+            let expected = reference_cost(map, start, target);
+            let check = |name: &str, found: Option<f64>| match (found, expected) {
+                (Some(found), Some(expected)) => assert!(
+                    (found - expected).abs() < 1e-9,
+                    "{name} from {start:?} to {target:?}: {found} vs {expected}"
+                ),
+                _ => assert_eq!(found, expected, "{name} from {start:?} to {target:?}"),
+            };
+
+            let expander = |pool| JpsExpander::from_grid(&jps_grid, pool, state, target);
+            let found = searcher.search(&mut pool, expander, start, target);
+            check("JPS", found.map(|(_, cost)| cost));
+            let expander = |pool| JpsPlusExpander::new(&jump_db, pool, state, target);
+            let found = searcher.search(&mut pool, expander, start, target);
+            check("JPS+", found.map(|(_, cost)| cost));
+            let expander = |pool| TopsExpander::new(&jump_db, &cpd, pool, state, target);
+            let found = searcher.search(&mut pool, expander, start, target);
+            check("TOPS", found.map(|(_, cost)| cost));
+            let expander = |pool| JpsBbExpander::new(&jump_db, &bb, pool, state, target);
+            let found = searcher.search(&mut pool, expander, start, target);
+            check("JPS+BB+", found.map(|(_, cost)| cost));
+            let topping_cost = Some(*topping_cost).filter(|cost| cost.is_finite());
+            check("Topping+", topping_cost);
+        }
+    }
+}
+
+#[test]
+fn trivial_and_adjacent_queries() {
+    use mkpath_grid::{BitGrid, EightConnectedExpander, SAFE_SQRT_2};
+    use mkpath_grid_gb::{PartialCellCpd, ToppingPlus};
+    use mkpath_jps::{JpsExpander, JpsGrid, JpsPlusExpander, JumpDatabase};
+
+    let mut map = BitGrid::new(10, 8);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, !(x == 5 && y > 1) && (x, y) != (2, 5));
+        }
+    }
+    let jps_grid = JpsGrid::new(map.clone());
+    let jump_db = JumpDatabase::new(&map);
+    let cpd = PartialCellCpd::compute(&map, &jump_db, |_, _, _| {});
+    let mut topping_plus = ToppingPlus::new(&jump_db, &cpd);
+
+    let (mut searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let state = searcher.state;
+
+    let cases = [
+        ((3, 3), (3, 3), 0.0),
+        ((0, 0), (0, 0), 0.0),
+        ((3, 3), (4, 3), 1.0),
+        ((3, 3), (3, 2), 1.0),
+        ((3, 3), (4, 4), SAFE_SQRT_2),
+        ((6, 1), (5, 0), SAFE_SQRT_2),
+        // Adjacent along a wall and next to an obstacle.
+        ((4, 4), (4, 5), 1.0),
+        ((1, 4), (2, 4), 1.0),
+    ];
+
+    for (start, target, cost) in cases {
+        let expected: Vec<_> = if start == target {
+            vec![start]
+        } else {
+            vec![start, target]
+        };
+
+        let check = |name: &str, path: Vec<(i32, i32)>, found: f64| {
+            assert_eq!(path, expected, "{name} from {start:?} to {target:?}");
+            assert_eq!(found, cost, "{name} from {start:?} to {target:?}");
+        };
+
+        let expander = |pool| EightConnectedExpander::new(&map, pool, state);
+        let (path, found) = searcher.search(&mut pool, expander, start, target).unwrap();
+        check("A*", path, found);
+        let expander = |pool| JpsExpander::from_grid(&jps_grid, pool, state, target);
+        let (path, found) = searcher.search(&mut pool, expander, start, target).unwrap();
+        check("JPS", path, found);
+        let expander = |pool| JpsPlusExpander::new(&jump_db, pool, state, target);
+        let (path, found) = searcher.search(&mut pool, expander, start, target).unwrap();
+        check("JPS+", path, found);
+        let (path, found) = topping_plus.get_path(start, target);
+        check("Topping+", path, found);
+    }
+}
+
+#[test]
+fn search_stats_reflect_pruning() {
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedExpander};
+    use mkpath_jps::{JpsGrid, JpsExpander};
+
+    let mut map = BitGrid::new(20, 20);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, x != 10 || y == 17);
+        }
+    }
+    let jps_grid = JpsGrid::new(map.clone());
+
+    let (searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let TestSearcher {
+        state,
+        mut astar,
+        mut open_list_factory,
+    } = searcher;
+
+    let (start, target) = ((2, 3), (18, 4));
+
+    let mut a_star_stats = SearchStats::default();
+    pool.reset();
+    let a_star_path = astar
+        .search_with_stats(
+            EightConnectedExpander::new(&map, &pool, state),
+            open_list_factory.new_queue(astar.ordering()),
+            |node| octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            pool.generate(start),
+            &mut a_star_stats,
+        )
+        .unwrap();
+    let a_star_cost = a_star_path.last().unwrap().get(astar.g());
+
+    let mut jps_stats = SearchStats::default();
+    pool.reset();
+    let jps_path = astar
+        .search_with_stats(
+            JpsExpander::from_grid(&jps_grid, &pool, state, target),
+            open_list_factory.new_queue(astar.ordering()),
+            |node| octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            pool.generate(start),
+            &mut jps_stats,
+        )
+        .unwrap();
+    assert_eq!(jps_path.last().unwrap().get(astar.g()), a_star_cost);
+
+    for stats in [a_star_stats, jps_stats] {
+        assert!(stats.expanded > 0);
+        assert!(stats.generated >= stats.expanded);
+        assert!(stats.peak_open > 0);
+    }
+    assert!(jps_stats.expanded < a_star_stats.expanded);
+    assert!(jps_stats.generated < a_star_stats.generated);
+
+    // Nodes are left on the open list when the goal is found, which doesn't affect equality.
+    assert_ne!(a_star_stats.open, 0);
+    let cleared = SearchStats {
+        open: 0,
+        ..a_star_stats
+    };
+    assert_eq!(cleared, a_star_stats);
+
+    // Statistics accumulate over searches.
+    let mut total = a_star_stats;
+    pool.reset();
+    astar.search_with_stats(
+        EightConnectedExpander::new(&map, &pool, state),
+        open_list_factory.new_queue(astar.ordering()),
+        |node| octile_distance(node.get(state), target),
+        |node| node.get(state) == target,
+        pool.generate(start),
+        &mut total,
+    );
+    assert_eq!(total.expanded, 2 * a_star_stats.expanded);
+    assert_eq!(total.generated, 2 * a_star_stats.generated);
+    assert_eq!(total.peak_open, a_star_stats.peak_open);
+}
+
+#[test]
+fn custom_orderings_stay_optimal() {
+    use std::cmp::Reverse;
+
+    use mkpath_grid::{BitGrid, EightConnectedExpander};
+
+    let mut map = BitGrid::new(16, 12);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            map.set(x, y, (x != 6 || y > 8) && (y != 4 || !(3..=12).contains(&x)));
+        }
+    }
+
+    let (mut searcher, mut pool) = TestSearcher::new(map.width(), map.height());
+    let state = searcher.state;
+    let (f, g, h) = (searcher.astar.f(), searcher.astar.g(), searcher.astar.h());
+
+    let (start, target) = ((1, 1), (14, 10));
+    let (_, default) = searcher
+        .search(
+            &mut pool,
+            |pool| EightConnectedExpander::new(&map, pool, state),
+            start,
+            target,
+        )
+        .unwrap();
+    let (_, cost) = searcher
+        .search_with_ordering(
+            (f, g),
+            &mut pool,
+            |pool| EightConnectedExpander::new(&map, pool, state),
+            start,
+            target,
+        )
+        .unwrap();
+    assert_eq!(cost, default);
+    let (_, cost) = searcher
+        .search_with_ordering(
+            (f, Reverse(g)),
+            &mut pool,
+            |pool| EightConnectedExpander::new(&map, pool, state),
+            start,
+            target,
+        )
+        .unwrap();
+    assert_eq!(cost, default);
+    let (_, cost) = searcher
+        .search_with_ordering(
+            (f, Reverse(h)),
+            &mut pool,
+            |pool| EightConnectedExpander::new(&map, pool, state),
+            start,
+            target,
+        )
+        .unwrap();
+    assert_eq!(cost, default);
 }
@@ -0,0 +1,57 @@
+/// Statistics collected by [`AStarSearcher::search_with_stats`](crate::AStarSearcher::search_with_stats).
+///
+/// These are the measures of search effort commonly reported in the pathfinding literature, and
+/// unlike running times they do not depend on the machine. With JPS-based expanders, each
+/// generated node is a jump point, so the counts are much lower than those of a plain 8-connected
+/// search over the same map.
+#[derive(Clone, Copy, Debug, Default, Eq)]
+pub struct SearchStats {
+    /// Number of nodes expanded.
+    pub expanded: usize,
+    /// Number of edges produced by the expander, including those which did not improve the
+    /// successor's `g` value.
+    pub generated: usize,
+    /// Largest number of nodes in the open list at once.
+    ///
+    /// This assumes that nodes are not re-expanded, which holds for consistent heuristics.
+    pub peak_open: usize,
+    pub(crate) open: usize,
+}
+
+/// Compares the public counters only; the size of the open list is scratch space for the search.
+impl PartialEq for SearchStats {
+    fn eq(&self, other: &Self) -> bool {
+        self.expanded == other.expanded
+            && self.generated == other.generated
+            && self.peak_open == other.peak_open
+    }
+}
+
+/// Receives events from the search loop. Implemented as no-ops for `()` so that searches without
+/// statistics compile to the same code as before.
+pub(crate) trait StatsRecorder {
+    fn opened(&mut self);
+
+    fn expanded(&mut self, edges: usize);
+}
+
+impl StatsRecorder for () {
+    #[inline(always)]
+    fn opened(&mut self) {}
+
+    #[inline(always)]
+    fn expanded(&mut self, _edges: usize) {}
+}
+
+impl StatsRecorder for SearchStats {
+    fn opened(&mut self) {
+        self.open += 1;
+        self.peak_open = self.peak_open.max(self.open);
+    }
+
+    fn expanded(&mut self, edges: usize) {
+        self.open = self.open.saturating_sub(1);
+        self.expanded += 1;
+        self.generated += edges;
+    }
+}
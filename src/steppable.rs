@@ -0,0 +1,197 @@
+use std::task::Poll;
+
+use mkpath_core::traits::{Cost, CostValue, Expander, OpenList, Successor};
+use mkpath_core::{NodeMemberPointer, NodeRef};
+
+use crate::stats::StatsRecorder;
+use crate::{path_to, AStarSearcher};
+
+/// An A* search which can be run a few expansions at a time, created by
+/// [`AStarSearcher::search_steppable`].
+///
+/// This is useful when a search must not block for long, e.g. in a game loop which budgets a
+/// fixed number of expansions per frame. The open list and other search state are kept between
+/// calls to [`SteppableAStar::step_n`]; the node pool is borrowed for the whole search, so it must
+/// not be reset until the search is finished or abandoned.
+pub struct SteppableAStar<'a, Exp: Expander<'a>, Open, H, G, C = f64> {
+    g: NodeMemberPointer<C>,
+    h: NodeMemberPointer<C>,
+    f: NodeMemberPointer<C>,
+    expander: Exp,
+    open_list: Open,
+    heuristic: H,
+    goal_test: G,
+    edges: Vec<Exp::Edge>,
+    finished: bool,
+}
+
+impl<'a, Exp, Open, H, G, C, Edge> SteppableAStar<'a, Exp, Open, H, G, C>
+where
+    C: CostValue,
+    Exp: Expander<'a, Edge = Edge>,
+    Edge: Successor<'a> + Cost,
+    Open: OpenList<'a>,
+    H: FnMut(NodeRef<'a>) -> f64,
+    G: FnMut(NodeRef<'a>) -> bool,
+{
+    pub(crate) fn new(
+        searcher: &AStarSearcher<C>,
+        expander: Exp,
+        mut open_list: Open,
+        mut heuristic: H,
+        goal_test: G,
+        start: NodeRef<'a>,
+    ) -> Self {
+        let (g, h, f) = (searcher.g(), searcher.h(), searcher.f());
+
+        start.set(g, C::ZERO);
+        start.set(h, C::from_f64(heuristic(start)));
+        start.set(f, start.get(h));
+        open_list.relaxed(start);
+        let edges = Vec::with_capacity(expander.max_degree());
+
+        SteppableAStar {
+            g,
+            h,
+            f,
+            expander,
+            open_list,
+            heuristic,
+            goal_test,
+            edges,
+            finished: false,
+        }
+    }
+
+    /// Continues the search for at most `n` expansions.
+    ///
+    /// Returns `Poll::Pending` if the search is not finished yet, otherwise the path from the
+    /// start to the goal, or `None` if there is none, like [`AStarSearcher::search`]. Once the
+    /// search has finished, further calls return `Poll::Ready(None)`.
+    pub fn step_n(&mut self, n: usize) -> Poll<Option<Vec<NodeRef<'a>>>> {
+        self.step_impl(n, &mut ()).map(|goal| goal.map(path_to))
+    }
+
+    /// Returns whether the search has finished, i.e. [`SteppableAStar::step_n`] returned
+    /// `Poll::Ready`.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub(crate) fn step_impl(
+        &mut self,
+        n: usize,
+        stats: &mut impl StatsRecorder,
+    ) -> Poll<Option<NodeRef<'a>>> {
+        let SteppableAStar { g, h, f, .. } = *self;
+
+        if self.finished {
+            return Poll::Ready(None);
+        }
+
+        for _ in 0..n {
+            let Some(node) = self.open_list.next() else {
+                self.finished = true;
+                return Poll::Ready(None);
+            };
+
+            if (self.goal_test)(node) {
+                self.finished = true;
+                return Poll::Ready(Some(node));
+            }
+
+            self.edges.clear();
+            self.expander.expand(node, &mut self.edges);
+            stats.expanded(self.edges.len());
+
+            let node_g = node.get(g).to_f64();
+
+            for edge in &self.edges {
+                let successor = edge.successor();
+                let new_g = C::from_f64(node_g + edge.cost());
+                if new_g < successor.get(g) {
+                    if successor.get(h).to_f64().is_nan() {
+                        successor.set(h, C::from_f64((self.heuristic)(successor)));
+                        stats.opened();
+                    }
+                    successor.set(g, new_g);
+                    successor.set(f, C::from_f64(new_g.to_f64() + successor.get(h).to_f64()));
+                    successor.set_parent(Some(node));
+                    self.open_list.relaxed(successor);
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[test]
+fn stepping_finds_the_same_paths() {
+    use mkpath_core::{NodeBuilder, PriorityQueueFactory};
+    use mkpath_grid::{octile_distance, BitGrid, EightConnectedExpander, GridPool};
+
+    let map = BitGrid::from_str_rows(
+        &[
+            "..........",
+            ".@@@@@@@@.",
+            "........@.",
+            "@@@@@@.@@.",
+            "..........",
+        ],
+        &['.'],
+    );
+
+    let mut builder = NodeBuilder::new();
+    let state = builder.add_field((-1, -1));
+    let mut astar = AStarSearcher::new(&mut builder);
+    let mut open_list_factory = PriorityQueueFactory::new(&mut builder);
+    let mut pool = GridPool::new(builder.build(), state, map.width(), map.height());
+
+    for (start, target) in [((0, 2), (0, 4)), ((4, 2), (9, 4)), ((5, 0), (5, 2))] {
+        let expected = astar
+            .search_reset(
+                &mut pool,
+                |pool| EightConnectedExpander::new(&map, pool, state),
+                open_list_factory.new_queue(astar.ordering()),
+                |node| octile_distance(node.get(state), target),
+                |node| node.get(state) == target,
+                start,
+            )
+            .map(|path| path.iter().map(|node| node.get(state)).collect::<Vec<_>>());
+
+        pool.reset();
+        let mut search = astar.search_steppable(
+            EightConnectedExpander::new(&map, &pool, state),
+            open_list_factory.new_queue(astar.ordering()),
+            |node| octile_distance(node.get(state), target),
+            |node| node.get(state) == target,
+            pool.generate(start),
+        );
+        let mut steps = 0;
+        let found = loop {
+            if let Poll::Ready(path) = search.step_n(3) {
+                break path;
+            }
+            steps += 1;
+        };
+        let found = found.map(|path| path.iter().map(|node| node.get(state)).collect::<Vec<_>>());
+
+        assert_eq!(found, expected);
+        assert!(steps > 0);
+        assert!(search.is_finished());
+        assert!(matches!(search.step_n(3), Poll::Ready(None)));
+    }
+
+    // The search makes no progress with a budget of 0.
+    pool.reset();
+    let mut search = astar.search_steppable(
+        EightConnectedExpander::new(&map, &pool, state),
+        open_list_factory.new_queue(astar.ordering()),
+        |_| 0.0,
+        |node| node.get(state) == (0, 0),
+        pool.generate((0, 0)),
+    );
+    assert!(search.step_n(0).is_pending());
+    assert!(search.step_n(1).is_ready());
+}